@@ -3,6 +3,7 @@
 //! Usage:
 //!   titanbash                  Interactive shell
 //!   titanbash -c "command"     Execute single command
+//!   titanbash --explain -c "command"  Show how a command would be dispatched
 //!   titanbash script.titan     Execute script file
 
 use std::env;
@@ -17,6 +18,8 @@ use titan_bash::shell::input::{CrosstermInput, InputResult, normalize_pasted_lin
 use titan_bash::shell::parser;
 use titan_bash::shell::path as shell_path;
 use titan_bash::shell::busybox;
+use titan_bash::shell::executor;
+use titan_bash::shell::history;
 
 #[cfg(windows)]
 mod ctrlc {
@@ -183,6 +186,15 @@ fn main() -> Result<()> {
                 let code = execute_command(&cmd)?;
                 std::process::exit(code);
             }
+            "--explain" => {
+                if args.len() < 4 || args[2] != "-c" {
+                    eprintln!("titanbash: --explain requires -c \"command\"");
+                    std::process::exit(1);
+                }
+                let cmd = args[3..].join(" ");
+                let code = explain_command(&cmd)?;
+                std::process::exit(code);
+            }
             "-h" | "--help" => {
                 print_help();
                 return Ok(());
@@ -216,6 +228,8 @@ fn print_help() {
     println!("  titanbash                  Start interactive shell");
     println!("  titanbash -c \"command\"     Execute single command");
     println!("  titanbash script.titan     Execute script file");
+    println!("  titanbash --explain -c \"command\"");
+    println!("                             Show how a command would be dispatched");
     println!("  titanbash -h, --help       Show this help");
     println!("  titanbash -v, --version    Show version");
     println!();
@@ -240,6 +254,20 @@ fn execute_command(cmd: &str) -> Result<i32> {
     }
 }
 
+fn explain_command(cmd: &str) -> Result<i32> {
+    let mut shell = Shell::new()?;
+    load_titanbashrc(&mut shell);
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match executor::explain(&mut shell, cmd, &mut out) {
+        Ok(code) => Ok(code),
+        Err(e) => {
+            eprintln!("{}: {}", "error".red(), e);
+            Ok(1)
+        }
+    }
+}
+
 fn execute_script(path: &str, script_args: &[String]) -> Result<i32> {    
     let cwd = env::current_dir()?;
     let resolved = shell_path::resolve_fs(&cwd, path);
@@ -381,45 +409,11 @@ fn print_banner() {
     println!();
 }
 
-fn escape_history_line(s: &str) -> String {
-    let mut out = String::with_capacity(s.len());
-    for c in s.chars() {
-        match c {
-            '\\' => out.push_str("\\\\"),
-            '\n' => out.push_str("\\n"),
-            '\r' => out.push_str("\\r"),
-            _ => out.push(c),
-        }
-    }
-    out
-}
-
-fn unescape_history_line(s: &str) -> String {
-    let mut out = String::with_capacity(s.len());
-    let mut chars = s.chars();
-    while let Some(c) = chars.next() {
-        if c != '\\' {
-            out.push(c);
-            continue;
-        }
-        match chars.next() {
-            Some('n') => out.push('\n'),
-            Some('r') => out.push('\r'),
-            Some('\\') => out.push('\\'),
-            Some(other) => {
-                out.push('\\');
-                out.push(other);
-            }
-            None => out.push('\\'),
-        }
-    }
-    out
-}
-
 fn run_repl(is_double_click: bool) -> Result<i32> {
     print_banner();
 
     let mut shell = Shell::new()?;
+    shell.interactive = true;
     load_titanbashrc(&mut shell);
     let mut input = CrosstermInput::new(shell.cwd.clone());
 
@@ -446,7 +440,7 @@ fn run_repl(is_double_click: bool) -> Result<i32> {
         let mut entries: Vec<String> = reader
             .lines()
             .filter_map(|l| l.ok())
-            .map(|l| unescape_history_line(&l))
+            .map(|l| history::unescape_line(&l))
             .collect();
         const MAX_HISTORY: usize = 5000;
         // Dedup history: keep last occurrence of each command
@@ -517,17 +511,36 @@ fn run_repl(is_double_click: bool) -> Result<i32> {
                     continue;
                 }
 
-                let full_input = input_buffer.trim();
+                let mut full_input = input_buffer.trim().to_string();
                 if full_input.is_empty() {
                     input_buffer.clear();
                     continue;
                 }
 
+                // History expansion (`!!`, `!$`, `^old^new`, ...) before the line is recorded.
+                if shell.options.histexpand && (full_input.contains('!') || full_input.starts_with('^')) {
+                    let entries = history::load_file(&history_path)
+                        .unwrap_or_else(|_| input.history_entries().to_vec());
+                    match history::expand(&full_input, &entries) {
+                        Ok(Some(expanded)) => {
+                            println!("{}", expanded);
+                            full_input = expanded;
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            eprintln!("titanbash: {}", e);
+                            input_buffer.clear();
+                            continue;
+                        }
+                    }
+                }
+                let full_input = full_input.as_str();
+
                 // Execute and add to history
                 input.add_history(full_input.to_string());
                 if last_written.as_deref() != Some(full_input) {
                     if let Some(w) = history_writer.as_mut() {
-                        let _ = writeln!(w, "{}", escape_history_line(full_input));
+                        let _ = writeln!(w, "{}", history::escape_line(full_input));
                         let _ = w.flush();
                     }
                     last_written = Some(full_input.to_string());
@@ -583,7 +596,7 @@ fn run_repl(is_double_click: bool) -> Result<i32> {
                     input.add_history(cmd.to_string());
                     if last_written.as_deref() != Some(cmd) {
                         if let Some(w) = history_writer.as_mut() {
-                            let _ = writeln!(w, "{}", escape_history_line(cmd));
+                            let _ = writeln!(w, "{}", history::escape_line(cmd));
                             let _ = w.flush();
                         }
                         last_written = Some(cmd.to_string());
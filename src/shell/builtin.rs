@@ -18,26 +18,46 @@ use sha1::Sha1;
 
 use super::Shell;
 use super::path;
+use super::printf;
+use super::stat;
 use super::parser::split_args;
 use super::busybox;
+use super::cond;
+use super::encoding::{self, Encoding, LineEnding};
+use super::executor;
+use super::find;
+use super::parser;
+use super::frecency::{self, DirDb};
+use super::fsutil::{
+    copy_entry, file_attributes, move_entry, ATTR_ARCHIVE, ATTR_DIRECTORY, ATTR_HIDDEN, ATTR_READONLY,
+    ATTR_REPARSE_POINT, ATTR_SYSTEM,
+};
+use super::guard;
+use super::input::RawRead;
+use super::link::{self, LinkKind};
+use super::trash::{self, TrashStore};
 use super::venv;
+use super::walk;
+use super::watch;
+use crate::task::signal::{self, Signal};
 use crate::task::{TaskId, TaskStatus};
 
 /// Builtins that affect shell state (must run in main process)
 const STATE_BUILTINS: &[&str] = &[
-    "cd", "z", "export", "set", "alias", "unalias", "activate", "deactivate", "exit", "quit", "fg", "wait", "kill",
+    "cd", "z", "zi", "pushd", "popd", "dirs", "export", "unset", "readonly", "declare", "set", "alias", "unalias", "activate", "deactivate", "exit", "quit", "fg", "bg", "wait", "kill", "disown", "nohup",
 ];
 
 /// All builtin command names
 const ALL_BUILTINS: &[&str] = &[
-    "cd", "z", "pwd", "ls", "dir", "cat", "type", "echo",
-    "clear", "cls", "exit", "quit", "help", "jobs",
-    "export", "set", "env", "printenv",
+    "cd", "z", "zi", "pushd", "popd", "dirs", "pwd", "ls", "dir", "tree", "du", "ff", "cat", "type", "command", "echo", "printf",
+    "test", "[", "clear", "cls", "exit", "quit", "help", "jobs",
+    "export", "unset", "readonly", "declare", "set", "env", "printenv",
     "alias", "unalias", "which", "where", "mkdir", "rm",
-    "del", "cp", "copy", "mv", "move", "touch", "history",
+    "del", "cp", "copy", "mv", "move", "ln", "touch", "stat", "history",
     "head", "tail", "whoami", "hostname",
     "md5sum", "sha1sum", "sha256sum", "sha512sum",
-    "activate", "deactivate", "fg", "wait", "kill",
+    "activate", "deactivate", "fg", "bg", "wait", "kill", "disown", "nohup",
+    "explain", "watch", "transcode", "trash", "read",
 ];
 
 pub fn is_builtin(name: &str) -> bool {
@@ -133,8 +153,20 @@ pub fn try_builtin(shell: &mut Shell, cmd: &str) -> Result<Option<i32>> {
             let code = builtin_cd(shell, &rest)?;
             Ok(Some(code))
         }
-        "z" => {
-            let code = builtin_z(shell, &rest)?;
+        "z" | "zi" => {
+            let code = builtin_z(shell, &rest, command == "zi")?;
+            Ok(Some(code))
+        }
+        "pushd" => {
+            let code = builtin_pushd(shell, &rest)?;
+            Ok(Some(code))
+        }
+        "popd" => {
+            let code = builtin_popd(shell, &rest)?;
+            Ok(Some(code))
+        }
+        "dirs" => {
+            let code = builtin_dirs(shell, &rest)?;
             Ok(Some(code))
         }
         "pwd" => {
@@ -145,14 +177,42 @@ pub fn try_builtin(shell: &mut Shell, cmd: &str) -> Result<Option<i32>> {
             let code = builtin_ls(shell, &rest)?;
             Ok(Some(code))
         }
-        "cat" | "type" => {
+        "tree" => {
+            let code = builtin_tree(shell, &rest)?;
+            Ok(Some(code))
+        }
+        "du" => {
+            let code = builtin_du(shell, &rest)?;
+            Ok(Some(code))
+        }
+        "ff" => {
+            let code = builtin_ff(shell, &rest)?;
+            Ok(Some(code))
+        }
+        "cat" => {
             let code = builtin_cat(shell, &rest)?;
             Ok(Some(code))
         }
+        "type" => {
+            let code = builtin_type(shell, &rest)?;
+            Ok(Some(code))
+        }
+        "command" => {
+            let code = builtin_command(shell, &rest)?;
+            Ok(Some(code))
+        }
         "echo" => {
             let code = builtin_echo(&rest)?;
             Ok(Some(code))
         }
+        "printf" => {
+            let code = builtin_printf(shell, &rest)?;
+            Ok(Some(code))
+        }
+        "test" | "[" => {
+            let code = builtin_test(shell, &command, &rest)?;
+            Ok(Some(code))
+        }
         "clear" | "cls" => {
             let code = builtin_clear()?;
             Ok(Some(code))
@@ -179,8 +239,24 @@ pub fn try_builtin(shell: &mut Shell, cmd: &str) -> Result<Option<i32>> {
             let code = builtin_jobs(shell)?;
             Ok(Some(code))
         }
-        "export" | "set" => {
-            let code = builtin_export(&rest)?;
+        "export" => {
+            let code = builtin_export(shell, &rest)?;
+            Ok(Some(code))
+        }
+        "unset" => {
+            let code = builtin_unset(shell, &rest)?;
+            Ok(Some(code))
+        }
+        "readonly" => {
+            let code = builtin_readonly(shell, &rest)?;
+            Ok(Some(code))
+        }
+        "declare" => {
+            let code = builtin_declare(shell, &rest)?;
+            Ok(Some(code))
+        }
+        "set" => {
+            let code = builtin_set(shell, &rest)?;
             Ok(Some(code))
         }
         "env" | "printenv" => {
@@ -215,10 +291,18 @@ pub fn try_builtin(shell: &mut Shell, cmd: &str) -> Result<Option<i32>> {
             let code = builtin_mv(shell, &rest)?;
             Ok(Some(code))
         }
+        "ln" => {
+            let code = builtin_ln(shell, &rest)?;
+            Ok(Some(code))
+        }
         "touch" => {
             let code = builtin_touch(shell, &rest)?;
             Ok(Some(code))
         }
+        "stat" => {
+            let code = builtin_stat(shell, &rest)?;
+            Ok(Some(code))
+        }
         "history" => {
             let code = builtin_history(&rest)?;
             Ok(Some(code))
@@ -267,6 +351,18 @@ pub fn try_builtin(shell: &mut Shell, cmd: &str) -> Result<Option<i32>> {
             let code = builtin_kill(shell, &rest)?;
             Ok(Some(code))
         }
+        "bg" => {
+            let code = builtin_bg(shell, &rest)?;
+            Ok(Some(code))
+        }
+        "disown" => {
+            let code = builtin_disown(shell, &rest)?;
+            Ok(Some(code))
+        }
+        "nohup" => {
+            let code = builtin_nohup(shell, &rest)?;
+            Ok(Some(code))
+        }
         "activate" => {
             let code = builtin_activate(shell, &rest)?;
             Ok(Some(code))
@@ -275,11 +371,35 @@ pub fn try_builtin(shell: &mut Shell, cmd: &str) -> Result<Option<i32>> {
             let code = builtin_deactivate(shell)?;
             Ok(Some(code))
         }
+        "explain" => {
+            let code = builtin_explain(shell, &rest)?;
+            Ok(Some(code))
+        }
+        "watch" => {
+            let code = builtin_watch(shell, &rest)?;
+            Ok(Some(code))
+        }
+        "transcode" => {
+            let code = builtin_transcode(shell, &rest)?;
+            Ok(Some(code))
+        }
+        "trash" => {
+            let code = builtin_trash(&rest)?;
+            Ok(Some(code))
+        }
+        "read" => {
+            let code = builtin_read(shell, &rest)?;
+            Ok(Some(code))
+        }
         _ => Ok(None),
     }
 }
 
 /// Run builtin with explicit stdin/stdout/stderr streams.
+///
+/// `stdin_tty` lets `read` use the console directly (`-s`, `-t`, `-n`); `stdout_tty` tells
+/// formatting builtins (`ls`) that stdout is the console.
+#[allow(clippy::too_many_arguments)]
 pub fn run_builtin_io(
     shell: &mut Shell,
     name: &str,
@@ -287,17 +407,29 @@ pub fn run_builtin_io(
     stdin: &mut dyn BufRead,
     stdout: &mut dyn Write,
     stderr: &mut dyn Write,
+    stdin_tty: bool,
+    stdout_tty: bool,
 ) -> Result<i32> {
     let args_ref: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
     let lower = name.to_ascii_lowercase();
 
     match lower.as_str() {
-        "cd" => builtin_cd(shell, &args_ref),
-        "z" => builtin_z(shell, &args_ref),
+        "cd" => builtin_cd_impl(shell, &args_ref, stdout),
+        "z" | "zi" => builtin_z_impl(shell, &args_ref, lower == "zi", stdin, stdout, stderr),
+        "pushd" => builtin_pushd_impl(shell, &args_ref, stdout),
+        "popd" => builtin_popd_impl(shell, &args_ref, stdout),
+        "dirs" => builtin_dirs_impl(shell, &args_ref, stdout),
         "pwd" => builtin_pwd_impl(shell, stdout),
-        "ls" | "dir" => builtin_ls_impl(shell, &args_ref, stdout, stderr),
-        "cat" | "type" => builtin_cat_impl(shell, &args_ref, stdin, stdout, stderr),
+        "ls" | "dir" => builtin_ls_impl(shell, &args_ref, stdout_tty, stdout, stderr),
+        "tree" => builtin_tree_impl(shell, &args_ref, stdout_tty, stdout, stderr),
+        "du" => builtin_du_impl(shell, &args_ref, stdout, stderr),
+        "ff" => builtin_ff_impl(shell, &args_ref, stdout, stderr),
+        "cat" => builtin_cat_impl(shell, &args_ref, stdin, stdout, stderr),
+        "type" => builtin_type_impl(shell, &args_ref, stdin, stdout, stderr),
+        "command" => builtin_command_impl(shell, &args_ref, stdin, stdout, stderr),
         "echo" => builtin_echo_impl(&args_ref, stdout),
+        "printf" => builtin_printf_impl(shell, &args_ref, stdout, stderr),
+        "test" | "[" => builtin_test_impl(shell, name, &args_ref, stderr),
         "clear" | "cls" => builtin_clear_impl(stdout),
         "exit" | "quit" => {
             shell.should_exit = true;
@@ -305,7 +437,11 @@ pub fn run_builtin_io(
         }
         "help" => builtin_help_impl(stdout),
         "jobs" => builtin_jobs_impl(shell, stdout),
-        "export" | "set" => builtin_export_impl(&args_ref, stdout),
+        "export" => builtin_export_impl(shell, &args_ref, stdout),
+        "unset" => builtin_unset_impl(shell, &args_ref, stderr),
+        "readonly" => builtin_readonly_impl(shell, &args_ref, stdout),
+        "declare" => builtin_declare_impl(shell, &args_ref, stdout, stderr),
+        "set" => builtin_set_impl(shell, &args_ref, stdout),
         "env" | "printenv" => builtin_env_impl(&args_ref, stdout),
         "alias" => builtin_alias_impl(shell, &args_ref, stdout),
         "unalias" => builtin_unalias(shell, &args_ref),
@@ -314,9 +450,11 @@ pub fn run_builtin_io(
         "which" | "where" => builtin_which_impl(&args_ref, stdout),
         "mkdir" => builtin_mkdir(shell, &args_ref),
         "rm" | "del" => builtin_rm(shell, &args_ref),
-        "cp" | "copy" => builtin_cp(shell, &args_ref),
-        "mv" | "move" => builtin_mv(shell, &args_ref),
+        "cp" | "copy" => builtin_cp_impl(shell, &args_ref, stdin, stdout, stderr),
+        "mv" | "move" => builtin_mv_impl(shell, &args_ref, stdin, stdout, stderr),
+        "ln" => builtin_ln_impl(shell, &args_ref, stdout, stderr),
         "touch" => builtin_touch(shell, &args_ref),
+        "stat" => builtin_stat_impl(shell, &args_ref, stdout, stderr),
         "history" => builtin_history_impl(&args_ref, stdout),
         "head" => builtin_head_impl(shell, &args_ref, stdin, stdout),
         "tail" => builtin_tail_impl(shell, &args_ref, stdin, stdout),
@@ -328,7 +466,15 @@ pub fn run_builtin_io(
         "sha512sum" => builtin_checksum_impl(HashKind::Sha512, shell, &args_ref, stdin, stdout, stderr),
         "fg" => builtin_fg(shell, &args_ref),
         "wait" => builtin_wait(shell, &args_ref),
-        "kill" => builtin_kill(shell, &args_ref),
+        "kill" => builtin_kill_impl(shell, &args_ref, stdout, stderr),
+        "bg" => builtin_bg_impl(shell, &args_ref, stderr),
+        "disown" => builtin_disown(shell, &args_ref),
+        "nohup" => builtin_nohup(shell, &args_ref),
+        "explain" => builtin_explain_impl(shell, &args_ref, stdout),
+        "watch" => builtin_watch_impl(shell, &args_ref, stdout_tty, stdout),
+        "transcode" => builtin_transcode_impl(shell, &args_ref, stdin, stdout),
+        "trash" => builtin_trash_impl(&TrashStore::open()?, &args_ref, stdout),
+        "read" => builtin_read_impl(shell, &args_ref, stdin, stderr, stdin_tty),
         _ => Err(anyhow::anyhow!("Unknown builtin: {}", name)),
     }
 }
@@ -343,19 +489,36 @@ pub fn run_builtin_captured(
     let mut stdin = BufReader::new(io::empty());
     let stderr_handle = io::stderr();
     let mut stderr = stderr_handle.lock();
-    run_builtin_io(shell, name, args, &mut stdin, output, &mut stderr)
+    run_builtin_io(shell, name, args, &mut stdin, output, &mut stderr, false, false)
 }
 
 /// cd - change directory
-fn builtin_cd(shell: &mut Shell, args: &[&str]) -> Result<i32> {
-    let target = if args.is_empty() {
+fn builtin_cd_impl(shell: &mut Shell, args: &[&str], out: &mut dyn Write) -> Result<i32> {
+    let target = match args.first() {
         // cd with no args goes to home
-        dirs::home_dir().unwrap_or_else(|| shell.cwd.clone())
-    } else {
-        // Normalize and resolve the path
-        let raw_path = args[0];
-        let expanded = path::expand_env(raw_path);
-        path::resolve_fs(&shell.cwd, &expanded)
+        None => dirs::home_dir().unwrap_or_else(|| shell.cwd.clone()),
+        Some(&"-") => {
+            let Some(old) = env::var_os("OLDPWD") else {
+                anyhow::bail!("cd: OLDPWD not set");
+            };
+            std::path::PathBuf::from(old)
+        }
+        Some(raw_path) => {
+            // Normalize and resolve the path
+            let expanded = path::expand_env(raw_path);
+            path::resolve_fs(&shell.cwd, &expanded)
+        }
+    };
+
+    // Relative names that are not below the current directory are looked up in $CDPATH.
+    let (target, from_cdpath) = match args.first() {
+        Some(raw_path) if !target.is_dir() && *raw_path != "-" => {
+            match path::cdpath_lookup(&path::expand_env(raw_path)) {
+                Some(found) => (found, true),
+                None => (target, false),
+            }
+        }
+        _ => (target, false),
     };
 
     // Check if directory exists
@@ -363,785 +526,1975 @@ fn builtin_cd(shell: &mut Shell, args: &[&str]) -> Result<i32> {
         anyhow::bail!("cd: {}: No such directory", target.display());
     }
 
-    // Change directory
-    env::set_current_dir(&target)?;
-    shell.cwd = target;
+    shell.change_dir(target)?;
+    if from_cdpath || args.first() == Some(&"-") {
+        writeln!(out, "{}", shell.cwd.display())?;
+    }
 
     Ok(0)
 }
 
-/// z - zoxide smart directory jump
-fn builtin_z(shell: &mut Shell, args: &[&str]) -> Result<i32> {
-    use std::process::Command;
+fn builtin_cd(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    builtin_cd_impl(shell, args, &mut out)
+}
 
-    if args.is_empty() {
-        // z with no args: go to home (like cd)
-        let home = dirs::home_dir().unwrap_or_else(|| shell.cwd.clone());
-        env::set_current_dir(&home)?;
-        shell.cwd = home;
-        return Ok(0);
+/// `dirs` style display: `~` for the home directory unless `long`.
+fn display_dir(dir: &Path, long: bool) -> String {
+    if !long {
+        if let Some(rest) = dirs::home_dir().and_then(|home| dir.strip_prefix(home).ok().map(Path::to_path_buf)) {
+            return if rest.as_os_str().is_empty() {
+                "~".to_string()
+            } else {
+                format!("~{}{}", std::path::MAIN_SEPARATOR, rest.display())
+            };
+        }
     }
+    dir.display().to_string()
+}
 
-    // Query zoxide for the best match
-    let output = Command::new("zoxide")
-        .arg("query")
-        .arg("--")
-        .args(args)
-        .output();
+fn is_stack_offset(arg: &str) -> bool {
+    arg.len() > 1
+        && (arg.starts_with('+') || arg.starts_with('-'))
+        && arg[1..].chars().all(|c| c.is_ascii_digit())
+}
 
-    match output {
-        Ok(out) if out.status.success() => {
-            let path_str = String::from_utf8_lossy(&out.stdout).trim().to_string();
-            if path_str.is_empty() {
-                eprintln!("z: no match found");
-                return Ok(1);
-            }
-            let target = std::path::PathBuf::from(&path_str);
-            if !target.is_dir() {
-                anyhow::bail!("z: {}: No such directory", target.display());
-            }
-            env::set_current_dir(&target)?;
-            shell.cwd = target.clone();
-            // Print the directory we jumped to
-            println!("{}", target.display());
+fn write_dir_stack(shell: &Shell, out: &mut dyn Write) -> Result<()> {
+    let line: Vec<String> = shell.dir_list().iter().map(|d| display_dir(d, false)).collect();
+    writeln!(out, "{}", line.join(" "))?;
+    Ok(())
+}
 
-            // Also add to zoxide database
-            let _ = Command::new("zoxide").arg("add").arg(&path_str).spawn();
-            Ok(0)
+/// pushd [dir | +N | -N] - push onto / rotate the directory stack
+fn builtin_pushd_impl(shell: &mut Shell, args: &[&str], out: &mut dyn Write) -> Result<i32> {
+    match args.first() {
+        None => {
+            let Some(top) = shell.dir_stack.first().cloned() else {
+                anyhow::bail!("pushd: no other directory");
+            };
+            if !top.is_dir() {
+                anyhow::bail!("pushd: {}: No such directory", top.display());
+            }
+            shell.dir_stack[0] = shell.cwd.clone();
+            shell.change_dir(top)?;
         }
-        Ok(out) => {
-            let err = String::from_utf8_lossy(&out.stderr);
-            if err.contains("no match found") {
-                eprintln!("z: no match found for {:?}", args.join(" "));
-            } else {
-                eprintln!("z: {}", err.trim());
+        Some(spec) if is_stack_offset(spec) => {
+            let Some(idx) = shell.dir_stack_index(spec) else {
+                anyhow::bail!("pushd: {}: directory stack index out of range", spec);
+            };
+            let mut list = shell.dir_list();
+            list.rotate_left(idx);
+            let target = list.remove(0);
+            if !target.is_dir() {
+                anyhow::bail!("pushd: {}: No such directory", target.display());
             }
-            Ok(1)
+            shell.dir_stack = list;
+            shell.change_dir(target)?;
         }
-        Err(e) => {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                eprintln!("z: zoxide not found. Install with: scoop install zoxide");
-            } else {
-                eprintln!("z: failed to run zoxide: {}", e);
+        Some(raw_path) => {
+            let target = path::resolve_fs(&shell.cwd, &path::expand_env(raw_path));
+            if !target.is_dir() {
+                anyhow::bail!("pushd: {}: No such directory", target.display());
             }
-            Ok(1)
+            let previous = shell.cwd.clone();
+            shell.change_dir(target)?;
+            shell.dir_stack.insert(0, previous);
         }
     }
-}
-
-/// pwd - print working directory
-fn builtin_pwd_impl(shell: &Shell, out: &mut dyn Write) -> Result<i32> {
-    writeln!(out, "{}", shell.cwd.display())?;
+    write_dir_stack(shell, out)?;
     Ok(0)
 }
 
-fn builtin_pwd(shell: &Shell) -> Result<i32> {
+fn builtin_pushd(shell: &mut Shell, args: &[&str]) -> Result<i32> {
     let stdout = io::stdout();
     let mut out = stdout.lock();
-    builtin_pwd_impl(shell, &mut out)
+    builtin_pushd_impl(shell, args, &mut out)
 }
 
-/// ls - list directory
-fn builtin_ls_impl(
-    shell: &Shell,
-    args: &[&str],
-    out: &mut dyn Write,
-    err: &mut dyn Write,
-) -> Result<i32> {
-    // Parse options vs path arguments
-    let mut show_all = false;
-    let mut long_format = false;
-    let mut target_path: Option<&str> = None;
-
-    for arg in args {
-        if arg.starts_with('-') {
-            for ch in arg.chars().skip(1) {
-                match ch {
-                    'a' => show_all = true,
-                    'l' => long_format = true,
-                    _ => {}
-                }
-            }
-        } else if target_path.is_none() {
-            target_path = Some(arg);
-        }
+/// popd [+N | -N] - pop the directory stack
+fn builtin_popd_impl(shell: &mut Shell, args: &[&str], out: &mut dyn Write) -> Result<i32> {
+    if shell.dir_stack.is_empty() {
+        anyhow::bail!("popd: directory stack empty");
     }
-
-    let target = match target_path {
-        Some(p) => {
-            let expanded = path::expand_env(p);
-            let resolved = path::resolve_fs(&shell.cwd, &expanded);
-
-            // Check for Windows reserved device names - provide helpful warning
-            if path::is_windows_reserved_name(&resolved) {
-                writeln!(err, "ls: warning: '{}' is a Windows reserved device name", p)?;
-            }
-
-            resolved
-        }
-        None => shell.cwd.clone(),
+    let idx = match args.first() {
+        None => 0,
+        Some(spec) if is_stack_offset(spec) => match shell.dir_stack_index(spec) {
+            Some(idx) => idx,
+            None => anyhow::bail!("popd: {}: directory stack index out of range", spec),
+        },
+        Some(other) => anyhow::bail!("popd: {}: invalid argument", other),
     };
 
-    let entries = fs::read_dir(&target)
-        .with_context(|| format!("ls: cannot access '{}'", target.display()))?;
-
-    let mut items: Vec<_> = entries
-        .filter_map(|e| e.ok())
-        .filter(|e| {
-            if show_all {
-                true
-            } else {
-                // Hide dotfiles by default
-                !e.file_name().to_string_lossy().starts_with('.')
-            }
-        })
-        .collect();
-
-    // Sort by name
-    items.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
-
-    if long_format {
-        // Long format: one entry per line with details
-        for entry in items {
-            let name = entry.file_name();
-            let name_str = name.to_string_lossy();
-
-            if let Ok(meta) = entry.metadata() {
-                let is_dir = meta.is_dir();
-                let size = meta.len();
-                let modified = meta.modified()
-                    .map(|t| {
-                        let datetime: chrono::DateTime<chrono::Local> = t.into();
-                        datetime.format("%Y-%m-%d %H:%M").to_string()
-                    })
-                    .unwrap_or_else(|_| "????-??-?? ??:??".to_string());
-
-                let type_char = if is_dir { "d" } else { "-" };
-                let colored_name = if is_dir {
-                    name_str.blue().bold().to_string()
-                } else {
-                    name_str.to_string()
-                };
-
-                writeln!(out, "{} {:>10} {} {}", type_char, size, modified, colored_name)?;
-            }
+    if idx == 0 {
+        let target = shell.dir_stack[0].clone();
+        if !target.is_dir() {
+            anyhow::bail!("popd: {}: No such directory", target.display());
         }
+        shell.change_dir(target)?;
+        shell.dir_stack.remove(0);
     } else {
-        // Short format: multi-column layout
-        let names: Vec<_> = items.iter().map(|e| {
-            let name = e.file_name();
-            let name_str = name.to_string_lossy().to_string();
-            let is_dir = e.file_type().map(|t| t.is_dir()).unwrap_or(false);
-            (name_str, is_dir)
-        }).collect();
-
-        if names.is_empty() {
-            return Ok(0);
-        }
-
-        // Get terminal width (default 80)
-        let term_width = crossterm::terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+        shell.dir_stack.remove(idx - 1);
+    }
+    write_dir_stack(shell, out)?;
+    Ok(0)
+}
 
-        // Find max name length
-        let max_len = names.iter().map(|(n, _)| n.len()).max().unwrap_or(10);
-        let col_width = max_len + 2; // 2 spaces padding
-        let num_cols = (term_width / col_width).max(1);
+fn builtin_popd(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    builtin_popd_impl(shell, args, &mut out)
+}
 
-        // Print in columns
-        for (i, (name, is_dir)) in names.iter().enumerate() {
-            let formatted = if *is_dir {
-                format!("{:<width$}", name.blue().bold(), width = col_width)
-            } else {
-                format!("{:<width$}", name, width = col_width)
-            };
-            write!(out, "{}", formatted)?;
+/// dirs [-clpv] [+N | -N] - show the directory stack
+fn builtin_dirs_impl(shell: &mut Shell, args: &[&str], out: &mut dyn Write) -> Result<i32> {
+    let mut long = false;
+    let mut per_line = false;
+    let mut numbered = false;
+    let mut pick: Option<usize> = None;
 
-            // Newline after last column or last item
-            if (i + 1) % num_cols == 0 || i == names.len() - 1 {
-                writeln!(out)?;
+    for arg in args {
+        if is_stack_offset(arg) {
+            match shell.dir_stack_index(arg) {
+                Some(idx) => pick = Some(idx),
+                None => anyhow::bail!("dirs: {}: directory stack index out of range", arg),
+            }
+            continue;
+        }
+        let Some(flags) = arg.strip_prefix('-').filter(|f| !f.is_empty()) else {
+            anyhow::bail!("dirs: {}: invalid argument", arg);
+        };
+        for flag in flags.chars() {
+            match flag {
+                'c' => {
+                    shell.dir_stack.clear();
+                    return Ok(0);
+                }
+                'l' => long = true,
+                'p' => per_line = true,
+                'v' => {
+                    per_line = true;
+                    numbered = true;
+                }
+                _ => anyhow::bail!("dirs: -{}: invalid option (usage: dirs [-clpv] [+N | -N])", flag),
             }
         }
     }
 
+    let list = shell.dir_list();
+    if let Some(idx) = pick {
+        writeln!(out, "{}", display_dir(&list[idx], long))?;
+        return Ok(0);
+    }
+    if !per_line {
+        let line: Vec<String> = list.iter().map(|d| display_dir(d, long)).collect();
+        writeln!(out, "{}", line.join(" "))?;
+        return Ok(0);
+    }
+    for (i, dir) in list.iter().enumerate() {
+        if numbered {
+            writeln!(out, "{:2}  {}", i, display_dir(dir, long))?;
+        } else {
+            writeln!(out, "{}", display_dir(dir, long))?;
+        }
+    }
     Ok(0)
 }
 
-fn builtin_ls(shell: &Shell, args: &[&str]) -> Result<i32> {
+fn builtin_dirs(shell: &mut Shell, args: &[&str]) -> Result<i32> {
     let stdout = io::stdout();
-    let stderr = io::stderr();
     let mut out = stdout.lock();
-    let mut err = stderr.lock();
-    builtin_ls_impl(shell, args, &mut out, &mut err)
+    builtin_dirs_impl(shell, args, &mut out)
 }
 
-/// cat - display file contents (streaming for large files)
-fn builtin_cat_impl(
-    shell: &Shell,
+/// z / zi - jump to a frecent directory (`zi` picks from the matches interactively)
+fn builtin_z_impl(
+    shell: &mut Shell,
     args: &[&str],
+    pick: bool,
     stdin: &mut dyn BufRead,
     out: &mut dyn Write,
     err: &mut dyn Write,
 ) -> Result<i32> {
-    if args.is_empty() {
-        for line in stdin.lines() {
-            writeln!(out, "{}", line?)?;
-        }
-        return Ok(0);
-    }
-
-    for arg in args {
-        if arg.starts_with('-') {
-            continue;
+    let name = if pick { "zi" } else { "z" };
+    match args.first().copied() {
+        None if !pick => {
+            // z with no args: go to home (like cd)
+            let home = dirs::home_dir().unwrap_or_else(|| shell.cwd.clone());
+            shell.change_dir(home)?;
+            return Ok(0);
         }
-        if *arg == "-" {
-            for line in stdin.lines() {
-                writeln!(out, "{}", line?)?;
+        Some("-") if !pick => return builtin_cd_impl(shell, &["-"], out),
+        Some("-l") | Some("--list") => {
+            let mut db = DirDb::open()?;
+            if db.prune() > 0 {
+                db.save()?;
             }
-            continue;
+            let now = frecency::now();
+            for entry in db.query(&args[1..], now) {
+                writeln!(out, "{:>8.1}  {}", entry.score(now), entry.path.display())?;
+            }
+            return Ok(0);
         }
-
-        let expanded = path::expand_env(arg);
-        let paths = expand_glob(&shell.cwd, &expanded);
-
-        for path_str in paths {
-            let target = path::resolve_fs(&shell.cwd, &path_str);
-
-            // Check for Windows reserved device names
-            if path::is_windows_reserved_name(&target) {
-                writeln!(
-                    err,
-                    "cat: warning: '{}' is a Windows reserved device name - reading from device",
-                    path_str
-                )?;
+        Some("--import") => {
+            let mut db = DirDb::open()?;
+            let count = match args.get(1) {
+                Some(file) => db.import_z_file(&path::resolve_fs(&shell.cwd, &path::expand_env(file)))?,
+                None => db.import_zoxide(frecency::now())?,
+            };
+            db.prune();
+            db.save()?;
+            writeln!(out, "z: imported {} entries", count)?;
+            return Ok(0);
+        }
+        Some(flag) if flag.starts_with('-') && flag.len() > 1 => {
+            anyhow::bail!("{}: {}: invalid option (usage: z [-l] [--import [file]] [keywords...])", name, flag);
+        }
+        Some(dir) if !pick && args.len() == 1 => {
+            // A real path wins over the database, like zoxide.
+            let target = path::resolve_fs(&shell.cwd, &path::expand_env(dir));
+            if target.is_dir() {
+                shell.change_dir(target)?;
+                return Ok(0);
             }
+        }
+        _ => {}
+    }
 
-            let file = File::open(&target)
-                .with_context(|| format!("cat: {}: No such file", target.display()))?;
-
-            let reader = BufReader::new(file);
+    let mut db = DirDb::open()?;
+    if db.prune() > 0 {
+        db.save()?;
+    }
+    let now = frecency::now();
+    let candidates: Vec<std::path::PathBuf> = db
+        .query(args, now)
+        .into_iter()
+        .map(|e| e.path.clone())
+        .filter(|p| *p != shell.cwd)
+        .collect();
+    if candidates.is_empty() {
+        writeln!(err, "{}: no match found for {:?}", name, args.join(" "))?;
+        return Ok(1);
+    }
 
-            for line in reader.lines() {
-                let line = line.with_context(|| format!("cat: error reading {}", target.display()))?;
-                writeln!(out, "{}", line)?;
-            }
+    let target = if pick {
+        let shown = &candidates[..candidates.len().min(20)];
+        for (i, dir) in shown.iter().enumerate() {
+            writeln!(err, "{:>3}  {}", i + 1, dir.display())?;
         }
-    }
+        write!(err, "{}: select [1-{}]: ", name, shown.len())?;
+        err.flush()?;
+        let mut answer = String::new();
+        stdin.read_line(&mut answer)?;
+        match answer.trim().parse::<usize>() {
+            Ok(n) if (1..=shown.len()).contains(&n) => shown[n - 1].clone(),
+            _ => return Ok(1),
+        }
+    } else {
+        candidates[0].clone()
+    };
 
+    shell.change_dir(target)?;
+    // Print the directory we jumped to
+    writeln!(out, "{}", shell.cwd.display())?;
     Ok(0)
 }
 
-fn builtin_cat(shell: &Shell, args: &[&str]) -> Result<i32> {
-    let mut stdin = BufReader::new(io::stdin());
+fn builtin_z(shell: &mut Shell, args: &[&str], pick: bool) -> Result<i32> {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
     let stdout = io::stdout();
-    let stderr = io::stderr();
     let mut out = stdout.lock();
-    let mut err = stderr.lock();
-    builtin_cat_impl(shell, args, &mut stdin, &mut out, &mut err)
+    let mut err = io::stderr();
+    builtin_z_impl(shell, args, pick, &mut input, &mut out, &mut err)
 }
 
-/// echo - print arguments
-fn builtin_echo_impl(args: &[&str], out: &mut dyn Write) -> Result<i32> {
-    let output = args.join(" ");
-    // Expand environment variables
-    let expanded = path::expand_env(&output);
-    writeln!(out, "{}", expanded)?;
+/// pwd - print working directory
+fn builtin_pwd_impl(shell: &Shell, out: &mut dyn Write) -> Result<i32> {
+    writeln!(out, "{}", shell.cwd.display())?;
     Ok(0)
 }
 
-fn builtin_echo(args: &[&str]) -> Result<i32> {
+fn builtin_pwd(shell: &Shell) -> Result<i32> {
     let stdout = io::stdout();
     let mut out = stdout.lock();
-    builtin_echo_impl(args, &mut out)
+    builtin_pwd_impl(shell, &mut out)
 }
 
-fn parse_head_tail_args<'a>(args: &'a [&'a str]) -> (usize, Vec<&'a str>) {
-    let mut count = 10usize;
-    let mut files = Vec::new();
+/// ls - list directory
+struct LsOptions {
+    show_all: bool,
+    long_format: bool,
+    human: bool,
+    sort: LsSort,
+    reverse: bool,
+    recursive: bool,
+    one_per_line: bool,
+    directory: bool,
+    classify: bool,
+    group_dirs_first: bool,
+    /// stdout is a terminal: use columns + colours
+    tty: bool,
+}
 
-    let mut i = 0usize;
-    while i < args.len() {
-        let arg = args[i];
-        if arg == "--" {
-            files.extend_from_slice(&args[i + 1..]);
-            break;
+#[derive(Clone, Copy, PartialEq)]
+enum LsSort {
+    Name,
+    Time,
+    Size,
+}
+
+struct LsEntry {
+    name: String,
+    path: std::path::PathBuf,
+    /// Metadata of the entry itself (links are not followed)
+    meta: fs::Metadata,
+    attrs: u32,
+    /// Target of a symlink or junction
+    link_target: Option<std::path::PathBuf>,
+}
+
+impl LsEntry {
+    fn new(name: String, path: std::path::PathBuf) -> io::Result<Self> {
+        let meta = fs::symlink_metadata(&path)?;
+        let attrs = file_attributes(&meta);
+        let link_target = if meta.file_type().is_symlink() || attrs & ATTR_REPARSE_POINT != 0 {
+            fs::read_link(&path).ok()
+        } else {
+            None
+        };
+        Ok(Self { name, path, meta, attrs, link_target })
+    }
+
+    fn is_link(&self) -> bool {
+        self.link_target.is_some() || self.meta.file_type().is_symlink()
+    }
+
+    fn is_dir(&self) -> bool {
+        if self.is_link() {
+            return self.path.is_dir();
         }
-        if arg == "-n" {
-            if i + 1 < args.len() {
-                count = args[i + 1].parse().unwrap_or(count);
-                i += 2;
-                continue;
-            }
+        self.meta.is_dir()
+    }
+
+    fn is_hidden(&self) -> bool {
+        self.name.starts_with('.') || self.attrs & ATTR_HIDDEN != 0
+    }
+
+    fn is_executable(&self) -> bool {
+        if self.meta.is_dir() {
+            return false;
         }
-        if let Some(rest) = arg.strip_prefix("-n") {
-            if !rest.is_empty() {
-                count = rest.parse().unwrap_or(count);
-                i += 1;
-                continue;
+        let Some(ext) = Path::new(&self.name).extension() else {
+            return false;
+        };
+        let ext = format!(".{}", ext.to_string_lossy()).to_ascii_uppercase();
+        let pathext = env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+        ext == ".PS1" || pathext.split(';').any(|e| e.eq_ignore_ascii_case(&ext))
+    }
+
+    fn modified(&self) -> SystemTime {
+        self.meta.modified().unwrap_or(SystemTime::UNIX_EPOCH)
+    }
+
+    /// PowerShell-style mode column: `darhsl`
+    fn mode(&self) -> String {
+        let flag = |set: bool, c: char| if set { c } else { '-' };
+        [
+            flag(self.attrs & ATTR_DIRECTORY != 0 || self.meta.is_dir(), 'd'),
+            flag(self.attrs & ATTR_ARCHIVE != 0, 'a'),
+            flag(self.attrs & ATTR_READONLY != 0, 'r'),
+            flag(self.attrs & ATTR_HIDDEN != 0, 'h'),
+            flag(self.attrs & ATTR_SYSTEM != 0, 's'),
+            flag(self.is_link(), 'l'),
+        ]
+        .iter()
+        .collect()
+    }
+
+    fn display_name(&self, opts: &LsOptions) -> String {
+        let mut name = self.name.clone();
+        if opts.classify {
+            if self.is_link() {
+                name.push('@');
+            } else if self.is_dir() {
+                name.push('/');
+            } else if self.is_executable() {
+                name.push('*');
             }
         }
-        if arg.chars().all(|c| c.is_ascii_digit()) {
-            count = arg.parse().unwrap_or(count);
-            i += 1;
-            continue;
-        }
-        files.push(arg);
-        i += 1;
+        name
     }
 
-    (count, files)
+    fn colored_name(&self, opts: &LsOptions) -> String {
+        let name = self.display_name(opts);
+        if !opts.tty {
+            return name;
+        }
+        let colored = if self.is_link() {
+            name.cyan().bold()
+        } else if self.is_dir() {
+            name.blue().bold()
+        } else if self.is_executable() {
+            name.green().bold()
+        } else {
+            name.normal()
+        };
+        if self.attrs & ATTR_HIDDEN != 0 {
+            colored.dimmed().to_string()
+        } else {
+            colored.to_string()
+        }
+    }
 }
 
-struct TailArgs<'a> {
-    count: usize,
-    follow: bool,
-    files: Vec<&'a str>,
+fn human_size(size: u64) -> String {
+    const UNITS: &[&str] = &["K", "M", "G", "T", "P"];
+    if size < 1024 {
+        return size.to_string();
+    }
+    let mut value = size as f64;
+    let mut unit = "";
+    for u in UNITS {
+        value /= 1024.0;
+        unit = u;
+        if value < 1024.0 {
+            break;
+        }
+    }
+    if value < 10.0 {
+        format!("{:.1}{}", value, unit)
+    } else {
+        format!("{:.0}{}", value, unit)
+    }
 }
 
-fn parse_tail_args<'a>(args: &'a [&'a str]) -> TailArgs<'a> {
-    let mut count = 10usize;
-    let mut follow = false;
-    let mut files = Vec::new();
+fn parse_ls_args<'a>(args: &[&'a str], tty: bool) -> Result<(LsOptions, Vec<&'a str>)> {
+    let mut opts = LsOptions {
+        show_all: false,
+        long_format: false,
+        human: false,
+        sort: LsSort::Name,
+        reverse: false,
+        recursive: false,
+        one_per_line: !tty,
+        directory: false,
+        classify: false,
+        group_dirs_first: false,
+        tty,
+    };
+    let mut paths = Vec::new();
 
-    let mut i = 0usize;
-    while i < args.len() {
-        let arg = args[i];
-        if arg == "--" {
-            files.extend_from_slice(&args[i + 1..]);
+    for (i, arg) in args.iter().enumerate() {
+        if *arg == "--" {
+            paths.extend_from_slice(&args[i + 1..]);
             break;
         }
-
-        if matches!(arg, "-f" | "-F" | "--follow") {
-            follow = true;
-            i += 1;
-            continue;
+        if let Some(long) = arg.strip_prefix("--") {
+            match long {
+                "all" => opts.show_all = true,
+                "human-readable" => opts.human = true,
+                "reverse" => opts.reverse = true,
+                "recursive" => opts.recursive = true,
+                "directory" => opts.directory = true,
+                "classify" => opts.classify = true,
+                "group-directories-first" => opts.group_dirs_first = true,
+                // `--color[=WHEN]` (common in aliases) and other GNU long options are accepted
+                // and ignored; names are colored whenever output is a terminal.
+                _ => {}
+            }
+        } else if arg.starts_with('-') && arg.len() > 1 {
+            for ch in arg.chars().skip(1) {
+                match ch {
+                    'a' | 'A' => opts.show_all = true,
+                    'l' => opts.long_format = true,
+                    'h' => opts.human = true,
+                    't' => opts.sort = LsSort::Time,
+                    'S' => opts.sort = LsSort::Size,
+                    'r' => opts.reverse = true,
+                    'R' => opts.recursive = true,
+                    '1' => opts.one_per_line = true,
+                    'd' => opts.directory = true,
+                    'F' => opts.classify = true,
+                    _ => anyhow::bail!("ls: invalid option -- '{}'", ch),
+                }
+            }
+        } else {
+            paths.push(*arg);
         }
+    }
 
-        if arg == "-n" || arg == "--lines" {
-            if i + 1 < args.len() {
-                count = args[i + 1].parse().unwrap_or(count);
-                i += 2;
-                continue;
+    Ok((opts, paths))
+}
+
+fn sort_ls_entries(entries: &mut [LsEntry], opts: &LsOptions) {
+    match opts.sort {
+        LsSort::Name => entries.sort_by(|a, b| a.name.cmp(&b.name)),
+        // Newest / largest first, like GNU ls
+        LsSort::Time => entries.sort_by(|a, b| b.modified().cmp(&a.modified()).then_with(|| a.name.cmp(&b.name))),
+        LsSort::Size => entries.sort_by(|a, b| b.meta.len().cmp(&a.meta.len()).then_with(|| a.name.cmp(&b.name))),
+    }
+    if opts.reverse {
+        entries.reverse();
+    }
+    if opts.group_dirs_first {
+        entries.sort_by_key(|e| !e.is_dir());
+    }
+}
+
+fn write_ls_entries(entries: &[LsEntry], opts: &LsOptions, out: &mut dyn Write) -> Result<()> {
+    use unicode_width::UnicodeWidthStr;
+
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    if opts.long_format {
+        let sizes: Vec<String> = entries
+            .iter()
+            .map(|e| if opts.human { human_size(e.meta.len()) } else { e.meta.len().to_string() })
+            .collect();
+        let size_width = sizes.iter().map(|s| s.len()).max().unwrap_or(0);
+        for (entry, size) in entries.iter().zip(sizes) {
+            let datetime: chrono::DateTime<chrono::Local> = entry.modified().into();
+            write!(
+                out,
+                "{} {:>width$} {} {}",
+                entry.mode(),
+                size,
+                datetime.format("%Y-%m-%d %H:%M"),
+                entry.colored_name(opts),
+                width = size_width
+            )?;
+            if let Some(target) = &entry.link_target {
+                write!(out, " -> {}", target.display())?;
             }
+            writeln!(out)?;
         }
+        return Ok(());
+    }
 
-        if let Some(rest) = arg.strip_prefix("-n") {
-            if !rest.is_empty() {
-                count = rest.parse().unwrap_or(count);
-                i += 1;
-                continue;
-            }
+    if opts.one_per_line {
+        for entry in entries {
+            writeln!(out, "{}", entry.colored_name(opts))?;
         }
+        return Ok(());
+    }
 
-        if let Some(rest) = arg.strip_prefix("--lines=") {
-            if !rest.is_empty() {
-                count = rest.parse().unwrap_or(count);
-                i += 1;
-                continue;
+    // Column-major layout sized to the terminal
+    let term_width = crossterm::terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+    let widths: Vec<usize> = entries.iter().map(|e| e.display_name(opts).width()).collect();
+    let col_width = widths.iter().copied().max().unwrap_or(0) + 2;
+    let num_cols = (term_width / col_width).max(1);
+    let num_rows = entries.len().div_ceil(num_cols);
+
+    for row in 0..num_rows {
+        let mut line = String::new();
+        for col in 0..num_cols {
+            let idx = col * num_rows + row;
+            let Some(entry) = entries.get(idx) else { break };
+            line.push_str(&entry.colored_name(opts));
+            if col + 1 < num_cols && idx + num_rows < entries.len() {
+                line.push_str(&" ".repeat(col_width - widths[idx]));
             }
         }
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
 
-        if arg.chars().all(|c| c.is_ascii_digit()) {
-            count = arg.parse().unwrap_or(count);
-            i += 1;
+fn read_ls_dir(dir: &Path, opts: &LsOptions) -> io::Result<Vec<LsEntry>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)?.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(item) = LsEntry::new(name, entry.path()) else { continue };
+        if !opts.show_all && item.is_hidden() {
             continue;
         }
+        entries.push(item);
+    }
+    sort_ls_entries(&mut entries, opts);
+    Ok(entries)
+}
 
-        files.push(arg);
-        i += 1;
+fn list_ls_dir(
+    label: &str,
+    dir: &Path,
+    opts: &LsOptions,
+    show_header: bool,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<i32> {
+    let entries = match read_ls_dir(dir, opts) {
+        Ok(entries) => entries,
+        Err(e) => {
+            writeln!(err, "ls: cannot open directory '{}': {}", label, e)?;
+            return Ok(2);
+        }
+    };
+
+    if show_header {
+        writeln!(out, "{}:", label)?;
     }
+    write_ls_entries(&entries, opts, out)?;
 
-    TailArgs {
-        count,
-        follow,
-        files,
+    let mut code = 0;
+    if opts.recursive {
+        for entry in entries.iter().filter(|e| e.is_dir() && !e.is_link()) {
+            if crate::interrupt::seen() {
+                return Ok(130);
+            }
+            writeln!(out)?;
+            let child_label = format!("{}/{}", label.trim_end_matches(['/', '\\']), entry.name);
+            code = code.max(list_ls_dir(&child_label, &entry.path, opts, true, out, err)?);
+        }
     }
+    Ok(code)
 }
 
-/// head - show first N lines
-fn builtin_head_impl(
+fn builtin_ls_impl(
     shell: &Shell,
     args: &[&str],
-    stdin: &mut dyn BufRead,
+    tty: bool,
     out: &mut dyn Write,
+    err: &mut dyn Write,
 ) -> Result<i32> {
-    let (count, files) = parse_head_tail_args(args);
-    if files.is_empty() {
-        for (idx, line) in stdin.lines().enumerate() {
-            if idx >= count {
-                break;
+    let (opts, path_args) = parse_ls_args(args, tty)?;
+
+    let mut targets: Vec<(String, std::path::PathBuf)> = Vec::new();
+    for p in &path_args {
+        let expanded = path::expand_env(p);
+        for path_str in expand_glob(&shell.cwd, &expanded) {
+            let resolved = path::resolve_fs(&shell.cwd, &path_str);
+
+            // Check for Windows reserved device names - provide helpful warning
+            if path::is_windows_reserved_name(&resolved) {
+                writeln!(err, "ls: warning: '{}' is a Windows reserved device name", p)?;
             }
-            writeln!(out, "{}", line?)?;
+
+            targets.push((path_str, resolved));
         }
-        return Ok(0);
     }
-    for file in files {
-        let expanded = path::expand_env(file);
-        let target = path::resolve_fs(&shell.cwd, &expanded);
-        let f = File::open(&target).with_context(|| format!("head: cannot open '{}'", target.display()))?;
-        let reader = BufReader::new(f);
-        for (idx, line) in reader.lines().enumerate() {
-            if idx >= count {
-                break;
+    if path_args.is_empty() {
+        targets.push((".".to_string(), shell.cwd.clone()));
+    }
+
+    // Files (and -d directories) are listed together first, then each directory.
+    let mut code = 0;
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    for (label, target) in targets {
+        match LsEntry::new(label.clone(), target.clone()) {
+            Ok(entry) if entry.is_dir() && !opts.directory => dirs.push((label, target)),
+            Ok(entry) => files.push(entry),
+            Err(e) => {
+                writeln!(err, "ls: cannot access '{}': {}", label, e)?;
+                code = 2;
             }
-            writeln!(out, "{}", line.with_context(|| format!("head: error reading {}", target.display()))?)?;
         }
     }
-    Ok(0)
+
+    sort_ls_entries(&mut files, &opts);
+    write_ls_entries(&files, &opts, out)?;
+
+    let show_headers = opts.recursive || files.len() + dirs.len() > 1 || code != 0;
+    for (i, (label, dir)) in dirs.iter().enumerate() {
+        if i > 0 || !files.is_empty() {
+            writeln!(out)?;
+        }
+        code = code.max(list_ls_dir(label, dir, &opts, show_headers, out, err)?);
+    }
+
+    Ok(code)
 }
 
-fn builtin_head(shell: &Shell, args: &[&str]) -> Result<i32> {
+fn builtin_ls(shell: &Shell, args: &[&str]) -> Result<i32> {
+    use std::io::IsTerminal;
+
     let stdout = io::stdout();
-    let mut stdin = BufReader::new(io::empty());
+    let stderr = io::stderr();
+    let tty = stdout.is_terminal();
     let mut out = stdout.lock();
-    builtin_head_impl(shell, args, &mut stdin, &mut out)
+    let mut err = stderr.lock();
+    builtin_ls_impl(shell, args, tty, &mut out, &mut err)
 }
 
-/// tail - show last N lines (simple implementation)
-fn builtin_tail_impl(
+/// Parse a `-L 2` / `-L2` style numeric option value.
+fn option_number(cmd: &str, flag: &str, value: Option<&str>) -> Result<usize> {
+    let Some(value) = value else {
+        anyhow::bail!("{}: {}: option requires an argument", cmd, flag);
+    };
+    value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("{}: {}: invalid number '{}'", cmd, flag, value))
+}
+
+/// Resolve path arguments (globs expanded) to `(label, path)` pairs, defaulting to `.`.
+fn walk_targets(shell: &Shell, paths: &[&str]) -> Vec<(String, std::path::PathBuf)> {
+    if paths.is_empty() {
+        return vec![(".".to_string(), shell.cwd.clone())];
+    }
+    let mut targets = Vec::new();
+    for p in paths {
+        for path_str in expand_glob(&shell.cwd, &path::expand_env(p)) {
+            let resolved = path::resolve_fs(&shell.cwd, &path_str);
+            targets.push((path_str, resolved));
+        }
+    }
+    targets
+}
+
+/// tree [-L depth] [-a] [-d] [--gitignore] [dir...]
+fn builtin_tree_impl(
     shell: &Shell,
     args: &[&str],
-    stdin: &mut dyn BufRead,
+    tty: bool,
     out: &mut dyn Write,
+    err: &mut dyn Write,
 ) -> Result<i32> {
-    use std::collections::VecDeque;
-    use std::io::{Seek, SeekFrom};
-    use std::thread;
-    use std::time::Duration;
-
-    let TailArgs {
-        count,
-        follow,
-        files,
-    } = parse_tail_args(args);
-    if files.is_empty() {
-        if follow {
-            anyhow::bail!("tail: -f requires a file");
-        }
-        if count == 0 {
-            return Ok(0);
-        }
-        let mut ring: VecDeque<String> = VecDeque::with_capacity(count.max(1));
-        for line in stdin.lines() {
-            let line = line?;
-            if ring.len() == count {
-                ring.pop_front();
+    let mut opts = walk::TreeOptions {
+        max_depth: None,
+        all: false,
+        dirs_only: false,
+        gitignore: false,
+        color: tty,
+    };
+    let mut paths = Vec::new();
+    let mut i = 0usize;
+    while i < args.len() {
+        let arg = args[i];
+        match arg {
+            "-a" => opts.all = true,
+            "-d" => opts.dirs_only = true,
+            "--gitignore" => opts.gitignore = true,
+            "-L" => {
+                opts.max_depth = Some(option_number("tree", "-L", args.get(i + 1).copied())?);
+                i += 1;
             }
-            ring.push_back(line);
-        }
-        for line in ring {
-            writeln!(out, "{}", line)?;
+            _ if arg.starts_with("-L") => opts.max_depth = Some(option_number("tree", "-L", Some(&arg[2..]))?),
+            _ if arg.starts_with('-') && arg.len() > 1 => anyhow::bail!("tree: invalid option '{}'", arg),
+            _ => paths.push(arg),
         }
-        return Ok(0);
+        i += 1;
+    }
+    if opts.max_depth == Some(0) {
+        anyhow::bail!("tree: -L: depth must be greater than 0");
     }
 
-    if follow {
-        if files.len() != 1 {
-            anyhow::bail!("tail: -f currently supports a single file");
+    let mut code = 0;
+    let mut total = walk::TreeCounts::default();
+    for (label, dir) in walk_targets(shell, &paths) {
+        if !dir.is_dir() {
+            writeln!(err, "tree: {}: not a directory", label)?;
+            code = 2;
+            continue;
         }
+        let counts = walk::write_tree(&dir, &label, &opts, out)?;
+        total.dirs += counts.dirs;
+        total.files += counts.files;
+    }
+    if crate::interrupt::seen() {
+        return Ok(130);
+    }
+    writeln!(out)?;
+    writeln!(out, "{}", walk::tree_summary(total, opts.dirs_only))?;
+    Ok(code)
+}
 
-        let expanded = path::expand_env(files[0]);
-        let target = path::resolve_fs(&shell.cwd, &expanded);
-        let f = File::open(&target)
-            .with_context(|| format!("tail: cannot open '{}'", target.display()))?;
-        let mut reader = BufReader::new(f);
+fn builtin_tree(shell: &Shell, args: &[&str]) -> Result<i32> {
+    use std::io::IsTerminal;
 
-        if count > 0 {
-            let mut ring: VecDeque<String> = VecDeque::with_capacity(count.max(1));
-            let mut buf = String::new();
-            loop {
-                buf.clear();
-                let read = reader.read_line(&mut buf)?;
-                if read == 0 {
+    let stdout = io::stdout();
+    let tty = stdout.is_terminal();
+    let mut out = stdout.lock();
+    let mut err = io::stderr();
+    builtin_tree_impl(shell, args, tty, &mut out, &mut err)
+}
+
+/// du [-s] [-h] [-d N] [--sort] [path...] - apparent sizes in KiB (human-readable with -h)
+fn builtin_du_impl(shell: &Shell, args: &[&str], out: &mut dyn Write, err: &mut dyn Write) -> Result<i32> {
+    let mut human = false;
+    let mut sort = false;
+    let mut max_depth = None;
+    let mut paths = Vec::new();
+    let mut i = 0usize;
+    while i < args.len() {
+        let arg = args[i];
+        if let Some(long) = arg.strip_prefix("--") {
+            match long.split_once('=') {
+                Some(("max-depth", value)) => max_depth = Some(option_number("du", "--max-depth", Some(value))?),
+                None if long == "summarize" => max_depth = Some(0),
+                None if long == "human-readable" => human = true,
+                None if long == "sort" => sort = true,
+                None if long.is_empty() => {
+                    paths.extend_from_slice(&args[i + 1..]);
                     break;
                 }
-                if ring.len() == count {
-                    ring.pop_front();
-                }
-                ring.push_back(buf.trim_end_matches(&['\r', '\n'][..]).to_string());
+                _ => anyhow::bail!("du: unrecognized option '{}'", arg),
             }
-            for line in ring {
-                writeln!(out, "{}", line)?;
+        } else if arg.starts_with('-') && arg.len() > 1 {
+            for (pos, flag) in arg.char_indices().skip(1) {
+                match flag {
+                    's' => max_depth = Some(0),
+                    'h' => human = true,
+                    'd' => {
+                        let inline = &arg[pos + 1..];
+                        max_depth = Some(if inline.is_empty() {
+                            i += 1;
+                            option_number("du", "-d", args.get(i).copied())?
+                        } else {
+                            option_number("du", "-d", Some(inline))?
+                        });
+                        break;
+                    }
+                    _ => anyhow::bail!("du: invalid option -- '{}'", flag),
+                }
             }
         } else {
-            reader.seek(SeekFrom::End(0))?;
+            paths.push(arg);
         }
-        let _ = out.flush();
-
-        let mut pos = reader.stream_position()?;
-        let mut buf = String::new();
-        loop {
-            if crate::interrupt::seen() {
-                return Ok(130);
-            }
+        i += 1;
+    }
 
-            buf.clear();
-            let read = reader.read_line(&mut buf)?;
-            if read == 0 {
-                if let Ok(meta) = reader.get_ref().metadata() {
-                    let len = meta.len();
-                    if len < pos {
-                        reader.seek(SeekFrom::Start(0))?;
-                        pos = 0;
-                    }
+    let format_size = |size: u64| if human { human_size(size) } else { size.div_ceil(1024).to_string() };
+    let mut code = 0;
+    let mut sorted: Vec<(u64, String)> = Vec::new();
+    for (label, target) in walk_targets(shell, &paths) {
+        let walked = walk::disk_usage(&target, &label, max_depth, |event| {
+            match event {
+                walk::DuEvent::Size { size, label } if sort => sorted.push((size, label)),
+                walk::DuEvent::Size { size, label } => writeln!(out, "{}\t{}", format_size(size), label)?,
+                walk::DuEvent::Error(message) => {
+                    writeln!(err, "du: {}", message)?;
+                    code = 1;
                 }
-                thread::sleep(Duration::from_millis(200));
-                continue;
             }
-
-            pos = reader.stream_position()?;
-            writeln!(out, "{}", buf.trim_end_matches(&['\r', '\n'][..]))?;
-            let _ = out.flush();
+            Ok(())
+        });
+        if let Err(e) = walked {
+            writeln!(err, "du: cannot access '{}': {}", label, e)?;
+            code = 1;
         }
     }
 
-    if count == 0 {
-        return Ok(0);
+    // Largest first, so the culprits are at the top.
+    sorted.sort_by_key(|(size, _)| std::cmp::Reverse(*size));
+    for (size, label) in sorted {
+        writeln!(out, "{}\t{}", format_size(size), label)?;
     }
-    for file in files {
-        let expanded = path::expand_env(file);
-        let target = path::resolve_fs(&shell.cwd, &expanded);
-        let f = File::open(&target).with_context(|| format!("tail: cannot open '{}'", target.display()))?;
-        let reader = BufReader::new(f);
-        let mut ring: VecDeque<String> = VecDeque::with_capacity(count.max(1));
-        for line in reader.lines() {
-            let line = line?;
-            if ring.len() == count {
-                ring.pop_front();
-            }
-            ring.push_back(line);
-        }
-        for line in ring {
-            writeln!(out, "{}", line)?;
-        }
+    if crate::interrupt::seen() {
+        return Ok(130);
     }
-    Ok(0)
+    Ok(code)
 }
 
-fn builtin_tail(shell: &Shell, args: &[&str]) -> Result<i32> {
+fn builtin_du(shell: &Shell, args: &[&str]) -> Result<i32> {
     let stdout = io::stdout();
-    let mut stdin = BufReader::new(io::empty());
     let mut out = stdout.lock();
-    builtin_tail_impl(shell, args, &mut stdin, &mut out)
+    let mut err = io::stderr();
+    builtin_du_impl(shell, args, &mut out, &mut err)
 }
 
-fn builtin_whoami_impl(out: &mut dyn Write) -> Result<i32> {
-    let user = env::var("USERNAME")
-        .or_else(|_| env::var("USER"))
-        .unwrap_or_else(|_| "unknown".to_string());
-    writeln!(out, "{}", user)?;
-    Ok(0)
+/// ff [options] [pattern] [path...] - find files, skipping hidden and gitignored entries
+fn builtin_ff_impl(shell: &mut Shell, args: &[&str], out: &mut dyn Write, err: &mut dyn Write) -> Result<i32> {
+    let mut opts = find::FindOptions::default();
+    let (mut glob_mode, mut case_sensitive, mut absolute, mut print0) = (false, None, false, false);
+    let mut exec: Option<Vec<String>> = None;
+    let mut positional = Vec::new();
+    let now = SystemTime::now();
+
+    let mut i = 0usize;
+    while i < args.len() {
+        let arg = args[i];
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+            _ if arg.len() > 2 && arg.starts_with('-') && b"tedSE".contains(&arg.as_bytes()[1]) => {
+                (&arg[..2], Some(&arg[2..]))
+            }
+            _ => (arg, None),
+        };
+        let mut value = || -> Result<String> {
+            if let Some(value) = inline {
+                return Ok(value.to_string());
+            }
+            i += 1;
+            match args.get(i) {
+                Some(value) => Ok(value.to_string()),
+                None => anyhow::bail!("ff: {}: option requires an argument", flag),
+            }
+        };
+        match flag {
+            "-H" | "--hidden" => opts.hidden = true,
+            "-I" | "--no-ignore" => opts.no_ignore = true,
+            "-u" | "--unrestricted" => (opts.hidden, opts.no_ignore) = (true, true),
+            "-g" | "--glob" => glob_mode = true,
+            "-i" | "--ignore-case" => case_sensitive = Some(false),
+            "-s" | "--case-sensitive" => case_sensitive = Some(true),
+            "-a" | "--absolute-path" => absolute = true,
+            "-0" | "--print0" => print0 = true,
+            "-t" | "--type" => {
+                opts.types.push(find::EntryType::parse(&value()?).map_err(|e| anyhow::anyhow!("ff: {}", e))?)
+            }
+            "-e" | "--extension" => opts.extensions.push(value()?.trim_start_matches('.').to_lowercase()),
+            "-S" | "--size" => opts.sizes.push(find::SizeFilter::parse(&value()?).map_err(|e| anyhow::anyhow!("ff: {}", e))?),
+            "-d" | "--max-depth" => opts.max_depth = Some(option_number("ff", flag, Some(&value()?))?),
+            "-E" | "--exclude" => {
+                let pattern = value()?;
+                let compiled = glob::Pattern::new(&pattern).map_err(|e| anyhow::anyhow!("ff: invalid glob '{}': {}", pattern, e))?;
+                opts.excludes.push(compiled);
+            }
+            "--changed-within" | "--newer" => {
+                opts.changed_after = Some(find::parse_time_limit(&value()?, now).map_err(|e| anyhow::anyhow!("ff: {}", e))?)
+            }
+            "--changed-before" | "--older" => {
+                opts.changed_before = Some(find::parse_time_limit(&value()?, now).map_err(|e| anyhow::anyhow!("ff: {}", e))?)
+            }
+            "-x" | "--exec" => {
+                // The command runs to the end of the line or up to a `;` argument.
+                let rest = &args[i + 1..];
+                let end = rest.iter().position(|a| matches!(*a, ";" | "\\;")).unwrap_or(rest.len());
+                if end == 0 {
+                    anyhow::bail!("ff: {}: missing command", flag);
+                }
+                exec = Some(rest[..end].iter().map(|a| a.to_string()).collect());
+                i += end + 1;
+            }
+            "--" => {
+                positional.extend_from_slice(&args[i + 1..]);
+                break;
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => anyhow::bail!("ff: unrecognized option '{}'", arg),
+            _ => positional.push(arg),
+        }
+        i += 1;
+    }
+
+    let (pattern, paths) = match positional.split_first() {
+        Some((pattern, paths)) => (*pattern, paths),
+        None => ("", &[][..]),
+    };
+    if !pattern.is_empty() {
+        opts.name = Some(find::NameFilter::new(pattern, glob_mode, case_sensitive).map_err(|e| anyhow::anyhow!("ff: {}", e))?);
+    }
+
+    let mut code = 0;
+    for (label, target) in walk_targets(shell, paths) {
+        let searched = find::search(&target, &label, &opts, |event| {
+            let (path, label) = match event {
+                find::FindEvent::Match { path, label } => (path, label),
+                find::FindEvent::Error(message) => {
+                    writeln!(err, "ff: {}", message)?;
+                    code = 1;
+                    return Ok(());
+                }
+            };
+            let shown = if absolute { path.display().to_string() } else { label };
+            match &exec {
+                Some(template) => {
+                    let mut argv: Vec<String> = template.iter().map(|a| find::expand_placeholders(a, &shown)).collect();
+                    if !find::has_placeholder(template) {
+                        argv.push(shown);
+                    }
+                    // Single-quoted words: paths are passed through without expansion or globbing.
+                    let words = argv
+                        .into_iter()
+                        .map(|text| parser::Word { parts: vec![parser::WordPart { text, quote: parser::QuoteMode::Single }] })
+                        .collect();
+                    let (status, output) = executor::execute_ast_captured(shell, &parser::Command::Simple(words))?;
+                    out.write_all(&output)?;
+                    if status != 0 {
+                        code = 1;
+                    }
+                }
+                None if print0 => write!(out, "{}\0", shown)?,
+                None => writeln!(out, "{}", shown)?,
+            }
+            Ok(())
+        });
+        if let Err(e) = searched {
+            writeln!(err, "ff: {}", e)?;
+            code = 1;
+        }
+    }
+    if crate::interrupt::seen() {
+        return Ok(130);
+    }
+    Ok(code)
 }
 
-fn builtin_whoami() -> Result<i32> {
+fn builtin_ff(shell: &mut Shell, args: &[&str]) -> Result<i32> {
     let stdout = io::stdout();
     let mut out = stdout.lock();
-    builtin_whoami_impl(&mut out)
+    let mut err = io::stderr();
+    builtin_ff_impl(shell, args, &mut out, &mut err)
 }
 
-fn builtin_hostname_impl(out: &mut dyn Write) -> Result<i32> {
-    let host = env::var("COMPUTERNAME")
-        .or_else(|_| env::var("HOSTNAME"))
-        .unwrap_or_else(|_| "unknown".to_string());
-    writeln!(out, "{}", host)?;
+/// cat - display file contents (streaming for large files)
+fn builtin_cat_impl(
+    shell: &Shell,
+    args: &[&str],
+    stdin: &mut dyn BufRead,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<i32> {
+    let (forced, args) = take_encoding_flag("cat", args)?;
+    if args.is_empty() {
+        for line in encoding::text_reader(stdin, forced)?.lines() {
+            writeln!(out, "{}", line?)?;
+        }
+        return Ok(0);
+    }
+
+    for arg in args {
+        if arg == "-" {
+            for line in encoding::text_reader(&mut *stdin, forced)?.lines() {
+                writeln!(out, "{}", line?)?;
+            }
+            continue;
+        }
+        if arg.starts_with('-') {
+            continue;
+        }
+
+        let expanded = path::expand_env(arg);
+        let paths = expand_glob(&shell.cwd, &expanded);
+
+        for path_str in paths {
+            let target = path::resolve_fs(&shell.cwd, &path_str);
+
+            // Check for Windows reserved device names
+            if path::is_windows_reserved_name(&target) {
+                writeln!(
+                    err,
+                    "cat: warning: '{}' is a Windows reserved device name - reading from device",
+                    path_str
+                )?;
+            }
+
+            let file = File::open(&target)
+                .with_context(|| format!("cat: {}: No such file", target.display()))?;
+
+            let reader = encoding::text_reader(BufReader::new(file), forced)
+                .with_context(|| format!("cat: error reading {}", target.display()))?;
+
+            for line in reader.lines() {
+                let line = line.with_context(|| format!("cat: error reading {}", target.display()))?;
+                writeln!(out, "{}", line)?;
+            }
+        }
+    }
+
     Ok(0)
 }
 
-fn builtin_hostname() -> Result<i32> {
+fn builtin_cat(shell: &Shell, args: &[&str]) -> Result<i32> {
+    let mut stdin = io::stdin().lock();
     let stdout = io::stdout();
+    let stderr = io::stderr();
     let mut out = stdout.lock();
-    builtin_hostname_impl(&mut out)
-}
-
-#[derive(Clone, Copy)]
-enum HashKind {
-    Md5,
-    Sha1,
-    Sha256,
-    Sha512,
+    let mut err = stderr.lock();
+    builtin_cat_impl(shell, args, &mut stdin, &mut out, &mut err)
 }
 
-impl HashKind {
-    fn name(&self) -> &'static str {
-        match self {
-            HashKind::Md5 => "md5sum",
-            HashKind::Sha1 => "sha1sum",
-            HashKind::Sha256 => "sha256sum",
-            HashKind::Sha512 => "sha512sum",
+/// One line of `type` output for a candidate.
+fn describe_candidate(name: &str, candidate: &executor::Candidate) -> String {
+    use executor::Candidate;
+    match candidate {
+        Candidate::Alias(value) => format!("{} is aliased to `{}'", name, value),
+        Candidate::Builtin => format!("{} is a shell builtin", name),
+        Candidate::File(file) => format!("{} is {}", name, file.display()),
+        Candidate::Ps1(script) => format!("{} is a PowerShell script ({})", name, script.display()),
+        Candidate::Busybox { busybox, applet } => {
+            format!("{} is a BusyBox applet ({} {})", name, busybox.display(), applet)
         }
     }
 }
 
-/// checksum - compute hashes (file or stdin)
-fn builtin_checksum_impl(
-    kind: HashKind,
+/// type [-atp] name... - describe how names resolve (`type FILE` still prints files, like cmd)
+fn builtin_type_impl(
     shell: &Shell,
     args: &[&str],
     stdin: &mut dyn BufRead,
     out: &mut dyn Write,
     err: &mut dyn Write,
 ) -> Result<i32> {
-    let mut exit_code = 0;
+    use executor::Candidate;
 
-    let inputs: Vec<&str> = if args.is_empty() { vec!["-"] } else { args.iter().copied().filter(|a| *a != "--").collect() };
+    let mut all = false;
+    let mut kind_only = false;
+    let mut path_only = false;
+    let mut names: Vec<&str> = Vec::new();
+    let mut options_done = false;
+    for arg in args {
+        if options_done || !arg.starts_with('-') || *arg == "-" {
+            names.push(arg);
+            continue;
+        }
+        if *arg == "--" {
+            options_done = true;
+            continue;
+        }
+        for flag in arg[1..].chars() {
+            match flag {
+                'a' => all = true,
+                't' => kind_only = true,
+                'p' => path_only = true,
+                _ => anyhow::bail!("type: -{}: invalid option (usage: type [-atp] name...)", flag),
+            }
+        }
+    }
 
-    for arg in inputs {
-        if arg.starts_with('-') && arg != "-" {
-            if arg == "--help" || arg == "-h" {
-                writeln!(out, "Usage: {} [FILE...]", kind.name())?;
-                writeln!(out, "  - (or no args) reads from stdin")?;
-                return Ok(0);
+    let has_flags = all || kind_only || path_only;
+    if !has_flags
+        && !names.is_empty()
+        && names
+            .iter()
+            .all(|n| path::resolve_fs(&shell.cwd, &path::expand_env(n)).is_file())
+    {
+        return builtin_cat_impl(shell, args, stdin, out, err);
+    }
+
+    let mut status = 0;
+    for name in names {
+        let candidates = executor::command_candidates(shell, name, true);
+        if candidates.is_empty() {
+            if !kind_only && !path_only {
+                writeln!(err, "type: {}: not found", name)?;
             }
+            status = 1;
             continue;
         }
-
-        if arg == "-" {
-            let mut buf = [0u8; 64 * 1024];
-            match kind {
-                HashKind::Md5 => {
-                    let mut ctx = md5::Context::new();
-                    loop {
-                        let n = stdin.read(&mut buf)?;
-                        if n == 0 { break; }
-                        ctx.consume(&buf[..n]);
-                    }
-                    writeln!(out, "{:x} *-", ctx.compute())?;
-                }
-                HashKind::Sha1 => {
-                    let mut hasher = Sha1::new();
-                    loop {
-                        let n = stdin.read(&mut buf)?;
-                        if n == 0 { break; }
-                        hasher.update(&buf[..n]);
-                    }
-                    writeln!(out, "{:x} *-", hasher.finalize())?;
-                }
-                HashKind::Sha256 => {
-                    let mut hasher = Sha256::new();
-                    loop {
-                        let n = stdin.read(&mut buf)?;
-                        if n == 0 { break; }
-                        hasher.update(&buf[..n]);
-                    }
-                    writeln!(out, "{:x} *-", hasher.finalize())?;
-                }
-                HashKind::Sha512 => {
-                    let mut hasher = sha2::Sha512::new();
-                    loop {
-                        let n = stdin.read(&mut buf)?;
-                        if n == 0 { break; }
-                        hasher.update(&buf[..n]);
-                    }
-                    writeln!(out, "{:x} *-", hasher.finalize())?;
+        let shown = if all { &candidates[..] } else { &candidates[..1] };
+        for candidate in shown {
+            if kind_only {
+                let kind = match candidate {
+                    Candidate::Alias(_) => "alias",
+                    Candidate::Builtin => "builtin",
+                    _ => "file",
+                };
+                writeln!(out, "{}", kind)?;
+            } else if path_only {
+                match candidate {
+                    Candidate::File(file) | Candidate::Ps1(file) => writeln!(out, "{}", file.display())?,
+                    Candidate::Busybox { busybox, .. } => writeln!(out, "{}", busybox.display())?,
+                    Candidate::Alias(_) | Candidate::Builtin => {}
                 }
+            } else {
+                writeln!(out, "{}", describe_candidate(name, candidate))?;
             }
-            continue;
         }
+    }
+    Ok(status)
+}
 
-        let expanded = path::expand_env(arg);
-        let paths = expand_glob(&shell.cwd, &expanded);
-
-        for path_str in paths {
-            let target = path::resolve_fs(&shell.cwd, &path_str);
+fn builtin_type(shell: &Shell, args: &[&str]) -> Result<i32> {
+    let mut stdin = io::stdin().lock();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut err = io::stderr();
+    builtin_type_impl(shell, args, &mut stdin, &mut out, &mut err)
+}
 
-            if path::is_windows_reserved_name(&target) {
-                writeln!(err, "{}: warning: '{}' is a Windows reserved device name - hashing a device may block", kind.name(), path_str)?;
+/// command -v|-V name... - report what each name resolves to
+///
+/// `command name args...` (no alias lookup) is handled by the executor before dispatch;
+/// only builtins can be run from here.
+fn builtin_command_impl(
+    shell: &mut Shell,
+    args: &[&str],
+    stdin: &mut dyn BufRead,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<i32> {
+    use executor::Candidate;
+
+    let verbose = match args.first().copied() {
+        None => return Ok(0),
+        Some("-v") => false,
+        Some("-V") => true,
+        Some(flag) if flag.starts_with('-') => {
+            anyhow::bail!("command: {}: invalid option (usage: command [-v | -V] name...)", flag)
+        }
+        Some(name) => {
+            if !is_builtin(name) {
+                anyhow::bail!("command: {}: only builtins can be run here", name);
             }
+            let rest: Vec<String> = args[1..].iter().map(|s| s.to_string()).collect();
+            return run_builtin_io(shell, name, &rest, stdin, out, err, false, false);
+        }
+    };
 
-            if target.is_dir() {
-                writeln!(err, "{}: {}: Is a directory", kind.name(), path_str)?;
-                exit_code = 1;
-                continue;
+    let mut status = 0;
+    for name in &args[1..] {
+        let Some(candidate) = executor::command_candidates(shell, name, true).into_iter().next() else {
+            if verbose {
+                writeln!(err, "command: {}: not found", name)?;
             }
+            status = 1;
+            continue;
+        };
+        if verbose {
+            writeln!(out, "{}", describe_candidate(name, &candidate))?;
+            continue;
+        }
+        match candidate {
+            Candidate::Alias(value) => writeln!(out, "alias {}='{}'", name, value)?,
+            Candidate::Builtin => writeln!(out, "{}", name)?,
+            Candidate::File(file) | Candidate::Ps1(file) => writeln!(out, "{}", file.display())?,
+            Candidate::Busybox { busybox, .. } => writeln!(out, "{}", busybox.display())?,
+        }
+    }
+    Ok(status)
+}
 
-            let mut file = match File::open(&target) {
-                Ok(f) => f,
-                Err(e) => {
-                    writeln!(err, "{}: {}: {}", kind.name(), path_str, e)?;
-                    exit_code = 1;
-                    continue;
-                }
-            };
+fn builtin_command(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    let mut stdin = io::stdin().lock();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut err = io::stderr();
+    builtin_command_impl(shell, args, &mut stdin, &mut out, &mut err)
+}
 
-            let mut buf = [0u8; 64 * 1024];
-            let digest = match kind {
-                HashKind::Md5 => {
-                    let mut ctx = md5::Context::new();
-                    loop {
-                        let n = file.read(&mut buf)?;
-                        if n == 0 { break; }
-                        ctx.consume(&buf[..n]);
-                    }
-                    format!("{:x}", ctx.compute())
-                }
-                HashKind::Sha1 => {
-                    let mut hasher = Sha1::new();
-                    loop {
-                        let n = file.read(&mut buf)?;
-                        if n == 0 { break; }
-                        hasher.update(&buf[..n]);
-                    }
-                    format!("{:x}", hasher.finalize())
-                }
-                HashKind::Sha256 => {
-                    let mut hasher = Sha256::new();
-                    loop {
-                        let n = file.read(&mut buf)?;
-                        if n == 0 { break; }
-                        hasher.update(&buf[..n]);
-                    }
-                    format!("{:x}", hasher.finalize())
-                }
-                HashKind::Sha512 => {
-                    let mut hasher = sha2::Sha512::new();
-                    loop {
-                        let n = file.read(&mut buf)?;
-                        if n == 0 { break; }
-                        hasher.update(&buf[..n]);
-                    }
-                    format!("{:x}", hasher.finalize())
-                }
-            };
-            writeln!(out, "{}  {}", digest, path_str)?;
+/// echo - print arguments
+///
+/// Leading `-n` (no newline), `-e` (escapes) and `-E` (no escapes) flags, combinable as
+/// `-ne`; anything else, including `--`, is printed.
+fn builtin_echo_impl(args: &[&str], out: &mut dyn Write) -> Result<i32> {
+    let mut newline = true;
+    let mut escapes = false;
+    let mut words = args;
+    while let Some(flags) = words.first().and_then(|a| a.strip_prefix('-')) {
+        if flags.is_empty() || !flags.chars().all(|c| matches!(c, 'n' | 'e' | 'E')) {
+            break;
         }
+        for flag in flags.chars() {
+            match flag {
+                'n' => newline = false,
+                'e' => escapes = true,
+                _ => escapes = false,
+            }
+        }
+        words = &words[1..];
     }
 
-    Ok(exit_code)
+    let output = words.join(" ");
+    // Expand environment variables
+    let mut expanded = path::expand_env(&output);
+    if escapes {
+        let (text, stop) = printf::unescape(&expanded, printf::Escapes::Echo);
+        expanded = text;
+        newline &= !stop;
+    }
+    write!(out, "{}", expanded)?;
+    if newline {
+        writeln!(out)?;
+    }
+    Ok(0)
 }
 
-fn builtin_checksum(kind: HashKind, shell: &Shell, args: &[&str]) -> Result<i32> {
-    let stdin = io::stdin();
+fn builtin_echo(args: &[&str]) -> Result<i32> {
     let stdout = io::stdout();
-    let stderr = io::stderr();
-    let mut stdin = stdin.lock();
-    let mut stdout = stdout.lock();
-    let mut stderr = stderr.lock();
-    builtin_checksum_impl(kind, shell, args, &mut stdin, &mut stdout, &mut stderr)
+    let mut out = stdout.lock();
+    builtin_echo_impl(args, &mut out)
 }
 
-/// clear - clear screen
-fn builtin_clear_impl(out: &mut dyn Write) -> Result<i32> {
-    // ANSI escape codes work in Windows Terminal
-    write!(out, "\x1B[2J\x1B[1;1H")?;
-    out.flush()?;
-    Ok(0)
+/// printf [-v var] FORMAT [ARGS...] - formatted output
+fn builtin_printf_impl(shell: &mut Shell, args: &[&str], out: &mut dyn Write, err: &mut dyn Write) -> Result<i32> {
+    let (var, args) = match args {
+        ["-v", name, rest @ ..] => {
+            if !is_identifier(name) {
+                anyhow::bail!("printf: `{}': not a valid identifier", name);
+            }
+            (Some(*name), rest)
+        }
+        ["-v"] => anyhow::bail!("printf: -v: option requires an argument"),
+        ["--", rest @ ..] => (None, rest),
+        _ => (None, args),
+    };
+    let Some((format, rest)) = args.split_first() else {
+        anyhow::bail!("printf: usage: printf [-v var] format [arguments]");
+    };
+
+    let rest: Vec<String> = rest.iter().map(|s| s.to_string()).collect();
+    let formatted = printf::format(format, &rest);
+    for error in &formatted.errors {
+        writeln!(err, "printf: {}", error)?;
+    }
+    match var {
+        Some(name) => shell.set_var(name, &formatted.text).map_err(|e| anyhow::anyhow!("printf: {}", e))?,
+        None => {
+            write!(out, "{}", formatted.text)?;
+            out.flush()?;
+        }
+    }
+    Ok(if formatted.errors.is_empty() { 0 } else { 1 })
 }
 
-fn builtin_clear() -> Result<i32> {
+fn builtin_printf(shell: &mut Shell, args: &[&str]) -> Result<i32> {
     let stdout = io::stdout();
     let mut out = stdout.lock();
-    builtin_clear_impl(&mut out)
+    let mut err = io::stderr();
+    builtin_printf_impl(shell, args, &mut out, &mut err)
 }
 
-/// help - show help
-fn builtin_help_impl(out: &mut dyn Write) -> Result<i32> {
-    writeln!(out, "{}", "TITAN Bash - Modern shell for Windows".bold())?;
+/// test EXPR / [ EXPR ] - evaluate a conditional expression (0 true, 1 false, 2 error)
+fn builtin_test_impl(shell: &Shell, name: &str, args: &[&str], err: &mut dyn Write) -> Result<i32> {
+    let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+    let args = if name == "[" {
+        match args.split_last() {
+            Some((last, rest)) if last == "]" => rest.to_vec(),
+            _ => {
+                writeln!(err, "[: missing `]'")?;
+                return Ok(2);
+            }
+        }
+    } else {
+        args
+    };
+    match cond::eval_test(&shell.cwd, &args, &|name| shell.get_var(name)) {
+        Ok(result) => Ok(if result { 0 } else { 1 }),
+        Err(e) => {
+            writeln!(err, "{}: {}", name, e)?;
+            Ok(2)
+        }
+    }
+}
+
+fn builtin_test(shell: &Shell, name: &str, args: &[&str]) -> Result<i32> {
+    let mut err = io::stderr();
+    builtin_test_impl(shell, name, args, &mut err)
+}
+
+/// Strip `--encoding ENC` / `--encoding=ENC` from `args` (`auto` or absent means detect).
+fn take_encoding_flag<'a>(cmd: &str, args: &[&'a str]) -> Result<(Option<Encoding>, Vec<&'a str>)> {
+    let mut forced = None;
+    let mut rest = Vec::with_capacity(args.len());
+    let mut i = 0usize;
+    while i < args.len() {
+        let arg = args[i];
+        if arg == "--" {
+            rest.extend_from_slice(&args[i..]);
+            break;
+        }
+        if arg == "--encoding" {
+            let Some(name) = args.get(i + 1) else {
+                anyhow::bail!("{}: --encoding requires a value", cmd);
+            };
+            forced = Encoding::parse(name).with_context(|| format!("{}: --encoding", cmd))?;
+            i += 2;
+            continue;
+        }
+        if let Some(name) = arg.strip_prefix("--encoding=") {
+            forced = Encoding::parse(name).with_context(|| format!("{}: --encoding", cmd))?;
+            i += 1;
+            continue;
+        }
+        rest.push(arg);
+        i += 1;
+    }
+    Ok((forced, rest))
+}
+
+fn parse_head_tail_args<'a>(args: &'a [&'a str]) -> (usize, Vec<&'a str>) {
+    let mut count = 10usize;
+    let mut files = Vec::new();
+
+    let mut i = 0usize;
+    while i < args.len() {
+        let arg = args[i];
+        if arg == "--" {
+            files.extend_from_slice(&args[i + 1..]);
+            break;
+        }
+        if arg == "-n" {
+            if i + 1 < args.len() {
+                count = args[i + 1].parse().unwrap_or(count);
+                i += 2;
+                continue;
+            }
+        }
+        if let Some(rest) = arg.strip_prefix("-n") {
+            if !rest.is_empty() {
+                count = rest.parse().unwrap_or(count);
+                i += 1;
+                continue;
+            }
+        }
+        if arg.chars().all(|c| c.is_ascii_digit()) {
+            count = arg.parse().unwrap_or(count);
+            i += 1;
+            continue;
+        }
+        files.push(arg);
+        i += 1;
+    }
+
+    (count, files)
+}
+
+struct TailArgs<'a> {
+    count: usize,
+    follow: bool,
+    files: Vec<&'a str>,
+}
+
+fn parse_tail_args<'a>(args: &'a [&'a str]) -> TailArgs<'a> {
+    let mut count = 10usize;
+    let mut follow = false;
+    let mut files = Vec::new();
+
+    let mut i = 0usize;
+    while i < args.len() {
+        let arg = args[i];
+        if arg == "--" {
+            files.extend_from_slice(&args[i + 1..]);
+            break;
+        }
+
+        if matches!(arg, "-f" | "-F" | "--follow") {
+            follow = true;
+            i += 1;
+            continue;
+        }
+
+        if arg == "-n" || arg == "--lines" {
+            if i + 1 < args.len() {
+                count = args[i + 1].parse().unwrap_or(count);
+                i += 2;
+                continue;
+            }
+        }
+
+        if let Some(rest) = arg.strip_prefix("-n") {
+            if !rest.is_empty() {
+                count = rest.parse().unwrap_or(count);
+                i += 1;
+                continue;
+            }
+        }
+
+        if let Some(rest) = arg.strip_prefix("--lines=") {
+            if !rest.is_empty() {
+                count = rest.parse().unwrap_or(count);
+                i += 1;
+                continue;
+            }
+        }
+
+        if arg.chars().all(|c| c.is_ascii_digit()) {
+            count = arg.parse().unwrap_or(count);
+            i += 1;
+            continue;
+        }
+
+        files.push(arg);
+        i += 1;
+    }
+
+    TailArgs {
+        count,
+        follow,
+        files,
+    }
+}
+
+/// head - show first N lines
+fn builtin_head_impl(
+    shell: &Shell,
+    args: &[&str],
+    stdin: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> Result<i32> {
+    let (forced, args) = take_encoding_flag("head", args)?;
+    let (count, files) = parse_head_tail_args(&args);
+    if files.is_empty() {
+        for line in encoding::text_reader(stdin, forced)?.lines().take(count) {
+            writeln!(out, "{}", line?)?;
+        }
+        return Ok(0);
+    }
+    for file in files {
+        let expanded = path::expand_env(file);
+        let target = path::resolve_fs(&shell.cwd, &expanded);
+        let f = File::open(&target).with_context(|| format!("head: cannot open '{}'", target.display()))?;
+        let reader = encoding::text_reader(BufReader::new(f), forced)?;
+        for line in reader.lines().take(count) {
+            writeln!(out, "{}", line.with_context(|| format!("head: error reading {}", target.display()))?)?;
+        }
+    }
+    Ok(0)
+}
+
+fn builtin_head(shell: &Shell, args: &[&str]) -> Result<i32> {
+    let stdout = io::stdout();
+    let mut stdin = BufReader::new(io::empty());
+    let mut out = stdout.lock();
+    builtin_head_impl(shell, args, &mut stdin, &mut out)
+}
+
+/// tail - show last N lines (simple implementation)
+fn builtin_tail_impl(
+    shell: &Shell,
+    args: &[&str],
+    stdin: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> Result<i32> {
+    use std::collections::VecDeque;
+    use std::io::{Seek, SeekFrom};
+    use std::thread;
+    use std::time::Duration;
+
+    let (forced, args) = take_encoding_flag("tail", args)?;
+    let TailArgs {
+        count,
+        follow,
+        files,
+    } = parse_tail_args(&args);
+    if files.is_empty() {
+        if follow {
+            anyhow::bail!("tail: -f requires a file");
+        }
+        if count == 0 {
+            return Ok(0);
+        }
+        let mut ring: VecDeque<String> = VecDeque::with_capacity(count.max(1));
+        for line in encoding::text_reader(stdin, forced)?.lines() {
+            let line = line?;
+            if ring.len() == count {
+                ring.pop_front();
+            }
+            ring.push_back(line);
+        }
+        for line in ring {
+            writeln!(out, "{}", line)?;
+        }
+        return Ok(0);
+    }
+
+    if follow {
+        if files.len() != 1 {
+            anyhow::bail!("tail: -f currently supports a single file");
+        }
+        if forced.is_some_and(|enc| enc != Encoding::Utf8) {
+            anyhow::bail!("tail: -f only supports UTF-8 files");
+        }
+
+        let expanded = path::expand_env(files[0]);
+        let target = path::resolve_fs(&shell.cwd, &expanded);
+        let f = File::open(&target)
+            .with_context(|| format!("tail: cannot open '{}'", target.display()))?;
+        let mut reader = BufReader::new(f);
+
+        if count > 0 {
+            let mut ring: VecDeque<String> = VecDeque::with_capacity(count.max(1));
+            let mut buf = String::new();
+            loop {
+                buf.clear();
+                let read = reader.read_line(&mut buf)?;
+                if read == 0 {
+                    break;
+                }
+                if ring.len() == count {
+                    ring.pop_front();
+                }
+                ring.push_back(buf.trim_end_matches(&['\r', '\n'][..]).to_string());
+            }
+            for line in ring {
+                writeln!(out, "{}", line)?;
+            }
+        } else {
+            reader.seek(SeekFrom::End(0))?;
+        }
+        let _ = out.flush();
+
+        let mut pos = reader.stream_position()?;
+        let mut buf = String::new();
+        loop {
+            if crate::interrupt::seen() {
+                return Ok(130);
+            }
+
+            buf.clear();
+            let read = reader.read_line(&mut buf)?;
+            if read == 0 {
+                if let Ok(meta) = reader.get_ref().metadata() {
+                    let len = meta.len();
+                    if len < pos {
+                        reader.seek(SeekFrom::Start(0))?;
+                        pos = 0;
+                    }
+                }
+                thread::sleep(Duration::from_millis(200));
+                continue;
+            }
+
+            pos = reader.stream_position()?;
+            writeln!(out, "{}", buf.trim_end_matches(&['\r', '\n'][..]))?;
+            let _ = out.flush();
+        }
+    }
+
+    if count == 0 {
+        return Ok(0);
+    }
+    for file in files {
+        let expanded = path::expand_env(file);
+        let target = path::resolve_fs(&shell.cwd, &expanded);
+        let f = File::open(&target).with_context(|| format!("tail: cannot open '{}'", target.display()))?;
+        let reader = encoding::text_reader(BufReader::new(f), forced)?;
+        let mut ring: VecDeque<String> = VecDeque::with_capacity(count.max(1));
+        for line in reader.lines() {
+            let line = line?;
+            if ring.len() == count {
+                ring.pop_front();
+            }
+            ring.push_back(line);
+        }
+        for line in ring {
+            writeln!(out, "{}", line)?;
+        }
+    }
+    Ok(0)
+}
+
+fn builtin_tail(shell: &Shell, args: &[&str]) -> Result<i32> {
+    let stdout = io::stdout();
+    let mut stdin = BufReader::new(io::empty());
+    let mut out = stdout.lock();
+    builtin_tail_impl(shell, args, &mut stdin, &mut out)
+}
+
+fn builtin_whoami_impl(out: &mut dyn Write) -> Result<i32> {
+    let user = env::var("USERNAME")
+        .or_else(|_| env::var("USER"))
+        .unwrap_or_else(|_| "unknown".to_string());
+    writeln!(out, "{}", user)?;
+    Ok(0)
+}
+
+fn builtin_whoami() -> Result<i32> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    builtin_whoami_impl(&mut out)
+}
+
+fn builtin_hostname_impl(out: &mut dyn Write) -> Result<i32> {
+    let host = env::var("COMPUTERNAME")
+        .or_else(|_| env::var("HOSTNAME"))
+        .unwrap_or_else(|_| "unknown".to_string());
+    writeln!(out, "{}", host)?;
+    Ok(0)
+}
+
+fn builtin_hostname() -> Result<i32> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    builtin_hostname_impl(&mut out)
+}
+
+#[derive(Clone, Copy)]
+enum HashKind {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl HashKind {
+    fn name(&self) -> &'static str {
+        match self {
+            HashKind::Md5 => "md5sum",
+            HashKind::Sha1 => "sha1sum",
+            HashKind::Sha256 => "sha256sum",
+            HashKind::Sha512 => "sha512sum",
+        }
+    }
+}
+
+/// Hash everything `reader` yields and return the lowercase hex digest.
+fn hash_reader(kind: HashKind, reader: &mut dyn Read) -> Result<String> {
+    let mut buf = [0u8; 64 * 1024];
+    let digest = match kind {
+        HashKind::Md5 => {
+            let mut ctx = md5::Context::new();
+            loop {
+                let n = reader.read(&mut buf)?;
+                if n == 0 { break; }
+                ctx.consume(&buf[..n]);
+            }
+            format!("{:x}", ctx.compute())
+        }
+        HashKind::Sha1 => {
+            let mut hasher = Sha1::new();
+            loop {
+                let n = reader.read(&mut buf)?;
+                if n == 0 { break; }
+                hasher.update(&buf[..n]);
+            }
+            format!("{:x}", hasher.finalize())
+        }
+        HashKind::Sha256 => {
+            let mut hasher = Sha256::new();
+            loop {
+                let n = reader.read(&mut buf)?;
+                if n == 0 { break; }
+                hasher.update(&buf[..n]);
+            }
+            format!("{:x}", hasher.finalize())
+        }
+        HashKind::Sha512 => {
+            let mut hasher = sha2::Sha512::new();
+            loop {
+                let n = reader.read(&mut buf)?;
+                if n == 0 { break; }
+                hasher.update(&buf[..n]);
+            }
+            format!("{:x}", hasher.finalize())
+        }
+    };
+    Ok(digest)
+}
+
+/// checksum - compute hashes (file or stdin)
+///
+/// With `--encoding` the input is hashed as UTF-8 text after transcoding, so a UTF-16 file
+/// written by PowerShell hashes the same as its UTF-8 equivalent.
+fn builtin_checksum_impl(
+    kind: HashKind,
+    shell: &Shell,
+    args: &[&str],
+    stdin: &mut dyn BufRead,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<i32> {
+    let mut exit_code = 0;
+
+    let text_mode = args.iter().any(|a| a.starts_with("--encoding"));
+    let (forced, args) = take_encoding_flag(kind.name(), args)?;
+
+    let inputs: Vec<&str> = if args.is_empty() { vec!["-"] } else { args.iter().copied().filter(|a| *a != "--").collect() };
+
+    for arg in inputs {
+        if arg.starts_with('-') && arg != "-" {
+            if arg == "--help" || arg == "-h" {
+                writeln!(out, "Usage: {} [--encoding ENC] [FILE...]", kind.name())?;
+                writeln!(out, "  - (or no args) reads from stdin")?;
+                writeln!(out, "  --encoding ENC  hash the text transcoded to UTF-8 (auto, utf-8, utf-16le, utf-16be)")?;
+                return Ok(0);
+            }
+            continue;
+        }
+
+        if arg == "-" {
+            let digest = if text_mode {
+                hash_reader(kind, &mut encoding::text_reader(&mut *stdin, forced)?)?
+            } else {
+                hash_reader(kind, stdin)?
+            };
+            writeln!(out, "{} *-", digest)?;
+            continue;
+        }
+
+        let expanded = path::expand_env(arg);
+        let paths = expand_glob(&shell.cwd, &expanded);
+
+        for path_str in paths {
+            let target = path::resolve_fs(&shell.cwd, &path_str);
+
+            if path::is_windows_reserved_name(&target) {
+                writeln!(err, "{}: warning: '{}' is a Windows reserved device name - hashing a device may block", kind.name(), path_str)?;
+            }
+
+            if target.is_dir() {
+                writeln!(err, "{}: {}: Is a directory", kind.name(), path_str)?;
+                exit_code = 1;
+                continue;
+            }
+
+            let mut file = match File::open(&target) {
+                Ok(f) => f,
+                Err(e) => {
+                    writeln!(err, "{}: {}: {}", kind.name(), path_str, e)?;
+                    exit_code = 1;
+                    continue;
+                }
+            };
+
+            let digest = if text_mode {
+                hash_reader(kind, &mut encoding::text_reader(BufReader::new(file), forced)?)?
+            } else {
+                hash_reader(kind, &mut file)?
+            };
+            writeln!(out, "{}  {}", digest, path_str)?;
+        }
+    }
+
+    Ok(exit_code)
+}
+
+fn builtin_checksum(kind: HashKind, shell: &Shell, args: &[&str]) -> Result<i32> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let stderr = io::stderr();
+    let mut stdin = stdin.lock();
+    let mut stdout = stdout.lock();
+    let mut stderr = stderr.lock();
+    builtin_checksum_impl(kind, shell, args, &mut stdin, &mut stdout, &mut stderr)
+}
+
+struct TranscodeArgs<'a> {
+    from: Option<Encoding>,
+    to: Encoding,
+    bom: bool,
+    line_ending: LineEnding,
+    files: Vec<&'a str>,
+}
+
+fn parse_transcode_args<'a>(args: &[&'a str]) -> Result<TranscodeArgs<'a>> {
+    let mut parsed = TranscodeArgs {
+        from: None,
+        to: Encoding::Utf8,
+        bom: false,
+        line_ending: LineEnding::Keep,
+        files: Vec::new(),
+    };
+
+    let mut i = 0usize;
+    while i < args.len() {
+        let arg = args[i];
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+            _ => (arg, None),
+        };
+        match flag {
+            "--" => {
+                parsed.files.extend_from_slice(&args[i + 1..]);
+                break;
+            }
+            "-f" | "--from" | "-t" | "--to" => {
+                let value = match inline {
+                    Some(v) => v,
+                    None => {
+                        i += 1;
+                        *args
+                            .get(i)
+                            .ok_or_else(|| anyhow::anyhow!("transcode: {} requires an encoding", flag))?
+                    }
+                };
+                let enc = Encoding::parse(value).context("transcode")?;
+                if matches!(flag, "-f" | "--from") {
+                    parsed.from = enc;
+                } else {
+                    parsed.to = enc.unwrap_or(Encoding::Utf8);
+                }
+            }
+            "--bom" => parsed.bom = true,
+            "--lf" => parsed.line_ending = LineEnding::Lf,
+            "--crlf" => parsed.line_ending = LineEnding::Crlf,
+            _ if arg.starts_with('-') && arg != "-" => {
+                anyhow::bail!("transcode: unknown option '{}'", arg)
+            }
+            _ => parsed.files.push(arg),
+        }
+        i += 1;
+    }
+
+    Ok(parsed)
+}
+
+/// transcode - iconv-style encoding conversion (auto-detects the input by default)
+fn builtin_transcode_impl(
+    shell: &Shell,
+    args: &[&str],
+    stdin: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> Result<i32> {
+    if args.iter().any(|a| matches!(*a, "-h" | "--help")) {
+        writeln!(out, "Usage: transcode [-f FROM] [-t TO] [--bom] [--lf|--crlf] [FILE...]")?;
+        writeln!(out, "  Encodings: auto (input only, default), utf-8 (output default), utf-16le, utf-16be")?;
+        writeln!(out, "  --bom   write a byte order mark")?;
+        writeln!(out, "  --lf    convert CRLF line endings to LF")?;
+        writeln!(out, "  --crlf  convert LF line endings to CRLF")?;
+        return Ok(0);
+    }
+
+    let parsed = parse_transcode_args(args)?;
+    let mut bom_pending = parsed.bom;
+    let mut convert = |reader: &mut dyn BufRead, out: &mut dyn Write| -> Result<()> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            let text = parsed.line_ending.apply(&line);
+            out.write_all(&encoding::encode(&text, parsed.to, bom_pending))?;
+            bom_pending = false;
+        }
+        Ok(())
+    };
+
+    if parsed.files.is_empty() {
+        convert(&mut *encoding::text_reader(&mut *stdin, parsed.from)?, out)?;
+    }
+    for file in &parsed.files {
+        if *file == "-" {
+            convert(&mut *encoding::text_reader(&mut *stdin, parsed.from)?, out)?;
+            continue;
+        }
+        let expanded = path::expand_env(file);
+        let target = path::resolve_fs(&shell.cwd, &expanded);
+        let f = File::open(&target)
+            .with_context(|| format!("transcode: cannot open '{}'", target.display()))?;
+        let mut reader = encoding::text_reader(BufReader::new(f), parsed.from)?;
+        convert(&mut *reader, out)
+            .with_context(|| format!("transcode: error reading {}", target.display()))?;
+    }
+    out.flush()?;
+    Ok(0)
+}
+
+fn builtin_transcode(shell: &Shell, args: &[&str]) -> Result<i32> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut stdin = stdin.lock();
+    let mut stdout = stdout.lock();
+    builtin_transcode_impl(shell, args, &mut stdin, &mut stdout)
+}
+
+/// clear - clear screen
+fn builtin_clear_impl(out: &mut dyn Write) -> Result<i32> {
+    // ANSI escape codes work in Windows Terminal
+    write!(out, "\x1B[2J\x1B[1;1H")?;
+    out.flush()?;
+    Ok(0)
+}
+
+fn builtin_clear() -> Result<i32> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    builtin_clear_impl(&mut out)
+}
+
+/// help - show help
+fn builtin_help_impl(out: &mut dyn Write) -> Result<i32> {
+    writeln!(out, "{}", "TITAN Bash - Modern shell for Windows".bold())?;
     writeln!(out)?;
     writeln!(out, "Built-in commands:")?;
-    writeln!(out, "  {}       Change directory (supports all path formats)", "cd".green())?;
+    writeln!(out, "  {}       Change directory (supports all path formats, cd - for $OLDPWD, $CDPATH)", "cd".green())?;
+    writeln!(out, "  {}    Push directory onto the stack (pushd +N/-N rotates)", "pushd".green())?;
+    writeln!(out, "  {}     Pop the directory stack (popd +N/-N drops an entry)", "popd".green())?;
+    writeln!(out, "  {}     Show the directory stack (-v -l -p -c)", "dirs".green())?;
+    writeln!(out, "  {} / {}   Jump to a frecent directory (z foo bar, z -l, z --import)", "z".green(), "zi".green())?;
     writeln!(out, "  {}      Print working directory", "pwd".green())?;
-    writeln!(out, "  {}       List directory contents (-l, -a)", "ls".green())?;
+    writeln!(out, "  {}       List directory contents (-l -a -h -t -S -r -R -1 -d -F)", "ls".green())?;
+    writeln!(out, "  {}     Show a directory tree (-L depth, -a, -d, --gitignore)", "tree".green())?;
+    writeln!(out, "  {}       Summarize disk usage (-s, -h, -d N, --sort)", "du".green())?;
+    writeln!(out, "  {}       Find files, skipping hidden/.gitignored (-g glob, -t f|d|l, -e ext, -S +10k, --changed-within 2d, -x cmd {{}}, -0)", "ff".green())?;
     writeln!(out, "  {}      Display file contents", "cat".green())?;
-    writeln!(out, "  {}     Print text", "echo".green())?;
+    writeln!(out, "  {}     Print text (-n no newline, -e escapes)", "echo".green())?;
+    writeln!(out, "  {}   Formatted output (%s %d %x %f %e %b %q ..., -v var)", "printf".green())?;
+    writeln!(out, "  {} / {}  Evaluate a condition (-f -d -e -nt, = !=, -eq -lt, ! -a -o; also [[ ]])", "test".green(), "[".green())?;
     writeln!(out, "  {}    Clear screen", "clear".green())?;
     writeln!(out, "  {}    Define or show aliases", "alias".green())?;
     writeln!(out, "  {}  Remove aliases", "unalias".green())?;
@@ -1149,17 +2502,32 @@ fn builtin_help_impl(out: &mut dyn Write) -> Result<i32> {
     writeln!(out, "  {}  Deactivate python venv", "deactivate".green())?;
     writeln!(out, "  {}     Exit shell", "exit".green())?;
     writeln!(out, "  {}     Show background jobs", "jobs".green())?;
-    writeln!(out, "  {}        Bring job to foreground", "fg".green())?;
+    writeln!(out, "  {}        Bring job to foreground (jobspecs: %N %% %+ %- %name %?text)", "fg".green())?;
+    writeln!(out, "  {}        Report on a job (jobs never stop, so they are always in the background)", "bg".green())?;
     writeln!(out, "  {}      Wait for background job(s)", "wait".green())?;
-    writeln!(out, "  {}      Kill background job", "kill".green())?;
-    writeln!(out, "  {}   Set environment variable", "export".green())?;
+    writeln!(out, "  {}      Signal processes or jobs (-s SIG, -SIG, --no-tree; -l lists signals)", "kill".green())?;
+    writeln!(out, "  {}    Forget jobs so they outlive the shell (-a all, -r running, -h keep listed)", "disown".green())?;
+    writeln!(out, "  {}     Start a detached job that survives exit (output to nohup.out)", "nohup".green())?;
+    writeln!(out, "  {}   Set environment variable (-n un-export, -p list)", "export".green())?;
+    writeln!(out, "  {}    Remove variables (unset NAME...)", "unset".green())?;
+    writeln!(out, "  {} Mark variables readonly (later assignments fail)", "readonly".green())?;
+    writeln!(out, "  {}  Set attributes (-i -r -x, +i +x) or print variables re-sourceably (-p)", "declare".green())?;
+    writeln!(out, "  {}     Read a line into variables (-r -p prompt -s -t secs -n N -d delim -a arr)", "read".green())?;
+    writeln!(out, "  {}      Shell options (set -o lists them; set -o noclobber, set +H)", "set".green())?;
     writeln!(out, "  {} / {}    Show environment variables", "env".green(), "printenv".green())?;
     writeln!(out, "  {}    Locate a command", "which".green())?;
+    writeln!(out, "  {}     Show what a name resolves to (-a all, -t kind, -p path; type FILE prints it)", "type".green())?;
+    writeln!(out, "  {}  command -v/-V name; command name args runs it without aliases", "command".green())?;
+    writeln!(out, "  {}  Show how a command line would be dispatched", "explain".green())?;
+    writeln!(out, "  {}    Re-run a command and redraw its output (-n secs, -d highlight, -g exit on change)", "watch".green())?;
     writeln!(out, "  {}    Create directory", "mkdir".green())?;
-    writeln!(out, "  {}       Remove file/directory", "rm".green())?;
-    writeln!(out, "  {}       Copy file", "cp".green())?;
-    writeln!(out, "  {}       Move/rename file", "mv".green())?;
+    writeln!(out, "  {}       Remove file/directory (--trash, --no-preserve-root; set -o saferm)", "rm".green())?;
+    writeln!(out, "  {}    List/restore/purge trashed files", "trash".green())?;
+    writeln!(out, "  {}       Copy files (-r -i -n -u -v -p)", "cp".green())?;
+    writeln!(out, "  {}       Move/rename files (-i -n -u -v, works across drives)", "mv".green())?;
+    writeln!(out, "  {}       Create links (hard by default, -s symlink, --junction; -f -n -T -v)", "ln".green())?;
     writeln!(out, "  {}    Create file or update timestamp", "touch".green())?;
+    writeln!(out, "  {}     File metadata (-c/--printf GNU formats like '%n %s %y %F', -L, --json)", "stat".green())?;
     writeln!(out, "  {}  Show command history", "history".green())?;
     writeln!(out, "  {}        Show first lines of file", "head".green())?;     
     writeln!(out, "  {}         Show last lines of file (-n, -f)", "tail".green())?;
@@ -1169,6 +2537,7 @@ fn builtin_help_impl(out: &mut dyn Write) -> Result<i32> {
     writeln!(out, "  {}     Compute SHA-1 hashes", "sha1sum".green())?;
     writeln!(out, "  {}     Compute SHA-256 hashes", "sha256sum".green())?;
     writeln!(out, "  {}     Compute SHA-512 hashes", "sha512sum".green())?;
+    writeln!(out, "  {}  Convert text encoding (-f/-t utf-8|utf-16le|utf-16be, --lf, --crlf, --bom)", "transcode".green())?;
     writeln!(out)?;
     writeln!(out, "Path formats (all work!):")?;
     writeln!(out, "  C:\\Users\\xxx")?;
@@ -1176,418 +2545,1737 @@ fn builtin_help_impl(out: &mut dyn Write) -> Result<i32> {
     writeln!(out, "  /c/Users/xxx")?;
     writeln!(out, "  ~/Documents")?;
     writeln!(out, "  ~username/Documents")?;
+    writeln!(out, "  ~1, ~-, ~+      Directory stack entry, $OLDPWD, $PWD")?;
     writeln!(out)?;
     writeln!(out, "Background jobs:")?;
     writeln!(out, "  command &     Run in background")?;
     writeln!(out, "  jobs          List jobs")?;
     writeln!(out, "  fg [id]       Wait for a job and remove it")?;
     writeln!(out, "  wait [id..]   Wait for job(s)")?;
-    writeln!(out, "  kill <id>     Terminate a job (taskkill)")?;
+    writeln!(out, "  kill %id|pid  Terminate a job or process (TERM: soft close, then force)")?;
+    Ok(0)
+}
+
+fn builtin_activate(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    let venv_dir = if let Some(arg) = args.first() {
+        // Support both: `activate venv` and `activate venv\\Scripts\\activate`.
+        if let Some(dir) = venv::try_extract_venv_dir(&shell.cwd, arg) {
+            dir
+        } else {
+            let expanded = path::expand_env(arg);
+            path::resolve(&shell.cwd, &expanded)
+        }
+    } else {
+        venv::find_default_venv_dir(&shell.cwd)
+            .ok_or_else(|| anyhow::anyhow!("activate: no venv found (try: activate .venv or activate venv)"))?
+    };
+
+    venv::activate(shell, &venv_dir)?;
+    Ok(0)
+}
+
+fn builtin_deactivate(shell: &mut Shell) -> Result<i32> {
+    venv::deactivate(shell)?;
+    Ok(0)
+}
+
+fn builtin_help() -> Result<i32> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    builtin_help_impl(&mut out)
+}
+
+/// jobs - list background jobs
+fn builtin_jobs_impl(shell: &Shell, out: &mut dyn Write) -> Result<i32> {
+    let jobs = shell.tasks.list();
+    if jobs.is_empty() {
+        writeln!(out, "No background jobs")?;
+    } else {
+        for (id, status, cmd) in jobs {
+            writeln!(out, "[{}] {} {}", id, status, cmd)?;
+        }
+    }
+    Ok(0)
+}
+
+fn builtin_jobs(shell: &Shell) -> Result<i32> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    builtin_jobs_impl(shell, &mut out)
+}
+
+/// `%N`, `N`, `%%`/`%+`, `%-`, `%name` or `%?text` → job id
+fn parse_job_spec(shell: &Shell, cmd: &str, spec: &str) -> Result<TaskId> {
+    shell.tasks.job_spec(spec).map_err(|e| anyhow::anyhow!("{}: {}", cmd, e))
+}
+
+fn last_running_job_id(shell: &Shell) -> Option<TaskId> {
+    let mut last: Option<TaskId> = None;
+    for (id, _, _) in shell.tasks.list() {
+        if matches!(shell.tasks.status(id), Some(TaskStatus::Running)) {
+            last = Some(id);
+        }
+    }
+    last
+}
+
+/// fg - bring a job to foreground (best-effort: just waits for it)
+fn builtin_fg(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    let id = if args.is_empty() {
+        last_running_job_id(shell).ok_or_else(|| anyhow::anyhow!("fg: no jobs"))?
+    } else {
+        parse_job_spec(shell, "fg", args[0])?
+    };
+
+    let status = shell
+        .tasks
+        .wait_and_remove(id)
+        .ok_or_else(|| anyhow::anyhow!("fg: {}: no such job", id))?;
+
+    match status {
+        TaskStatus::Completed(code) => Ok(code),
+        TaskStatus::Failed(msg) => anyhow::bail!("fg: {}", msg),
+        TaskStatus::Running => Ok(0),
+    }
+}
+
+/// wait - wait for background job(s)
+fn builtin_wait(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    let mut ids: Vec<TaskId> = if args.is_empty() {
+        shell.tasks.list().into_iter().map(|(id, _, _)| id).collect()
+    } else {
+        args.iter().map(|a| parse_job_spec(shell, "wait", a)).collect::<Result<Vec<_>>>()?
+    };
+
+    ids.sort_unstable();
+    ids.dedup();
+
+    let mut last_code = 0;
+    for id in ids {
+        let status = shell
+            .tasks
+            .wait_and_remove(id)
+            .ok_or_else(|| anyhow::anyhow!("wait: {}: no such job", id))?;
+        match status {
+            TaskStatus::Completed(code) => last_code = code,
+            TaskStatus::Failed(msg) => anyhow::bail!("wait: {}", msg),
+            TaskStatus::Running => {}
+        }
+    }
+
+    Ok(last_code)
+}
+
+/// kill [-s SIG | -n NUM | -SIG] [--no-tree] pid|%job... - signal processes (default TERM)
+/// kill -l [SIG|STATUS...] - list signal names, or translate between names and numbers
+fn builtin_kill_impl(shell: &Shell, args: &[&str], out: &mut dyn Write, err: &mut dyn Write) -> Result<i32> {
+    const USAGE: &str = "kill: usage: kill [-s sigspec | -n signum | -sigspec] [--no-tree] pid | jobspec ... or kill -l [sigspec]";
+    let parse_signal = |spec: &str| {
+        signal::parse(spec).ok_or_else(|| anyhow::anyhow!("kill: {}: invalid signal specification", spec))
+    };
+
+    let mut sig = Signal::TERM;
+    let mut tree = true;
+    let mut list = false;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i] {
+            "-l" | "-L" | "--list" => list = true,
+            "--no-tree" => tree = false,
+            "-s" | "-n" => {
+                i += 1;
+                let spec = args.get(i).ok_or_else(|| anyhow::anyhow!("kill: {}: option requires an argument", args[i - 1]))?;
+                sig = parse_signal(spec)?;
+            }
+            "--" => {
+                i += 1;
+                break;
+            }
+            arg if arg.starts_with('-') && arg.len() > 1 && !list => sig = parse_signal(&arg[1..])?,
+            _ => break,
+        }
+        i += 1;
+    }
+    let targets = &args[i..];
+
+    if list {
+        if targets.is_empty() {
+            for (n, s) in signal::all().iter().enumerate() {
+                let sep = if n % 5 == 4 || n + 1 == signal::all().len() { "\n" } else { "\t" };
+                write!(out, "{:2}) SIG{:<6}{}", s.number, s.name, sep)?;
+            }
+            return Ok(0);
+        }
+        let mut code = 0;
+        for spec in targets {
+            // Exit statuses above 128 name the signal that ended the process.
+            let found = match spec.parse::<i32>() {
+                Ok(n) if n > 128 => signal::parse(&(n - 128).to_string()),
+                _ => signal::parse(spec),
+            };
+            match found {
+                Some(s) if spec.parse::<i32>().is_ok() => writeln!(out, "{}", s.name)?,
+                Some(s) => writeln!(out, "{}", s.number)?,
+                None => {
+                    writeln!(err, "kill: {}: invalid signal specification", spec)?;
+                    code = 1;
+                }
+            }
+        }
+        return Ok(code);
+    }
+
+    if targets.is_empty() {
+        anyhow::bail!(USAGE);
+    }
+    let mut code = 0;
+    for target in targets {
+        let result = if target.starts_with('%') {
+            shell.tasks.job_spec(target).and_then(|id| shell.tasks.signal(id, sig, tree))
+        } else if let Ok(pid) = target.parse::<u32>() {
+            // A job's process keeps its job semantics (console group, PID registry).
+            match shell.tasks.job_for_pid(pid) {
+                Some(id) => shell.tasks.signal(id, sig, tree),
+                None => signal::send(pid, sig, tree, false).map_err(|e| anyhow::anyhow!("({}) - {}", pid, e)),
+            }
+        } else {
+            Err(anyhow::anyhow!("{}: arguments must be process or job IDs", target))
+        };
+        if let Err(e) = result {
+            writeln!(err, "kill: {}", e)?;
+            code = 1;
+        }
+    }
+    Ok(code)
+}
+
+fn builtin_kill(shell: &Shell, args: &[&str]) -> Result<i32> {
+    let mut out = io::stdout();
+    let mut err = io::stderr();
+    builtin_kill_impl(shell, args, &mut out, &mut err)
+}
+
+/// bg [jobspec...] - background jobs never stop here, so this only reports on them
+fn builtin_bg_impl(shell: &Shell, args: &[&str], err: &mut dyn Write) -> Result<i32> {
+    let specs: &[&str] = if args.is_empty() { &["%+"] } else { args };
+    let mut code = 0;
+    for spec in specs {
+        let id = match shell.tasks.job_spec(spec) {
+            Ok(id) => id,
+            Err(_) if args.is_empty() => anyhow::bail!("bg: no current job"),
+            Err(e) => {
+                writeln!(err, "bg: {}", e)?;
+                code = 1;
+                continue;
+            }
+        };
+        if matches!(shell.tasks.status(id), Some(TaskStatus::Running)) {
+            writeln!(err, "bg: job {} already in background", id)?;
+        } else {
+            writeln!(err, "bg: job {} has terminated", id)?;
+            code = 1;
+        }
+    }
+    Ok(code)
+}
+
+fn builtin_bg(shell: &Shell, args: &[&str]) -> Result<i32> {
+    let mut err = io::stderr();
+    builtin_bg_impl(shell, args, &mut err)
+}
+
+/// disown [-a] [-r] [-h] [jobspec...] - forget jobs so they keep running after titanbash exits
+/// (`-h` keeps them in the job table)
+fn builtin_disown(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    let (mut all, mut running_only, mut keep_listed) = (false, false, false);
+    let mut specs = Vec::new();
+    for arg in args {
+        match *arg {
+            "-a" => all = true,
+            "-r" => running_only = true,
+            "-h" => keep_listed = true,
+            _ if arg.starts_with('-') && arg.len() > 1 && arg[1..].chars().all(|c| "arh".contains(c)) => {
+                all |= arg.contains('a');
+                running_only |= arg.contains('r');
+                keep_listed |= arg.contains('h');
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => anyhow::bail!("disown: {}: invalid option", arg),
+            _ => specs.push(*arg),
+        }
+    }
+
+    let mut ids: Vec<TaskId> = if all || (running_only && specs.is_empty()) {
+        shell.tasks.list().into_iter().map(|(id, _, _)| id).collect()
+    } else if specs.is_empty() {
+        vec![shell.tasks.job_spec("%+").map_err(|_| anyhow::anyhow!("disown: no current job"))?]
+    } else {
+        specs.iter().map(|spec| parse_job_spec(shell, "disown", spec)).collect::<Result<_>>()?
+    };
+    if running_only {
+        ids.retain(|id| matches!(shell.tasks.status(*id), Some(TaskStatus::Running)));
+    }
+
+    for id in ids {
+        if keep_listed {
+            shell.tasks.keep_on_exit(id)?;
+        } else {
+            shell.tasks.disown(id)?;
+        }
+    }
+    Ok(0)
+}
+
+/// nohup command [args...] - start a detached job that survives titanbash exiting
+fn builtin_nohup(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    if args.is_empty() {
+        anyhow::bail!("nohup: missing operand");
+    }
+    let cmdline = args.iter().map(|a| printf::shell_quote(a)).collect::<Vec<_>>().join(" ");
+    executor::execute_background(&mut shell.tasks, &cmdline, &shell.cwd, &shell.aliases, true)?;
+    Ok(0)
+}
+
+/// explain - show how a command line would be dispatched (does not execute it)
+fn builtin_explain_impl(shell: &mut Shell, args: &[&str], out: &mut dyn Write) -> Result<i32> {
+    if args.is_empty() {
+        anyhow::bail!("explain: usage: explain '<command line>'");
+    }
+    executor::explain(shell, &args.join(" "), out)
+}
+
+fn builtin_explain(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    builtin_explain_impl(shell, args, &mut out)
+}
+
+/// watch [-n secs] [-d] [-g] command... - re-run a command line until Ctrl+C
+fn builtin_watch_impl(shell: &mut Shell, args: &[&str], tty: bool, out: &mut dyn Write) -> Result<i32> {
+    use std::time::Duration;
+
+    let mut opts = watch::WatchOptions {
+        interval: Duration::from_secs(2),
+        differences: false,
+        exit_on_change: false,
+    };
+    let parse_interval = |value: Option<&str>| -> Result<Duration> {
+        let Some(value) = value else {
+            anyhow::bail!("watch: -n: option requires an argument");
+        };
+        match value.parse::<f64>() {
+            Ok(secs) if secs.is_finite() && secs >= 0.0 => Ok(Duration::from_secs_f64(secs.max(0.1))),
+            _ => anyhow::bail!("watch: invalid interval '{}'", value),
+        }
+    };
+
+    let mut i = 0usize;
+    while i < args.len() {
+        let arg = args[i];
+        match arg {
+            "--" => {
+                i += 1;
+                break;
+            }
+            "-n" | "--interval" => {
+                opts.interval = parse_interval(args.get(i + 1).copied())?;
+                i += 1;
+            }
+            "--differences" => opts.differences = true,
+            "--chgexit" => opts.exit_on_change = true,
+            _ if arg.starts_with("--interval=") => opts.interval = parse_interval(Some(&arg["--interval=".len()..]))?,
+            _ if arg.starts_with("-n") => opts.interval = parse_interval(Some(&arg[2..]))?,
+            _ if arg.starts_with('-') && arg.len() > 1 && arg[1..].chars().all(|c| matches!(c, 'd' | 'g')) => {
+                opts.differences |= arg.contains('d');
+                opts.exit_on_change |= arg.contains('g');
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => anyhow::bail!("watch: invalid option '{}'", arg),
+            _ => break,
+        }
+        i += 1;
+    }
+
+    let cmdline = args[i.min(args.len())..].join(" ");
+    if cmdline.trim().is_empty() {
+        anyhow::bail!("watch: usage: watch [-n secs] [-d] [-g] command");
+    }
+    if !tty {
+        anyhow::bail!("watch: stdout is not a terminal");
+    }
+    watch::run(shell, &cmdline, &opts, out)
+}
+
+fn builtin_watch(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    use std::io::IsTerminal;
+
+    let stdout = io::stdout();
+    let tty = stdout.is_terminal();
+    let mut out = stdout.lock();
+    builtin_watch_impl(shell, args, tty, &mut out)
+}
+
+fn escape_single_quotes(value: &str) -> String {
+    value.replace('\'', r#"'\''"#)
+}
+
+/// alias - define or show aliases
+fn builtin_alias_impl(shell: &mut Shell, args: &[&str], out: &mut dyn Write) -> Result<i32> {
+    if args.is_empty() {
+        let mut keys: Vec<&String> = shell.aliases.keys().collect();
+        keys.sort();
+        for k in keys {
+            let v = shell.aliases.get(k).map(|s| s.as_str()).unwrap_or_default();
+            writeln!(out, "alias {}='{}'", k, escape_single_quotes(v))?;
+        }
+        return Ok(0);
+    }
+
+    for arg in args {
+        if let Some((name, value)) = arg.split_once('=') {
+            if name.is_empty() {
+                anyhow::bail!("alias: invalid name");
+            }
+            shell.aliases.insert(name.to_string(), value.to_string());
+        } else {
+            let Some(value) = shell.aliases.get(*arg) else {
+                anyhow::bail!("alias: {}: not found", arg);
+            };
+            writeln!(out, "alias {}='{}'", arg, escape_single_quotes(value))?;
+        }
+    }
+
+    Ok(0)
+}
+
+fn builtin_alias(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    builtin_alias_impl(shell, args, &mut out)
+}
+
+/// unalias - remove aliases
+fn builtin_unalias(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    if args.is_empty() {
+        anyhow::bail!("unalias: missing operand");
+    }
+
+    if args.iter().any(|a| *a == "-a") {
+        shell.aliases.clear();
+        return Ok(0);
+    }
+
+    for name in args {
+        if shell.aliases.remove(*name).is_none() {
+            anyhow::bail!("unalias: {}: not found", name);
+        }
+    }
+
+    Ok(0)
+}
+
+/// Split `NAME=value` / `NAME`, rejecting names that are not identifiers.
+fn split_assignment<'a>(cmd: &str, arg: &'a str) -> Result<(&'a str, Option<&'a str>)> {
+    let (name, value) = match arg.split_once('=') {
+        Some((name, value)) => (name, Some(value)),
+        None => (arg, None),
+    };
+    if !is_identifier(name) {
+        anyhow::bail!("{}: `{}': not a valid identifier", cmd, arg);
+    }
+    Ok((name, value))
+}
+
+/// Every variable name `declare -p` can print back: identifiers in the environment, shell
+/// variables, arrays and readonly names without a value.
+fn variable_names(shell: &Shell) -> Vec<String> {
+    let mut names: Vec<String> = env::vars_os()
+        .map(|(k, _)| k.to_string_lossy().into_owned())
+        .chain(shell.vars.keys().cloned())
+        .chain(shell.arrays.keys().cloned())
+        .chain(shell.var_attrs.keys().cloned())
+        .filter(|name| is_identifier(name))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Single-quote for `declare -p` (a `'` becomes `'"'"'`, which the parser reads back).
+fn declare_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\"'\"'"))
+}
+
+/// One `declare -irx NAME='value'` line, as printed by `declare -p` and read back by `declare`.
+/// Arrays print as `declare -a NAME=([0]='a' [1]='b')`.
+fn write_declaration(shell: &Shell, name: &str, out: &mut dyn Write) -> Result<()> {
+    let attrs = shell.var_attrs(name);
+    let array = shell.arrays.get(name);
+    let mut flags = String::from("-");
+    if array.is_some() {
+        flags.push('a');
+    }
+    if attrs.integer {
+        flags.push('i');
+    }
+    if attrs.readonly {
+        flags.push('r');
+    }
+    if shell.is_exported(name) {
+        flags.push('x');
+    }
+    if flags == "-" {
+        flags.push('-');
+    }
+    if let Some(items) = array {
+        let items: Vec<String> = items.iter().enumerate().map(|(i, item)| format!("[{}]={}", i, declare_quote(item))).collect();
+        writeln!(out, "declare {} {}=({})", flags, name, items.join(" "))?;
+        return Ok(());
+    }
+    match shell.get_var(name) {
+        Some(value) => writeln!(out, "declare {} {}={}", flags, name, declare_quote(&value))?,
+        None => writeln!(out, "declare {} {}", flags, name)?,
+    }
+    Ok(())
+}
+
+/// export [-n] [-p] [NAME[=value]...] - export variables (`-n` keeps them in the shell only)
+fn builtin_export_impl(shell: &mut Shell, args: &[&str], out: &mut dyn Write) -> Result<i32> {
+    let mut unexport = false;
+    let mut print = false;
+    let mut names = Vec::new();
+    for (i, arg) in args.iter().enumerate() {
+        match *arg {
+            "-n" => unexport = true,
+            "-p" => print = true,
+            "--" => {
+                names.extend_from_slice(&args[i + 1..]);
+                break;
+            }
+            _ => {
+                names.extend_from_slice(&args[i..]);
+                break;
+            }
+        }
+    }
+
+    if names.is_empty() {
+        if print {
+            for name in variable_names(shell).iter().filter(|n| shell.is_exported(n)) {
+                write_declaration(shell, name, out)?;
+            }
+        } else if !unexport {
+            // Show all environment variables
+            for (key, value) in env::vars() {
+                writeln!(out, "{}={}", key, value)?;
+            }
+        }
+        return Ok(0);
+    }
+
+    for arg in names {
+        let (name, value) = split_assignment("export", arg)?;
+        if unexport {
+            if let Some(value) = value {
+                shell.set_var(name, value).map_err(|e| anyhow::anyhow!("export: {}", e))?;
+            }
+            shell.set_exported(name, false);
+        } else if let Some(value) = value {
+            shell.check_assignable(name).map_err(|e| anyhow::anyhow!("export: {}", e))?;
+            shell.set_exported(name, true);
+            shell.set_var(name, value).map_err(|e| anyhow::anyhow!("export: {}", e))?;
+        } else if shell.vars.contains_key(name) {
+            shell.set_exported(name, true);
+        } else if let Ok(value) = env::var(name) {
+            // Just the name, show value
+            writeln!(out, "{}={}", name, value)?;
+        }
+    }
+
+    Ok(0)
+}
+
+fn builtin_export(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    builtin_export_impl(shell, args, &mut out)
+}
+
+/// unset [-v|-f] NAME... - remove variables (there are no shell functions, so `-f` removes nothing)
+fn builtin_unset_impl(shell: &mut Shell, args: &[&str], err: &mut dyn Write) -> Result<i32> {
+    let mut functions = false;
+    let mut names = Vec::new();
+    for (i, arg) in args.iter().enumerate() {
+        match *arg {
+            "-v" => functions = false,
+            "-f" => functions = true,
+            "--" => {
+                names.extend_from_slice(&args[i + 1..]);
+                break;
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                anyhow::bail!("unset: {}: invalid option (usage: unset [-f] [-v] name...)", arg)
+            }
+            _ => {
+                names.extend_from_slice(&args[i..]);
+                break;
+            }
+        }
+    }
+
+    let mut status = 0;
+    for name in names {
+        if !is_identifier(name) {
+            writeln!(err, "unset: `{}': not a valid identifier", name)?;
+            status = 1;
+        } else if !functions {
+            if let Err(e) = shell.unset_var(name) {
+                writeln!(err, "unset: {}", e)?;
+                status = 1;
+            }
+        }
+    }
+    Ok(status)
+}
+
+fn builtin_unset(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    let mut err = io::stderr();
+    builtin_unset_impl(shell, args, &mut err)
+}
+
+/// readonly [-p] [NAME[=value]...] - mark variables readonly (listing them without names)
+fn builtin_readonly_impl(shell: &mut Shell, args: &[&str], out: &mut dyn Write) -> Result<i32> {
+    let names: Vec<&str> = args.iter().copied().filter(|a| !matches!(*a, "-p" | "--")).collect();
+    if names.is_empty() {
+        for name in variable_names(shell).iter().filter(|n| shell.var_attrs(n).readonly) {
+            write_declaration(shell, name, out)?;
+        }
+        return Ok(0);
+    }
+
+    for arg in names {
+        let (name, value) = split_assignment("readonly", arg)?;
+        if let Some(value) = value {
+            if shell.get_var(name).is_none() {
+                shell.vars.insert(name.to_string(), String::new());
+            }
+            shell.set_var(name, value).map_err(|e| anyhow::anyhow!("readonly: {}", e))?;
+        }
+        shell.var_attrs.entry(name.to_string()).or_default().readonly = true;
+    }
     Ok(0)
 }
 
-fn builtin_activate(shell: &mut Shell, args: &[&str]) -> Result<i32> {
-    let venv_dir = if let Some(arg) = args.first() {
-        // Support both: `activate venv` and `activate venv\\Scripts\\activate`.
-        if let Some(dir) = venv::try_extract_venv_dir(&shell.cwd, arg) {
-            dir
+fn builtin_readonly(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    builtin_readonly_impl(shell, args, &mut out)
+}
+
+/// declare [-p] [-irx] [+ix] [NAME[=value]...] - set attributes and values, or print variables
+fn builtin_declare_impl(shell: &mut Shell, args: &[&str], out: &mut dyn Write, err: &mut dyn Write) -> Result<i32> {
+    let mut print = false;
+    let mut add = String::new();
+    let mut remove = String::new();
+    let mut names = Vec::new();
+    for (i, arg) in args.iter().enumerate() {
+        if *arg == "--" {
+            names.extend_from_slice(&args[i + 1..]);
+            break;
+        }
+        let (flags, adding) = match (arg.strip_prefix('-'), arg.strip_prefix('+')) {
+            (Some(flags), _) if !flags.is_empty() => (flags, true),
+            (_, Some(flags)) if !flags.is_empty() => (flags, false),
+            _ => {
+                names.extend_from_slice(&args[i..]);
+                break;
+            }
+        };
+        for flag in flags.chars() {
+            match (flag, adding) {
+                ('p', _) => print = true,
+                ('r', false) => anyhow::bail!("declare: +r: the readonly attribute cannot be removed"),
+                ('i' | 'r' | 'x', true) => add.push(flag),
+                ('i' | 'x', false) => remove.push(flag),
+                _ => anyhow::bail!("declare: {}{}: invalid option (usage: declare [-p] [-irx] [name[=value]...])", if adding { '-' } else { '+' }, flag),
+            }
+        }
+    }
+
+    if print || names.is_empty() {
+        let mut status = 0;
+        let selected: Vec<String> = if names.is_empty() {
+            variable_names(shell)
+                .into_iter()
+                .filter(|n| {
+                    let attrs = shell.var_attrs(n);
+                    (!add.contains('i') || attrs.integer)
+                        && (!add.contains('r') || attrs.readonly)
+                        && (!add.contains('x') || shell.is_exported(n))
+                })
+                .collect()
         } else {
-            let expanded = path::expand_env(arg);
-            path::resolve(&shell.cwd, &expanded)
+            names.iter().map(|n| n.to_string()).collect()
+        };
+        for name in &selected {
+            if shell.get_var(name).is_none() && !shell.var_attrs.contains_key(name) && !shell.arrays.contains_key(name) {
+                writeln!(err, "declare: {}: not found", name)?;
+                status = 1;
+                continue;
+            }
+            write_declaration(shell, name, out)?;
         }
-    } else {
-        venv::find_default_venv_dir(&shell.cwd)
-            .ok_or_else(|| anyhow::anyhow!("activate: no venv found (try: activate .venv or activate venv)"))?
-    };
+        return Ok(status);
+    }
 
-    venv::activate(shell, &venv_dir)?;
-    Ok(0)
+    let mut status = 0;
+    for arg in names {
+        let (name, value) = split_assignment("declare", arg)?;
+        if shell.var_attrs(name).readonly {
+            writeln!(err, "declare: {}: readonly variable", name)?;
+            status = 1;
+            continue;
+        }
+        if add.contains('i') || remove.contains('i') {
+            shell.var_attrs.entry(name.to_string()).or_default().integer = add.contains('i');
+        }
+        if let Some(value) = value {
+            // A new variable stays in the shell unless it is declared with -x.
+            if shell.get_var(name).is_none() && !add.contains('x') {
+                shell.vars.insert(name.to_string(), String::new());
+            }
+            if let Err(e) = shell.set_var(name, value) {
+                writeln!(err, "declare: {}", e)?;
+                status = 1;
+                continue;
+            }
+        }
+        if add.contains('x') {
+            shell.set_exported(name, true);
+        } else if remove.contains('x') {
+            shell.set_exported(name, false);
+        }
+        if add.contains('r') {
+            shell.var_attrs.entry(name.to_string()).or_default().readonly = true;
+        }
+    }
+    Ok(status)
 }
 
-fn builtin_deactivate(shell: &mut Shell) -> Result<i32> {
-    venv::deactivate(shell)?;
+fn builtin_declare(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut err = io::stderr();
+    builtin_declare_impl(shell, args, &mut out, &mut err)
+}
+
+/// Options understood by `set -o` / `set +o`
+const SET_OPTIONS: &[&str] = &["autocd", "devargs", "histexpand", "noclobber", "saferm"];
+
+fn set_option(shell: &mut Shell, name: &str, enable: bool) -> Result<()> {
+    match name {
+        "autocd" => shell.options.autocd = enable,
+        "devargs" => shell.options.devargs = enable,
+        "histexpand" => shell.options.histexpand = enable,
+        "noclobber" => shell.options.noclobber = enable,
+        "saferm" => shell.options.saferm = enable,
+        _ => anyhow::bail!("set: {}: invalid option name (known: {})", name, SET_OPTIONS.join(", ")),
+    }
+    Ok(())
+}
+
+fn get_option(shell: &Shell, name: &str) -> bool {
+    match name {
+        "autocd" => shell.options.autocd,
+        "devargs" => shell.options.devargs,
+        "histexpand" => shell.options.histexpand,
+        "noclobber" => shell.options.noclobber,
+        "saferm" => shell.options.saferm,
+        _ => false,
+    }
+}
+
+/// set - toggle shell options (`set -o noclobber`, `set +C`); other arguments behave like export
+fn builtin_set_impl(shell: &mut Shell, args: &[&str], out: &mut dyn Write) -> Result<i32> {
+    let is_option_syntax = args
+        .first()
+        .is_some_and(|a| matches!(*a, "-o" | "+o" | "-C" | "+C" | "-H" | "+H"));
+    if !is_option_syntax {
+        return builtin_export_impl(shell, args, out);
+    }
+
+    let mut i = 0usize;
+    while i < args.len() {
+        match args[i] {
+            "-C" => set_option(shell, "noclobber", true)?,
+            "+C" => set_option(shell, "noclobber", false)?,
+            "-H" => set_option(shell, "histexpand", true)?,
+            "+H" => set_option(shell, "histexpand", false)?,
+            flag @ ("-o" | "+o") => {
+                let enable = flag == "-o";
+                let Some(name) = args.get(i + 1) else {
+                    // `set -o` lists options; `set +o` prints them as re-runnable commands.
+                    for name in SET_OPTIONS {
+                        let on = get_option(shell, name);
+                        if enable {
+                            writeln!(out, "{:<15} {}", name, if on { "on" } else { "off" })?;
+                        } else {
+                            writeln!(out, "set {}o {}", if on { "-" } else { "+" }, name)?;
+                        }
+                    }
+                    return Ok(0);
+                };
+                set_option(shell, name, enable)?;
+                i += 1;
+            }
+            other => anyhow::bail!("set: {}: invalid option", other),
+        }
+        i += 1;
+    }
+
     Ok(0)
 }
 
-fn builtin_help() -> Result<i32> {
+fn builtin_set(shell: &mut Shell, args: &[&str]) -> Result<i32> {
     let stdout = io::stdout();
     let mut out = stdout.lock();
-    builtin_help_impl(&mut out)
+    builtin_set_impl(shell, args, &mut out)
 }
 
-/// jobs - list background jobs
-fn builtin_jobs_impl(shell: &Shell, out: &mut dyn Write) -> Result<i32> {
-    let jobs = shell.tasks.list();
-    if jobs.is_empty() {
-        writeln!(out, "No background jobs")?;
-    } else {
-        for (id, status, cmd) in jobs {
-            writeln!(out, "[{}] {} {}", id, status, cmd)?;
+/// env / printenv - show environment variables
+fn builtin_env_impl(args: &[&str], out: &mut dyn Write) -> Result<i32> {
+    if args.is_empty() {
+        for (key, value) in env::vars() {
+            writeln!(out, "{}={}", key, value)?;
+        }
+        return Ok(0);
+    }
+
+    for key in args {
+        if let Some(val) = env::var_os(key) {
+            writeln!(out, "{}", val.to_string_lossy())?;
         }
     }
     Ok(0)
 }
 
-fn builtin_jobs(shell: &Shell) -> Result<i32> {
+fn builtin_env(args: &[&str]) -> Result<i32> {
     let stdout = io::stdout();
     let mut out = stdout.lock();
-    builtin_jobs_impl(shell, &mut out)
+    builtin_env_impl(args, &mut out)
+}
+
+/// which - locate command
+fn builtin_which_impl(args: &[&str], out: &mut dyn Write) -> Result<i32> {
+    let mut all_found = true;
+    for name in args {
+        match which::which(name) {
+            Ok(path) => writeln!(out, "{}", path.display())?,
+            Err(_) => {
+                if !busybox::looks_like_path(name) {
+                    let applet = busybox::normalize_applet_name(name);
+                    if busybox::has_applet(&applet) {
+                        if let Some(bb) = busybox::get() {
+                            writeln!(out, "{} (busybox applet: {})", bb.path.display(), applet)?;
+                            continue;
+                        }
+                    }
+                }
+
+                all_found = false;
+                writeln!(out, "{}: not found", name)?;
+            }
+        }
+    }
+    Ok(if all_found { 0 } else { 1 })
 }
 
-fn parse_job_id(arg: &str) -> Result<TaskId> {
-    arg.parse::<TaskId>()
-        .with_context(|| format!("invalid job id: {}", arg))
+fn builtin_which(args: &[&str]) -> Result<i32> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    builtin_which_impl(args, &mut out)
 }
 
-fn last_running_job_id(shell: &Shell) -> Option<TaskId> {
-    let mut last: Option<TaskId> = None;
-    for (id, _, _) in shell.tasks.list() {
-        if matches!(shell.tasks.status(id), Some(TaskStatus::Running)) {
-            last = Some(id);
+/// Parsed `read` options.
+#[derive(Debug, Default)]
+struct ReadOptions {
+    raw: bool,
+    prompt: Option<String>,
+    silent: bool,
+    timeout: Option<std::time::Duration>,
+    max_chars: Option<usize>,
+    delim: Option<char>,
+    array: Option<String>,
+    names: Vec<String>,
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_read_args(args: &[&str]) -> Result<ReadOptions> {
+    let mut opts = ReadOptions::default();
+    let mut i = 0usize;
+    while i < args.len() {
+        let arg = args[i];
+        if arg == "--" {
+            i += 1;
+            break;
+        }
+        if !arg.starts_with('-') || arg == "-" {
+            break;
+        }
+        let flags: Vec<char> = arg[1..].chars().collect();
+        for (pos, flag) in flags.iter().enumerate() {
+            match flag {
+                'r' => opts.raw = true,
+                's' => opts.silent = true,
+                'p' | 't' | 'n' | 'd' | 'a' => {
+                    // The value is the rest of this argument or the next one (`-n1`, `-n 1`).
+                    let attached: String = flags[pos + 1..].iter().collect();
+                    let value = if !attached.is_empty() {
+                        attached
+                    } else {
+                        i += 1;
+                        match args.get(i) {
+                            Some(value) => value.to_string(),
+                            None => anyhow::bail!("read: -{}: option requires an argument", flag),
+                        }
+                    };
+                    match flag {
+                        'p' => opts.prompt = Some(value),
+                        't' => {
+                            let secs: f64 = value
+                                .parse()
+                                .ok()
+                                .filter(|s: &f64| *s >= 0.0)
+                                .ok_or_else(|| anyhow::anyhow!("read: {}: invalid timeout specification", value))?;
+                            opts.timeout = Some(std::time::Duration::from_secs_f64(secs));
+                        }
+                        'n' => {
+                            let n = value
+                                .parse()
+                                .map_err(|_| anyhow::anyhow!("read: {}: invalid number", value))?;
+                            opts.max_chars = Some(n);
+                        }
+                        'd' => opts.delim = Some(value.chars().next().unwrap_or('\0')),
+                        _ => opts.array = Some(value),
+                    }
+                    break;
+                }
+                _ => anyhow::bail!(
+                    "read: -{}: invalid option (usage: read [-rs] [-p prompt] [-t secs] [-n N] [-d delim] [-a arr] [name...])",
+                    flag
+                ),
+            }
+        }
+        i += 1;
+    }
+    opts.names = args[i..].iter().map(|s| s.to_string()).collect();
+    for name in opts.names.iter().chain(opts.array.iter()) {
+        if !is_identifier(name) {
+            anyhow::bail!("read: `{}': not a valid identifier", name);
         }
     }
-    last
+    if opts.delim.is_some_and(|d| !d.is_ascii()) {
+        anyhow::bail!("read: -d: delimiter must be an ASCII character");
+    }
+    Ok(opts)
 }
 
-/// fg - bring a job to foreground (best-effort: just waits for it)
-fn builtin_fg(shell: &mut Shell, args: &[&str]) -> Result<i32> {
-    let id = if args.is_empty() {
-        last_running_job_id(shell).ok_or_else(|| anyhow::anyhow!("fg: no jobs"))?
-    } else {
-        parse_job_id(args[0])?
-    };
+/// Read one record from a stream: up to `delim` (consumed, not returned) or `max_chars`
+/// characters. The flag is false when input ended first; `None` means nothing was left.
+fn read_record(input: &mut dyn BufRead, delim: u8, max_chars: Option<usize>) -> io::Result<Option<(Vec<u8>, bool)>> {
+    let mut record = Vec::new();
+    let mut chars = 0usize;
+    let mut saw_input = false;
+    loop {
+        let buf = input.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(saw_input.then_some((record, false)));
+        }
+        saw_input = true;
+        let mut used = buf.len();
+        let mut done = false;
+        for (i, &b) in buf.iter().enumerate() {
+            if b == delim {
+                used = i + 1;
+                done = true;
+                break;
+            }
+            // Count characters at UTF-8 lead bytes; stop before the first one over the limit.
+            if b & 0xC0 != 0x80 {
+                if max_chars == Some(chars) {
+                    used = i;
+                    done = true;
+                    break;
+                }
+                chars += 1;
+            }
+            record.push(b);
+        }
+        input.consume(used);
+        if done {
+            return Ok(Some((record, true)));
+        }
+    }
+}
 
-    let status = shell
-        .tasks
-        .wait_and_remove(id)
-        .ok_or_else(|| anyhow::anyhow!("fg: {}: no such job", id))?;
+/// Split `line` into at most `max_fields` fields on `$IFS` (the last field keeps the rest of
+/// the line). Without `-r`, a backslash quotes the next character.
+fn split_ifs(line: &str, ifs: &str, max_fields: Option<usize>, raw: bool) -> Vec<String> {
+    // (char, escaped)
+    let mut chars: Vec<(char, bool)> = Vec::with_capacity(line.len());
+    let mut iter = line.chars();
+    while let Some(c) = iter.next() {
+        match (c, raw) {
+            ('\\', false) => {
+                if let Some(next) = iter.next() {
+                    chars.push((next, true));
+                }
+            }
+            _ => chars.push((c, false)),
+        }
+    }
 
-    match status {
-        TaskStatus::Completed(code) => Ok(code),
-        TaskStatus::Failed(msg) => anyhow::bail!("fg: {}", msg),
-        TaskStatus::Running => Ok(0),
+    let is_ifs = |&(c, escaped): &(char, bool)| !escaped && ifs.contains(c);
+    let is_ifs_space = |&(c, escaped): &(char, bool)| !escaped && ifs.contains(c) && c.is_whitespace();
+    let text = |slice: &[(char, bool)]| slice.iter().map(|(c, _)| *c).collect::<String>();
+
+    let mut fields = Vec::new();
+    let mut pos = 0usize;
+    while pos < chars.len() && is_ifs_space(&chars[pos]) {
+        pos += 1;
+    }
+    while pos < chars.len() {
+        if max_fields.is_some_and(|max| fields.len() + 1 == max) {
+            let mut end = chars.len();
+            while end > pos && is_ifs_space(&chars[end - 1]) {
+                end -= 1;
+            }
+            fields.push(text(&chars[pos..end]));
+            return fields;
+        }
+        let start = pos;
+        while pos < chars.len() && !is_ifs(&chars[pos]) {
+            pos += 1;
+        }
+        fields.push(text(&chars[start..pos]));
+        // One delimiter: surrounding IFS whitespace plus at most one other IFS character.
+        while pos < chars.len() && is_ifs_space(&chars[pos]) {
+            pos += 1;
+        }
+        if pos < chars.len() && is_ifs(&chars[pos]) && !is_ifs_space(&chars[pos]) {
+            pos += 1;
+            while pos < chars.len() && is_ifs_space(&chars[pos]) {
+                pos += 1;
+            }
+        }
     }
+    fields
 }
 
-/// wait - wait for background job(s)
-fn builtin_wait(shell: &mut Shell, args: &[&str]) -> Result<i32> {
-    let mut ids: Vec<TaskId> = if args.is_empty() {
-        shell.tasks.list().into_iter().map(|(id, _, _)| id).collect()
+/// read - read a line from stdin into variables (`REPLY` when no names are given)
+///
+/// Returns 1 at end of input, 142 on timeout (`-t`, console only) and 130 on Ctrl+C.
+fn builtin_read_impl(
+    shell: &mut Shell,
+    args: &[&str],
+    stdin: &mut dyn BufRead,
+    err: &mut dyn Write,
+    stdin_tty: bool,
+) -> Result<i32> {
+    let opts = parse_read_args(args)?;
+    for name in opts.names.iter().chain(&opts.array) {
+        shell.check_assignable(name).map_err(|e| anyhow::anyhow!("read: {}", e))?;
+    }
+    let delim = opts.delim.unwrap_or('\n');
+
+    if let (Some(prompt), true) = (&opts.prompt, stdin_tty) {
+        write!(err, "{}", prompt)?;
+        err.flush()?;
+    }
+
+    let mut status = 0;
+    let mut line = String::new();
+    let console = stdin_tty && (opts.silent || opts.timeout.is_some() || opts.max_chars.is_some() || delim != '\n');
+    if console {
+        match super::input::read_console_raw(opts.silent, opts.timeout, opts.max_chars, delim)? {
+            RawRead::Done(text) => line = text,
+            RawRead::Eof(text) => {
+                line = text;
+                status = 1;
+            }
+            RawRead::TimedOut(text) => {
+                line = text;
+                status = 142;
+            }
+            RawRead::Interrupted => {
+                writeln!(err)?;
+                return Ok(130);
+            }
+        }
     } else {
-        args.iter().map(|a| parse_job_id(a)).collect::<Result<Vec<_>>>()?
-    };
+        loop {
+            let Some((record, complete)) = read_record(stdin, delim as u8, opts.max_chars)? else {
+                status = 1;
+                break;
+            };
+            if !complete {
+                status = 1;
+            }
+            let mut text = String::from_utf8_lossy(&record).into_owned();
+            if delim == '\n' && text.ends_with('\r') {
+                text.pop();
+            }
+            // Without -r a trailing backslash continues the record on the next line.
+            if complete && !opts.raw && opts.max_chars.is_none() && text.ends_with('\\') && !text.ends_with("\\\\") {
+                text.pop();
+                line.push_str(&text);
+                continue;
+            }
+            line.push_str(&text);
+            break;
+        }
+    }
 
-    ids.sort_unstable();
-    ids.dedup();
+    let ifs = shell.get_var("IFS").unwrap_or_else(|| " \t\n".to_string());
+    if let Some(array) = &opts.array {
+        shell.set_array(array, split_ifs(&line, &ifs, None, opts.raw)).map_err(|e| anyhow::anyhow!("read: {}", e))?;
+    } else if opts.names.is_empty() {
+        let value = if opts.raw { line } else { split_ifs(&line, "", Some(1), false).concat() };
+        shell.set_var("REPLY", &value).map_err(|e| anyhow::anyhow!("read: {}", e))?;
+    } else {
+        let mut fields = split_ifs(&line, &ifs, Some(opts.names.len()), opts.raw).into_iter();
+        for name in &opts.names {
+            shell.set_var(name, &fields.next().unwrap_or_default()).map_err(|e| anyhow::anyhow!("read: {}", e))?;
+        }
+    }
+    Ok(status)
+}
 
-    let mut last_code = 0;
-    for id in ids {
-        let status = shell
-            .tasks
-            .wait_and_remove(id)
-            .ok_or_else(|| anyhow::anyhow!("wait: {}: no such job", id))?;
-        match status {
-            TaskStatus::Completed(code) => last_code = code,
-            TaskStatus::Failed(msg) => anyhow::bail!("wait: {}", msg),
-            TaskStatus::Running => {}
+fn builtin_read(shell: &mut Shell, args: &[&str]) -> Result<i32> {
+    use std::io::IsTerminal;
+    let stdin = io::stdin();
+    let stdin_tty = stdin.is_terminal();
+    let mut input = stdin.lock();
+    let mut err = io::stderr();
+    builtin_read_impl(shell, args, &mut input, &mut err, stdin_tty)
+}
+
+/// mkdir - create directory
+fn builtin_mkdir(shell: &Shell, args: &[&str]) -> Result<i32> {
+    let create_parents = args.iter().any(|a| *a == "-p");
+
+    for arg in args.iter().filter(|a| !a.starts_with('-')) {
+        let expanded = path::expand_env(arg);
+        let target = path::resolve_fs(&shell.cwd, &expanded);
+
+        // Check for Windows reserved device names
+        if path::is_windows_reserved_name(&target) {
+            anyhow::bail!("mkdir: {}", path::reserved_name_error(arg));
+        }
+
+        if create_parents {
+            fs::create_dir_all(&target)?;
+        } else {
+            fs::create_dir(&target)?;
         }
     }
 
-    Ok(last_code)
+    Ok(0)
 }
 
-/// kill - terminate a background job (Windows: taskkill /T)
-fn builtin_kill(shell: &mut Shell, args: &[&str]) -> Result<i32> {
-    if args.is_empty() {
-        anyhow::bail!("kill: missing job id");
+/// rm - remove file/directory (`--trash` or `set -o saferm` moves it to the trash store)
+fn builtin_rm(shell: &Shell, args: &[&str]) -> Result<i32> {
+    let recursive = args.iter().any(|a| *a == "-r" || *a == "-rf");
+    let force = args.iter().any(|a| *a == "-f" || *a == "-rf");
+    let use_trash = !args.contains(&"--no-trash") && (shell.options.saferm || args.contains(&"--trash"));
+    let store = if use_trash { Some(TrashStore::open()?) } else { None };
+    let preserve_root = !args.contains(&"--no-preserve-root");
+
+    for arg in args.iter().filter(|a| !a.starts_with('-')) {
+        let expanded = path::expand_env(arg);
+        let target = path::resolve_fs(&shell.cwd, &expanded);
+
+        if preserve_root && (recursive || !target.is_dir()) && target.exists() {
+            if let Some(reason) = guard::check_remove(shell, &target) {
+                guard::confirm(shell, "rm", "remove", &target, &reason)?;
+            }
+        }
+
+        if target.is_dir() {
+            if recursive {
+                match &store {
+                    Some(store) => {
+                        store.put(&target)?;
+                    }
+                    None => fs::remove_dir_all(&target)?,
+                }
+            } else {
+                if !force {
+                    anyhow::bail!("rm: {}: is a directory (use -r)", target.display());
+                }
+            }
+        } else if target.exists() {
+            match &store {
+                Some(store) => {
+                    store.put(&target)?;
+                }
+                None => fs::remove_file(&target)?,
+            }
+        } else if !force {
+            anyhow::bail!("rm: {}: No such file or directory", target.display());
+        }
     }
-    let id = parse_job_id(args[0])?;
-    shell.tasks.kill(id)?;
+
     Ok(0)
 }
 
-fn escape_single_quotes(value: &str) -> String {
-    value.replace('\'', r#"'\''"#)
+/// Options shared by `cp` and `mv`
+#[derive(Default)]
+struct TransferOptions {
+    recursive: bool,
+    interactive: bool,
+    no_clobber: bool,
+    update: bool,
+    verbose: bool,
+    preserve: bool,
+    /// Skip the protected-path guard (`mv` only)
+    no_preserve_root: bool,
 }
 
-/// alias - define or show aliases
-fn builtin_alias_impl(shell: &mut Shell, args: &[&str], out: &mut dyn Write) -> Result<i32> {
-    if args.is_empty() {
-        let mut keys: Vec<&String> = shell.aliases.keys().collect();
-        keys.sort();
-        for k in keys {
-            let v = shell.aliases.get(k).map(|s| s.as_str()).unwrap_or_default();
-            writeln!(out, "alias {}='{}'", k, escape_single_quotes(v))?;
-        }
-        return Ok(0);
-    }
+fn parse_transfer_args<'a>(cmd: &str, args: &[&'a str]) -> Result<(TransferOptions, Vec<&'a str>)> {
+    let mut opts = TransferOptions::default();
+    let mut paths = Vec::new();
 
-    for arg in args {
-        if let Some((name, value)) = arg.split_once('=') {
-            if name.is_empty() {
-                anyhow::bail!("alias: invalid name");
+    for (i, arg) in args.iter().enumerate() {
+        if *arg == "--" {
+            paths.extend_from_slice(&args[i + 1..]);
+            break;
+        }
+        if let Some(long) = arg.strip_prefix("--") {
+            match long {
+                "recursive" => opts.recursive = true,
+                "interactive" => {
+                    opts.interactive = true;
+                    opts.no_clobber = false;
+                }
+                "no-clobber" => {
+                    opts.no_clobber = true;
+                    opts.interactive = false;
+                }
+                "force" => {
+                    opts.interactive = false;
+                    opts.no_clobber = false;
+                }
+                "update" => opts.update = true,
+                "verbose" => opts.verbose = true,
+                "preserve" => opts.preserve = true,
+                "no-preserve-root" => opts.no_preserve_root = true,
+                _ => anyhow::bail!("{}: unrecognized option '{}'", cmd, arg),
+            }
+        } else if arg.starts_with('-') && arg.len() > 1 {
+            for ch in arg.chars().skip(1) {
+                match ch {
+                    'r' | 'R' => opts.recursive = true,
+                    // Later flags win, like GNU coreutils
+                    'i' => {
+                        opts.interactive = true;
+                        opts.no_clobber = false;
+                    }
+                    'n' => {
+                        opts.no_clobber = true;
+                        opts.interactive = false;
+                    }
+                    'f' => {
+                        opts.interactive = false;
+                        opts.no_clobber = false;
+                    }
+                    'u' => opts.update = true,
+                    'v' => opts.verbose = true,
+                    'p' => opts.preserve = true,
+                    _ => anyhow::bail!("{}: invalid option -- '{}'", cmd, ch),
+                }
             }
-            shell.aliases.insert(name.to_string(), value.to_string());
         } else {
-            let Some(value) = shell.aliases.get(*arg) else {
-                anyhow::bail!("alias: {}: not found", arg);
-            };
-            writeln!(out, "alias {}='{}'", arg, escape_single_quotes(value))?;
+            paths.push(*arg);
         }
     }
 
-    Ok(0)
+    Ok((opts, paths))
 }
 
-fn builtin_alias(shell: &mut Shell, args: &[&str]) -> Result<i32> {
-    let stdout = io::stdout();
-    let mut out = stdout.lock();
-    builtin_alias_impl(shell, args, &mut out)
-}
+/// Resolve `SOURCE... DEST` operands into (source label, source path) pairs plus the destination.
+///
+/// Sources are glob-expanded; several sources require an existing destination directory.
+fn resolve_transfer_operands(
+    cmd: &str,
+    shell: &Shell,
+    paths: &[&str],
+) -> Result<(Vec<(String, std::path::PathBuf)>, std::path::PathBuf)> {
+    let Some((dest_arg, src_args)) = paths.split_last().filter(|(_, srcs)| !srcs.is_empty()) else {
+        anyhow::bail!("{}: missing destination", cmd);
+    };
 
-/// unalias - remove aliases
-fn builtin_unalias(shell: &mut Shell, args: &[&str]) -> Result<i32> {
-    if args.is_empty() {
-        anyhow::bail!("unalias: missing operand");
-    }
+    let dest = path::resolve_fs(&shell.cwd, &path::expand_env(dest_arg));
 
-    if args.iter().any(|a| *a == "-a") {
-        shell.aliases.clear();
-        return Ok(0);
+    // Check destination for reserved names
+    if path::is_windows_reserved_name(&dest) {
+        anyhow::bail!("{}: {}", cmd, path::reserved_name_error(dest_arg));
     }
 
-    for name in args {
-        if shell.aliases.remove(*name).is_none() {
-            anyhow::bail!("unalias: {}: not found", name);
+    let mut sources = Vec::new();
+    for src_arg in src_args {
+        for src_str in expand_glob(&shell.cwd, &path::expand_env(src_arg)) {
+            let src = path::resolve_fs(&shell.cwd, &src_str);
+            sources.push((src_str, src));
         }
     }
 
-    Ok(0)
+    if sources.len() > 1 && !dest.is_dir() {
+        anyhow::bail!("{}: target '{}' is not a directory", cmd, dest_arg);
+    }
+
+    Ok((sources, dest))
 }
 
-/// export - set environment variable
-fn builtin_export_impl(args: &[&str], out: &mut dyn Write) -> Result<i32> {
-    if args.is_empty() {
-        // Show all environment variables
-        for (key, value) in env::vars() {
-            writeln!(out, "{}={}", key, value)?;
-        }
-        return Ok(0);
+/// Where `src` lands: inside `dest` if it is a directory, otherwise `dest` itself.
+fn transfer_target(src: &Path, dest: &Path) -> Result<std::path::PathBuf> {
+    if dest.is_dir() {
+        let name = src.file_name().ok_or_else(|| anyhow::anyhow!("cannot get filename"))?;
+        Ok(dest.join(name))
+    } else {
+        Ok(dest.to_path_buf())
     }
+}
 
-    for arg in args {
-        if let Some((key, value)) = arg.split_once('=') {
-            // SAFETY: We're a shell, setting env vars is expected behavior
-            unsafe { env::set_var(key, value); }
-        } else {
-            // Just the name, show value
-            if let Ok(value) = env::var(arg) {
-                writeln!(out, "{}={}", arg, value)?;
+/// Apply `-n`, `-u` and `-i` to an existing target. Returns false to skip it.
+fn should_overwrite(
+    cmd: &str,
+    opts: &TransferOptions,
+    src: &Path,
+    target: &Path,
+    stdin: &mut dyn BufRead,
+    err: &mut dyn Write,
+) -> Result<bool> {
+    let Ok(target_meta) = fs::symlink_metadata(target) else {
+        return Ok(true);
+    };
+    if opts.no_clobber {
+        return Ok(false);
+    }
+    if opts.update {
+        let src_time = fs::metadata(src).and_then(|m| m.modified()).ok();
+        if let (Some(src_time), Ok(target_time)) = (src_time, target_meta.modified()) {
+            if src_time <= target_time {
+                return Ok(false);
             }
         }
     }
-
-    Ok(0)
+    if opts.interactive {
+        write!(err, "{}: overwrite '{}'? ", cmd, target.display())?;
+        err.flush()?;
+        let mut answer = String::new();
+        stdin.read_line(&mut answer)?;
+        return Ok(answer.trim_start().starts_with(['y', 'Y']));
+    }
+    Ok(true)
 }
 
-fn builtin_export(args: &[&str]) -> Result<i32> {
-    let stdout = io::stdout();
-    let mut out = stdout.lock();
-    builtin_export_impl(args, &mut out)
-}
+/// cp - copy files and directories
+fn builtin_cp_impl(
+    shell: &Shell,
+    args: &[&str],
+    stdin: &mut dyn BufRead,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<i32> {
+    let (opts, paths) = parse_transfer_args("cp", args)?;
+    let (sources, dest) = resolve_transfer_operands("cp", shell, &paths)?;
 
-/// env / printenv - show environment variables
-fn builtin_env_impl(args: &[&str], out: &mut dyn Write) -> Result<i32> {
-    if args.is_empty() {
-        for (key, value) in env::vars() {
-            writeln!(out, "{}={}", key, value)?;
+    let mut exit_code = 0;
+    for (label, src) in sources {
+        if !src.exists() && fs::symlink_metadata(&src).is_err() {
+            writeln!(err, "cp: cannot stat '{}': No such file or directory", label)?;
+            exit_code = 1;
+            continue;
+        }
+        if src.is_dir() && !opts.recursive {
+            writeln!(err, "cp: {}: is a directory (use -r)", label)?;
+            exit_code = 1;
+            continue;
         }
-        return Ok(0);
-    }
 
-    for key in args {
-        if let Some(val) = env::var_os(key) {
-            writeln!(out, "{}", val.to_string_lossy())?;
+        let target = transfer_target(&src, &dest)?;
+        if target.starts_with(&src) && src.is_dir() {
+            writeln!(err, "cp: cannot copy '{}' into itself", label)?;
+            exit_code = 1;
+            continue;
+        }
+        if !src.is_dir() && !should_overwrite("cp", &opts, &src, &target, stdin, err)? {
+            continue;
+        }
+
+        match copy_entry(&src, &target, opts.preserve) {
+            Ok(()) => {
+                if opts.verbose {
+                    writeln!(out, "'{}' -> '{}'", src.display(), target.display())?;
+                }
+            }
+            Err(e) => {
+                writeln!(err, "cp: {:#}", e)?;
+                exit_code = 1;
+            }
         }
     }
-    Ok(0)
+
+    Ok(exit_code)
 }
 
-fn builtin_env(args: &[&str]) -> Result<i32> {
+fn builtin_cp(shell: &Shell, args: &[&str]) -> Result<i32> {
+    let stdin = io::stdin();
     let stdout = io::stdout();
+    let stderr = io::stderr();
+    let mut stdin = stdin.lock();
     let mut out = stdout.lock();
-    builtin_env_impl(args, &mut out)
+    let mut err = stderr.lock();
+    builtin_cp_impl(shell, args, &mut stdin, &mut out, &mut err)
 }
 
-/// which - locate command
-fn builtin_which_impl(args: &[&str], out: &mut dyn Write) -> Result<i32> {
-    let mut all_found = true;
-    for name in args {
-        match which::which(name) {
-            Ok(path) => writeln!(out, "{}", path.display())?,
-            Err(_) => {
-                if !busybox::looks_like_path(name) {
-                    let applet = busybox::normalize_applet_name(name);
-                    if busybox::has_applet(&applet) {
-                        if let Some(bb) = busybox::get() {
-                            writeln!(out, "{} (busybox applet: {})", bb.path.display(), applet)?;
-                            continue;
-                        }
-                    }
-                }
+/// mv - move/rename
+fn builtin_mv_impl(
+    shell: &Shell,
+    args: &[&str],
+    stdin: &mut dyn BufRead,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<i32> {
+    let (opts, paths) = parse_transfer_args("mv", args)?;
+    let (sources, dest) = resolve_transfer_operands("mv", shell, &paths)?;
 
-                all_found = false;
-                writeln!(out, "{}: not found", name)?;
+    let mut exit_code = 0;
+    for (label, src) in sources {
+        if fs::symlink_metadata(&src).is_err() {
+            writeln!(err, "mv: cannot stat '{}': No such file or directory", label)?;
+            exit_code = 1;
+            continue;
+        }
+
+        if !opts.no_preserve_root {
+            if let Some(reason) = guard::check_remove(shell, &src) {
+                guard::confirm(shell, "mv", "move", &src, &reason)?;
+            }
+        }
+
+        let target = transfer_target(&src, &dest)?;
+        if !should_overwrite("mv", &opts, &src, &target, stdin, err)? {
+            continue;
+        }
+
+        match move_entry(&src, &target) {
+            Ok(()) => {
+                if opts.verbose {
+                    writeln!(out, "renamed '{}' -> '{}'", src.display(), target.display())?;
+                }
+            }
+            Err(e) => {
+                writeln!(err, "mv: {:#}", e)?;
+                exit_code = 1;
             }
         }
     }
-    Ok(if all_found { 0 } else { 1 })
+
+    Ok(exit_code)
 }
 
-fn builtin_which(args: &[&str]) -> Result<i32> {
+fn builtin_mv(shell: &Shell, args: &[&str]) -> Result<i32> {
+    let stdin = io::stdin();
     let stdout = io::stdout();
+    let stderr = io::stderr();
+    let mut stdin = stdin.lock();
     let mut out = stdout.lock();
-    builtin_which_impl(args, &mut out)
+    let mut err = stderr.lock();
+    builtin_mv_impl(shell, args, &mut stdin, &mut out, &mut err)
 }
 
-/// mkdir - create directory
-fn builtin_mkdir(shell: &Shell, args: &[&str]) -> Result<i32> {
-    let create_parents = args.iter().any(|a| *a == "-p");
-
-    for arg in args.iter().filter(|a| !a.starts_with('-')) {
-        let expanded = path::expand_env(arg);
-        let target = path::resolve_fs(&shell.cwd, &expanded);
-
-        // Check for Windows reserved device names
-        if path::is_windows_reserved_name(&target) {
-            anyhow::bail!("mkdir: {}", path::reserved_name_error(arg));
+/// ln [-s] [-f] [-n] [-T] [-v] [--junction] TARGET [LINK] / TARGET... DIR
+fn builtin_ln_impl(shell: &Shell, args: &[&str], out: &mut dyn Write, err: &mut dyn Write) -> Result<i32> {
+    let mut kind = LinkKind::Hard;
+    let (mut force, mut no_deref, mut no_target_dir, mut verbose) = (false, false, false, false);
+    let mut operands = Vec::new();
+    for (i, arg) in args.iter().enumerate() {
+        if *arg == "--" {
+            operands.extend_from_slice(&args[i + 1..]);
+            break;
         }
-
-        if create_parents {
-            fs::create_dir_all(&target)?;
+        if let Some(long) = arg.strip_prefix("--") {
+            match long {
+                "symbolic" => kind = LinkKind::Symbolic,
+                "junction" => kind = LinkKind::Junction,
+                "force" => force = true,
+                "no-dereference" => no_deref = true,
+                "no-target-directory" => no_target_dir = true,
+                "verbose" => verbose = true,
+                _ => anyhow::bail!("ln: unrecognized option '{}'", arg),
+            }
+        } else if arg.starts_with('-') && arg.len() > 1 {
+            for ch in arg.chars().skip(1) {
+                match ch {
+                    's' => kind = LinkKind::Symbolic,
+                    'f' => force = true,
+                    'n' => no_deref = true,
+                    'T' => no_target_dir = true,
+                    'v' => verbose = true,
+                    _ => anyhow::bail!("ln: invalid option -- '{}'", ch),
+                }
+            }
         } else {
-            fs::create_dir(&target)?;
+            operands.push(*arg);
         }
     }
 
-    Ok(0)
-}
+    let operands: Vec<String> = operands.iter().map(|a| path::expand_env(a)).collect();
+    let (targets, dest) = match operands.as_slice() {
+        [] => anyhow::bail!("ln: missing file operand"),
+        [target] => (std::slice::from_ref(target), None),
+        [targets @ .., dest] => (targets, Some(dest.as_str())),
+    };
 
-/// rm - remove file/directory
-fn builtin_rm(shell: &Shell, args: &[&str]) -> Result<i32> {
-    let recursive = args.iter().any(|a| *a == "-r" || *a == "-rf");
-    let force = args.iter().any(|a| *a == "-f" || *a == "-rf");
+    // With -n a link to a directory is replaced rather than linked into.
+    let dest_path = dest.map(|d| path::resolve_fs(&shell.cwd, d));
+    let dest_is_dir = match &dest_path {
+        Some(p) if no_deref => fs::symlink_metadata(p).is_ok_and(|m| m.is_dir()),
+        Some(p) => p.is_dir(),
+        None => true,
+    };
+    if no_target_dir && targets.len() > 1 {
+        anyhow::bail!("ln: extra operand '{}'", operands[2]);
+    }
+    if targets.len() > 1 && !dest_is_dir {
+        anyhow::bail!("ln: target '{}' is not a directory", dest.unwrap_or_default());
+    }
 
-    for arg in args.iter().filter(|a| !a.starts_with('-')) {
-        let expanded = path::expand_env(arg);
-        let target = path::resolve_fs(&shell.cwd, &expanded);
+    let mut exit_code = 0;
+    for target in targets {
+        let resolved = path::resolve_fs(&shell.cwd, target);
+        let (link, label) = match (dest, &dest_path) {
+            (Some(dest), Some(dest_path)) if no_target_dir || !dest_is_dir => (dest_path.clone(), dest.to_string()),
+            _ => {
+                let Some(name) = Path::new(target).file_name() else {
+                    writeln!(err, "ln: '{}': cannot derive a link name", target)?;
+                    exit_code = 1;
+                    continue;
+                };
+                let dir = dest_path.clone().unwrap_or_else(|| shell.cwd.clone());
+                let label = match dest {
+                    Some(dest) => Path::new(dest).join(name).display().to_string(),
+                    None => name.to_string_lossy().into_owned(),
+                };
+                (dir.join(name), label)
+            }
+        };
+
+        match kind {
+            LinkKind::Hard if resolved.is_dir() => {
+                writeln!(err, "ln: '{}': hard link not allowed for directory", target)?;
+                exit_code = 1;
+                continue;
+            }
+            LinkKind::Junction if !resolved.is_dir() => {
+                writeln!(err, "ln: '{}': junction target must be an existing directory", target)?;
+                exit_code = 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        if let Ok(meta) = fs::symlink_metadata(&link) {
+            if !force {
+                writeln!(err, "ln: failed to create {} '{}': File exists", kind.describe(), label)?;
+                exit_code = 1;
+                continue;
+            }
+            if meta.is_dir() && !meta.file_type().is_symlink() {
+                writeln!(err, "ln: '{}': cannot overwrite directory", label)?;
+                exit_code = 1;
+                continue;
+            }
+            if let Err(e) = link::remove_existing(&link) {
+                writeln!(err, "ln: cannot remove '{}': {}", label, e)?;
+                exit_code = 1;
+                continue;
+            }
+        }
 
-        if target.is_dir() {
-            if recursive {
-                fs::remove_dir_all(&target)?;
-            } else {
-                if !force {
-                    anyhow::bail!("rm: {}: is a directory (use -r)", target.display());
+        // Symlinks keep the target as written; the others point at the resolved path.
+        let source = if kind == LinkKind::Symbolic { Path::new(target) } else { resolved.as_path() };
+        match link::create(kind, source, &link) {
+            Ok(()) => {
+                if verbose {
+                    writeln!(out, "'{}' -> '{}'", label, source.display())?;
                 }
             }
-        } else if target.exists() {
-            fs::remove_file(&target)?;
-        } else if !force {
-            anyhow::bail!("rm: {}: No such file or directory", target.display());
+            Err(e) => {
+                writeln!(err, "ln: failed to create {} '{}' -> '{}': {}", kind.describe(), label, target, e)?;
+                exit_code = 1;
+            }
         }
     }
 
-    Ok(0)
+    Ok(exit_code)
 }
 
-/// cp - copy file
-fn builtin_cp(shell: &Shell, args: &[&str]) -> Result<i32> {
-    if args.len() < 2 {
-        anyhow::bail!("cp: missing destination");
-    }
-
-    let recursive = args.iter().any(|a| *a == "-r");
-    let paths: Vec<_> = args.iter().filter(|a| !a.starts_with('-')).collect();
-
-    if paths.len() < 2 {
-        anyhow::bail!("cp: missing destination");
-    }
-
-    let dest = path::resolve_fs(&shell.cwd, &path::expand_env(paths[paths.len() - 1]));
-
-    // Check destination for reserved names
-    if path::is_windows_reserved_name(&dest) {
-        anyhow::bail!("cp: {}", path::reserved_name_error(paths[paths.len() - 1]));
-    }
-
-    for src_arg in &paths[..paths.len() - 1] {
-        let src = path::resolve_fs(&shell.cwd, &path::expand_env(src_arg));
+fn builtin_ln(shell: &Shell, args: &[&str]) -> Result<i32> {
+    let stdout = io::stdout();
+    let stderr = io::stderr();
+    let mut out = stdout.lock();
+    let mut err = stderr.lock();
+    builtin_ln_impl(shell, args, &mut out, &mut err)
+}
 
-        if src.is_dir() {
-            if recursive {
-                copy_dir_all(&src, &dest)?;
-            } else {
-                anyhow::bail!("cp: {}: is a directory (use -r)", src.display());
+/// trash - list, restore and purge items removed with `rm --trash` / `set -o saferm`
+fn builtin_trash_impl(store: &TrashStore, args: &[&str], out: &mut dyn Write) -> Result<i32> {
+    match args.first().copied().unwrap_or("list") {
+        "list" | "ls" => {
+            let entries = store.list()?;
+            if entries.is_empty() {
+                writeln!(out, "Trash is empty ({})", store.root().display())?;
+                return Ok(0);
             }
-        } else {
-            let dest_path = if dest.is_dir() {
-                dest.join(src.file_name().ok_or_else(|| anyhow::anyhow!("cannot get filename"))?)
-            } else {
-                dest.clone()
+            writeln!(out, "{:>5}  {:<16}  {:>6}  ORIGINAL PATH", "ID", "DELETED", "SIZE")?;
+            for entry in &entries {
+                let deleted: chrono::DateTime<chrono::Local> = entry.deleted.into();
+                writeln!(
+                    out,
+                    "{:>5}  {:<16}  {:>6}  {}",
+                    entry.id,
+                    deleted.format("%Y-%m-%d %H:%M"),
+                    human_size(entry.size),
+                    entry.original.display()
+                )?;
+            }
+            let total: u64 = entries.iter().map(|e| e.size).sum();
+            writeln!(
+                out,
+                "{} item(s), {} of {} cap",
+                entries.len(),
+                human_size(total),
+                human_size(store.max_size)
+            )?;
+            Ok(0)
+        }
+        "restore" => {
+            if args.len() < 2 {
+                anyhow::bail!("trash: usage: trash restore <id>...");
+            }
+            for id in &args[1..] {
+                let id: u64 = id
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("trash: invalid id '{}'", id))?;
+                let restored = store.restore(id)?;
+                writeln!(out, "restored {} -> {}", id, restored.display())?;
+            }
+            Ok(0)
+        }
+        "purge" => {
+            let older_than = match args.get(1..).unwrap_or_default() {
+                ["--all"] => None,
+                ["--older-than", age] => Some(trash::parse_age(age)?),
+                [flag] if flag.starts_with("--older-than=") => {
+                    Some(trash::parse_age(&flag["--older-than=".len()..])?)
+                }
+                _ => anyhow::bail!("trash: usage: trash purge --older-than <age> | --all"),
             };
-            fs::copy(&src, &dest_path)?;
+            let (count, bytes) = store.purge(older_than)?;
+            writeln!(out, "purged {} item(s), {} freed", count, human_size(bytes))?;
+            Ok(0)
         }
-    }
-
-    Ok(0)
-}
-
-/// Helper: copy directory recursively
-fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
-    fs::create_dir_all(dst)?;
-    for entry in fs::read_dir(src)? {
-        let entry = entry?;
-        let ty = entry.file_type()?;
-        let dest_path = dst.join(entry.file_name());
-        if ty.is_dir() {
-            copy_dir_all(&entry.path(), &dest_path)?;
-        } else {
-            fs::copy(entry.path(), dest_path)?;
+        "-h" | "--help" | "help" => {
+            writeln!(out, "Usage: trash [list]")?;
+            writeln!(out, "       trash restore <id>...")?;
+            writeln!(out, "       trash purge --older-than <age> | --all    (age: 30m, 12h, 7d, 2w)")?;
+            writeln!(out, "Store: {} (set {}; cap {}, default 5G)", store.root().display(), trash::VAR_TRASH_DIR, trash::VAR_TRASH_MAX_SIZE)?;
+            Ok(0)
         }
+        other => anyhow::bail!("trash: unknown subcommand '{}' (list, restore, purge)", other),
     }
-    Ok(())
 }
 
-/// mv - move/rename
-fn builtin_mv(shell: &Shell, args: &[&str]) -> Result<i32> {
-    if args.len() < 2 {
-        anyhow::bail!("mv: missing destination");
-    }
-
-    let paths: Vec<_> = args.iter().filter(|a| !a.starts_with('-')).collect();
+fn builtin_trash(args: &[&str]) -> Result<i32> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    builtin_trash_impl(&TrashStore::open()?, args, &mut out)
+}
 
-    if paths.len() < 2 {
-        anyhow::bail!("mv: missing destination");
+/// stat [-L] [-c FORMAT | --printf=FORMAT | --json] FILE... - show file metadata
+fn builtin_stat_impl(shell: &Shell, args: &[&str], out: &mut dyn Write, err: &mut dyn Write) -> Result<i32> {
+    let mut follow = false;
+    let mut json = false;
+    // (format, omit the trailing newline); `--printf` escapes are already decoded
+    let mut format: Option<(String, bool)> = None;
+    let mut files = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i];
+        match arg {
+            "-L" | "--dereference" => follow = true,
+            "--json" => json = true,
+            "-c" | "--format" => {
+                i += 1;
+                let Some(fmt) = args.get(i) else {
+                    anyhow::bail!("stat: option requires an argument -- 'c'");
+                };
+                format = Some((fmt.to_string(), false));
+            }
+            "--" => {
+                files.extend_from_slice(&args[i + 1..]);
+                break;
+            }
+            _ if arg.starts_with("--format=") => format = Some((arg["--format=".len()..].to_string(), false)),
+            _ if arg.starts_with("--printf=") => {
+                // Decode before rendering, so backslashes in file names come through untouched
+                format = Some((printf::unescape(&arg["--printf=".len()..], printf::Escapes::Format).0, true))
+            }
+            _ if arg.starts_with("-c") => format = Some((arg[2..].to_string(), false)),
+            _ if arg.starts_with('-') && arg.len() > 1 => anyhow::bail!("stat: unrecognized option '{}'", arg),
+            _ => files.push(arg),
+        }
+        i += 1;
     }
-
-    let dest = path::resolve_fs(&shell.cwd, &path::expand_env(paths[paths.len() - 1]));
-
-    // Check destination for reserved names
-    if path::is_windows_reserved_name(&dest) {
-        anyhow::bail!("mv: {}", path::reserved_name_error(paths[paths.len() - 1]));
+    if files.is_empty() {
+        anyhow::bail!("stat: missing operand");
     }
 
-    for src_arg in &paths[..paths.len() - 1] {
-        let src = path::resolve_fs(&shell.cwd, &path::expand_env(src_arg));
-        let dest_path = if dest.is_dir() {
-            dest.join(src.file_name().ok_or_else(|| anyhow::anyhow!("cannot get filename"))?)
-        } else {
-            dest.clone()
+    let mut exit_code = 0;
+    for file in files {
+        let expanded = path::expand_env(file);
+        let target = path::resolve_fs(&shell.cwd, &expanded);
+        let st = match stat::FileStat::read(&expanded, &target, follow) {
+            Ok(st) => st,
+            Err(e) => {
+                let reason = if e.kind() == io::ErrorKind::NotFound { "No such file or directory".to_string() } else { e.to_string() };
+                writeln!(err, "stat: cannot stat '{}': {}", file, reason)?;
+                exit_code = 1;
+                continue;
+            }
         };
-        fs::rename(&src, &dest_path)?;
+        match &format {
+            _ if json => writeln!(out, "{}", stat::to_json(&st))?,
+            Some((fmt, true)) => write!(out, "{}", stat::render(fmt, &st))?,
+            Some((fmt, false)) => writeln!(out, "{}", stat::render(fmt, &st))?,
+            None => writeln!(out, "{}", stat::render(stat::DEFAULT_FORMAT, &st))?,
+        }
     }
+    Ok(exit_code)
+}
 
-    Ok(0)
+fn builtin_stat(shell: &Shell, args: &[&str]) -> Result<i32> {
+    let stdout = io::stdout();
+    let stderr = io::stderr();
+    let mut out = stdout.lock();
+    let mut err = stderr.lock();
+    builtin_stat_impl(shell, args, &mut out, &mut err)
 }
 
 /// touch - create empty file or update file timestamp
@@ -1820,4 +4508,616 @@ mod tests {
 
         let _ = fs::remove_file(&tmp);
     }
+
+    #[test]
+    fn test_set_noclobber_toggle() {
+        let mut shell = Shell::new().unwrap();
+        let mut out = Vec::<u8>::new();
+
+        builtin_set_impl(&mut shell, &["-o", "noclobber"], &mut out).unwrap();
+        assert!(shell.options.noclobber);
+        builtin_set_impl(&mut shell, &["+C"], &mut out).unwrap();
+        assert!(!shell.options.noclobber);
+        assert!(builtin_set_impl(&mut shell, &["-o", "bogus"], &mut out).is_err());
+
+        builtin_set_impl(&mut shell, &["-o"], &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("noclobber"));
+    }
+    fn ls_lines(shell: &Shell, args: &[&str]) -> Vec<String> {
+        let mut out = Vec::<u8>::new();
+        let mut err = Vec::<u8>::new();
+        builtin_ls_impl(shell, args, false, &mut out, &mut err).unwrap();
+        assert!(err.is_empty(), "{}", String::from_utf8_lossy(&err));
+        String::from_utf8(out).unwrap().lines().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn test_ls_sorting_classify_and_recursion() {
+        let dir = std::env::temp_dir().join("titanbash_ls_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.txt"), b"12345").unwrap();
+        fs::write(dir.join("b.exe"), vec![0u8; 3000]).unwrap();
+        fs::write(dir.join(".hidden"), b"").unwrap();
+        fs::write(dir.join("sub").join("inner.txt"), b"").unwrap();
+
+        let mut shell = Shell::new().unwrap();
+        shell.cwd = dir.clone();
+
+        // Piped output is one name per line, dotfiles hidden without -a
+        assert_eq!(ls_lines(&shell, &[]), vec!["a.txt", "b.exe", "sub"]);
+        assert!(ls_lines(&shell, &["-a"]).contains(&".hidden".to_string()));
+        assert_eq!(ls_lines(&shell, &["-F"]), vec!["a.txt", "b.exe*", "sub/"]);
+        assert_eq!(ls_lines(&shell, &["-Sr", "--group-directories-first"]), vec!["sub", "a.txt", "b.exe"]);
+        assert_eq!(ls_lines(&shell, &["-d", "sub"]), vec!["sub"]);
+        assert_eq!(ls_lines(&shell, &["--color=auto", "--color", "--time-style=iso"]), vec!["a.txt", "b.exe", "sub"]);
+
+        let recursive = ls_lines(&shell, &["-R"]);
+        assert_eq!(recursive[0], ".:");
+        assert!(recursive.contains(&"./sub:".to_string()));
+        assert!(recursive.contains(&"inner.txt".to_string()));
+
+        let long = ls_lines(&shell, &["-lh", "b.exe"]);
+        assert_eq!(long.len(), 1);
+        assert!(long[0].contains(" 2.9K "), "{}", long[0]);
+        assert!(long[0].ends_with("b.exe"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_cp_mv_multiple_sources_and_modes() {
+        let dir = std::env::temp_dir().join("titanbash_cp_mv_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("dest")).unwrap();
+        fs::write(dir.join("one.txt"), b"one").unwrap();
+        fs::write(dir.join("two.txt"), b"two").unwrap();
+        fs::write(dir.join("dest").join("one.txt"), b"old").unwrap();
+
+        let mut shell = Shell::new().unwrap();
+        shell.cwd = dir.clone();
+        let mut stdin = BufReader::new("n\n".as_bytes());
+        let mut out = Vec::<u8>::new();
+        let mut err = Vec::<u8>::new();
+
+        // -n keeps the existing file, the other source is copied
+        let code = builtin_cp_impl(&shell, &["-nv", "one.txt", "two.txt", "dest"], &mut stdin, &mut out, &mut err).unwrap();
+        assert_eq!(code, 0);
+        assert_eq!(fs::read(dir.join("dest").join("one.txt")).unwrap(), b"old");
+        assert_eq!(fs::read(dir.join("dest").join("two.txt")).unwrap(), b"two");
+        assert_eq!(String::from_utf8_lossy(&out).lines().count(), 1);
+
+        // -i declined
+        let code = builtin_cp_impl(&shell, &["-i", "one.txt", "dest"], &mut stdin, &mut out, &mut err).unwrap();
+        assert_eq!(code, 0);
+        assert!(String::from_utf8_lossy(&err).contains("overwrite"));
+        assert_eq!(fs::read(dir.join("dest").join("one.txt")).unwrap(), b"old");
+
+        // several sources need a directory target
+        assert!(builtin_cp_impl(&shell, &["one.txt", "two.txt", "missing"], &mut stdin, &mut out, &mut err).is_err());
+
+        // -r copies the directory itself into an existing directory, -p keeps mtimes
+        fs::create_dir_all(dir.join("copy")).unwrap();
+        builtin_cp_impl(&shell, &["-rp", "dest", "copy"], &mut stdin, &mut out, &mut err).unwrap();
+        let copied = dir.join("copy").join("dest").join("two.txt");
+        assert_eq!(fs::read(&copied).unwrap(), b"two");
+        assert_eq!(
+            fs::metadata(&copied).unwrap().modified().unwrap(),
+            fs::metadata(dir.join("dest").join("two.txt")).unwrap().modified().unwrap()
+        );
+
+        // mv of several files into a directory
+        let code = builtin_mv_impl(&shell, &["-f", "one.txt", "two.txt", "copy"], &mut stdin, &mut out, &mut err).unwrap();
+        assert_eq!(code, 0);
+        assert!(!dir.join("one.txt").exists());
+        assert_eq!(fs::read(dir.join("copy").join("one.txt")).unwrap(), b"one");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_trash_builtin_list_restore_purge() {
+        let base = std::env::temp_dir().join("titanbash_trash_builtin_test");
+        let _ = fs::remove_dir_all(&base);
+        fs::create_dir_all(&base).unwrap();
+        let store = TrashStore::with_root(base.join("trash"), 1024 * 1024);
+        let file = base.join("report.txt");
+        fs::write(&file, b"data").unwrap();
+        let id = store.put(&file).unwrap().id.to_string();
+
+        let mut out = Vec::<u8>::new();
+        builtin_trash_impl(&store, &["list"], &mut out).unwrap();
+        let listing = String::from_utf8_lossy(&out).to_string();
+        assert!(listing.contains("report.txt"));
+        assert!(listing.contains("1 item(s)"));
+
+        builtin_trash_impl(&store, &["restore", &id], &mut out).unwrap();
+        assert_eq!(fs::read(&file).unwrap(), b"data");
+
+        assert!(builtin_trash_impl(&store, &["purge"], &mut out).is_err());
+        builtin_trash_impl(&store, &["purge", "--older-than", "7d"], &mut out).unwrap();
+        assert!(builtin_trash_impl(&store, &["bogus"], &mut out).is_err());
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_rm_refuses_current_directory_ancestor() {
+        let base = std::env::temp_dir().join("titanbash_rm_guard_test");
+        let _ = fs::remove_dir_all(&base);
+        fs::create_dir_all(base.join("repo").join("src")).unwrap();
+        let mut shell = Shell::new().unwrap();
+        shell.cwd = base.join("repo").join("src");
+
+        let err = builtin_rm(&shell, &["-rf", ".."]).unwrap_err();
+        assert!(err.to_string().contains("--no-preserve-root"));
+        assert!(base.join("repo").is_dir());
+
+        builtin_rm(&shell, &["-rf", "--no-preserve-root", ".."]).unwrap();
+        assert!(!base.join("repo").exists());
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_dirs_listing_and_popd_offsets() {
+        let mut shell = Shell::new().unwrap();
+        let base = std::env::temp_dir().join("titanbash_dirs_test");
+        shell.cwd = base.join("cwd");
+        shell.dir_stack = vec![base.join("a"), base.join("b"), base.join("c")];
+
+        let mut out = Vec::<u8>::new();
+        builtin_dirs_impl(&mut shell, &["-v"], &mut out).unwrap();
+        let lines: Vec<String> = String::from_utf8(out).unwrap().lines().map(str::to_string).collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], format!(" 0  {}", base.join("cwd").display()));
+        assert_eq!(lines[3], format!(" 3  {}", base.join("c").display()));
+
+        let mut out = Vec::<u8>::new();
+        builtin_dirs_impl(&mut shell, &["-0"], &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().trim(), base.join("c").display().to_string());
+        assert!(builtin_dirs_impl(&mut shell, &["+9"], &mut Vec::new()).is_err());
+
+        // Removing anything but the top entry does not change directory.
+        builtin_popd_impl(&mut shell, &["+2"], &mut Vec::new()).unwrap();
+        assert_eq!(shell.dir_stack, vec![base.join("a"), base.join("c")]);
+        builtin_popd_impl(&mut shell, &["-0"], &mut Vec::new()).unwrap();
+        assert_eq!(shell.dir_stack, vec![base.join("a")]);
+        assert_eq!(shell.cwd, base.join("cwd"));
+
+        builtin_dirs_impl(&mut shell, &["-c"], &mut Vec::new()).unwrap();
+        assert!(builtin_popd_impl(&mut shell, &[], &mut Vec::new()).is_err());
+        assert!(builtin_pushd_impl(&mut shell, &[], &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_type_and_command_v() {
+        let mut shell = Shell::new().unwrap();
+        shell.aliases.insert("ll".to_string(), "ls -la".to_string());
+        let run_type = |shell: &Shell, args: &[&str]| {
+            let mut out = Vec::<u8>::new();
+            let mut err = Vec::<u8>::new();
+            let code = builtin_type_impl(shell, args, &mut io::empty(), &mut out, &mut err).unwrap();
+            (code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+        };
+
+        let (code, out, _) = run_type(&shell, &["ll", "cd"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "ll is aliased to `ls -la'\ncd is a shell builtin\n");
+        assert_eq!(run_type(&shell, &["-t", "ll", "pwd"]).1, "alias\nbuiltin\n");
+
+        let (code, out, err) = run_type(&shell, &["titanbash_no_such_command"]);
+        assert_eq!((code, out.as_str()), (1, ""));
+        assert!(err.contains("not found"));
+
+        // `type FILE` keeps its cmd.exe meaning.
+        shell.cwd = std::env::temp_dir();
+        let file = shell.cwd.join("titanbash_type_test.txt");
+        fs::write(&file, "file body\n").unwrap();
+        assert_eq!(run_type(&shell, &["titanbash_type_test.txt"]).1, "file body\n");
+        let _ = fs::remove_file(&file);
+
+        let mut out = Vec::<u8>::new();
+        let code = builtin_command_impl(&mut shell, &["-v", "ll", "echo", "titanbash_nope"], &mut io::empty(), &mut out, &mut Vec::new()).unwrap();
+        assert_eq!(code, 1);
+        assert_eq!(String::from_utf8(out).unwrap(), "alias ll='ls -la'\necho\n");
+    }
+
+    #[test]
+    fn test_read_splits_fields_and_handles_escapes() {
+        let mut shell = Shell::new().unwrap();
+        let mut read = |args: &[&str], input: &str| {
+            let mut stdin = BufReader::new(input.as_bytes());
+            builtin_read_impl(&mut shell, args, &mut stdin, &mut Vec::new(), false).unwrap()
+        };
+        let var = |name: &str| env::var(name).unwrap_or_default();
+
+        assert_eq!(read(&["TB_READ_A", "TB_READ_B"], "  alpha  beta gamma  \nnext\n"), 0);
+        assert_eq!(var("TB_READ_A"), "alpha");
+        assert_eq!(var("TB_READ_B"), "beta gamma");
+
+        read(&["TB_READ_C", "TB_READ_D", "TB_READ_E"], "one\\ two three\n");
+        assert_eq!((var("TB_READ_C"), var("TB_READ_D"), var("TB_READ_E")), ("one two".into(), "three".into(), String::new()));
+        read(&["-r", "TB_READ_C"], "C:\\temp\\x\r\n");
+        assert_eq!(var("TB_READ_C"), "C:\\temp\\x");
+        read(&["TB_READ_C"], "first \\\nsecond\n");
+        assert_eq!(var("TB_READ_C"), "first second");
+
+
+        read(&["-d", ",", "-n3", "TB_READ_N"], "abcdef,");
+        assert_eq!(var("TB_READ_N"), "abc");
+        read(&["-d", ",", "TB_READ_N"], "ab,cd");
+        assert_eq!(var("TB_READ_N"), "ab");
+
+        assert_eq!(read(&["TB_READ_EOF"], "partial"), 1);
+        assert_eq!(var("TB_READ_EOF"), "partial");
+        assert_eq!(read(&["TB_READ_EOF"], ""), 1);
+        assert!(parse_read_args(&["1abc"]).is_err());
+        assert!(parse_read_args(&["-t", "soon"]).is_err());
+
+        // Arrays live in the shell, not in the environment children inherit.
+        let mut stdin = BufReader::new("x y z\n".as_bytes());
+        builtin_read_impl(&mut shell, &["-a", "TB_READ_ARR"], &mut stdin, &mut Vec::new(), false).unwrap();
+        let get = |name: &str| shell.get_var(name).unwrap_or_default();
+        assert_eq!((get("TB_READ_ARR[0]"), get("TB_READ_ARR[2]"), get("TB_READ_ARR")), ("x".into(), "z".into(), "x".into()));
+        assert_eq!(get("TB_READ_ARR[@]"), "x y z");
+        assert!(shell.get_var("TB_READ_ARR[3]").is_none());
+        assert!(env::var_os("TB_READ_ARR[0]").is_none() && env::var_os("TB_READ_ARR[@]").is_none());
+        shell.unset_var("TB_READ_ARR").unwrap();
+        assert!(shell.get_var("TB_READ_ARR[0]").is_none());
+    }
+
+    #[test]
+    fn test_ln_hard_symbolic_and_force() {
+        let dir = std::env::temp_dir().join("titanbash_ln_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.txt"), "alpha").unwrap();
+        fs::write(dir.join("b.txt"), "beta").unwrap();
+        let mut shell = Shell::new().unwrap();
+        shell.cwd = dir.clone();
+        let ln = |args: &[&str]| {
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            let code = builtin_ln_impl(&shell, args, &mut out, &mut err).unwrap();
+            (code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+        };
+
+        assert_eq!(ln(&["a.txt", "hard.txt"]).0, 0);
+        assert_eq!(fs::read_to_string(dir.join("hard.txt")).unwrap(), "alpha");
+        let (code, _, err) = ln(&["b.txt", "hard.txt"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("failed to create hard link 'hard.txt': File exists"));
+        assert_eq!(ln(&["-fv", "b.txt", "hard.txt"]).1, format!("'hard.txt' -> '{}'\n", dir.join("b.txt").display()));
+        assert_eq!(fs::read_to_string(dir.join("hard.txt")).unwrap(), "beta");
+
+        // Several targets go into the directory; a directory cannot be hard-linked.
+        assert_eq!(ln(&["a.txt", "b.txt", "sub"]).0, 0);
+        assert!(dir.join("sub/a.txt").exists() && dir.join("sub/b.txt").exists());
+        assert!(builtin_ln_impl(&shell, &["a.txt", "b.txt", "hard.txt"], &mut Vec::new(), &mut Vec::new()).is_err());
+        assert!(ln(&["sub", "subdir"]).2.contains("hard link not allowed for directory"));
+        assert!(ln(&["a.txt"]).2.contains("File exists"));
+        assert!(ln(&["--junction", "a.txt", "j"]).2.contains("must be an existing directory"));
+
+        #[cfg(not(windows))]
+        {
+            assert_eq!(ln(&["-s", "sub", "link"]).0, 0);
+            assert_eq!(fs::read_link(dir.join("link")).unwrap(), Path::new("sub"));
+            // Without -n the existing link to a directory is linked into; -nf replaces it.
+            fs::write(dir.join("c.txt"), "gamma").unwrap();
+            assert_eq!(ln(&["-s", "../c.txt", "link"]).0, 0);
+            assert_eq!(fs::read_to_string(dir.join("sub/c.txt")).unwrap(), "gamma");
+            assert_eq!(ln(&["-snf", "a.txt", "link"]).0, 0);
+            assert_eq!(fs::read_to_string(dir.join("link")).unwrap(), "alpha");
+            assert_eq!(ln(&["--junction", "sub", "junction"]).0, 0);
+            assert_eq!(fs::read_link(dir.join("junction")).unwrap(), dir.join("sub"));
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_stat_formats() {
+        let dir = std::env::temp_dir().join("titanbash_stat_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("data.txt"), "12345").unwrap();
+        let mut shell = Shell::new().unwrap();
+        shell.cwd = dir.clone();
+        let stat = |args: &[&str]| {
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            let code = builtin_stat_impl(&shell, args, &mut out, &mut err).unwrap();
+            (code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+        };
+
+        assert_eq!(stat(&["-c", "%n %s %F", "data.txt"]).1, "data.txt 5 regular file\n");
+        assert_eq!(stat(&["-c", "%n %F", "."]).1, ". directory\n");
+        assert_eq!(stat(&["--printf=%s\\t%n\\n", "data.txt"]).1, "5\tdata.txt\n");
+        // A `\t` or `\n` inside the name is not an escape
+        if cfg!(windows) {
+            fs::create_dir_all(dir.join("new")).unwrap();
+        }
+        fs::write(dir.join("new\\table.txt"), "").unwrap();
+        assert_eq!(stat(&["--printf=%n\\n", "new\\table.txt"]).1, "new\\table.txt\n");
+        let (_, default, _) = stat(&["data.txt"]);
+        assert!(default.starts_with("  File: data.txt\n  Size: 5 ") && default.contains("Modify: "));
+        let (_, json, _) = stat(&["--json", "data.txt"]);
+        assert!(json.starts_with("{\"name\":\"data.txt\",\"type\":\"regular file\",\"size\":5,"));
+
+        let (code, out, err) = stat(&["-c", "%n", "missing", "data.txt"]);
+        assert_eq!((code, out.as_str()), (1, "data.txt\n"));
+        assert_eq!(err, "stat: cannot stat 'missing': No such file or directory\n");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_ff_print0_and_exec() {
+        let dir = std::env::temp_dir().join("titanbash_ff_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::create_dir_all(dir.join("node_modules/pkg")).unwrap();
+        fs::write(dir.join("src/a.rs"), "").unwrap();
+        fs::write(dir.join("src/b.txt"), "").unwrap();
+        fs::write(dir.join("src/日本.md"), "").unwrap();
+        fs::write(dir.join("node_modules/pkg/c.rs"), "").unwrap();
+        fs::write(dir.join(".ignore"), "node_modules\n").unwrap();
+        let mut shell = Shell::new().unwrap();
+        shell.cwd = dir.clone();
+        let mut ff = |args: &[&str]| {
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            let code = builtin_ff_impl(&mut shell, args, &mut out, &mut err).unwrap();
+            (code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+        };
+
+        assert_eq!(ff(&["-t", "f"]).1, "src/a.rs\nsrc/b.txt\nsrc/日本.md\n");
+        assert_eq!(ff(&["-0", "-g", "*.rs", "-I"]).1, "node_modules/pkg/c.rs\0src/a.rs\0");
+        assert_eq!(ff(&["-e", "rs", "-x", "echo", "found", "{/.}", "in", "{//}"]).1, "found a in src\n");
+        assert_eq!(ff(&["--extension=txt", "-x", "echo"]).1, "src/b.txt\n");
+        assert_eq!(ff(&["b", "src"]).1, "src/b.txt\n");
+        assert_eq!(ff(&["日本", "src"]).1, "src/日本.md\n");
+        assert_eq!(ff(&["-e", "md"]).1, "src/日本.md\n");
+        let (code, _, err) = ff(&["x", "missing"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("'missing' is not a directory"));
+        assert!(builtin_ff_impl(&mut shell, &["-S", "huge"], &mut Vec::new(), &mut Vec::new()).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_kill_list_and_targets() {
+        let shell = Shell::new().unwrap();
+        let run = |args: &[&str]| {
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            let code = builtin_kill_impl(&shell, args, &mut out, &mut err).map_err(|e| e.to_string());
+            (code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+        };
+
+        let (code, table, _) = run(&["-l"]);
+        assert_eq!(code, Ok(0));
+        assert!(table.contains(" 9) SIGKILL") && table.contains("15) SIGTERM"));
+        assert_eq!(run(&["-l", "143", "kill", "SIGINT"]).1, "TERM\n9\n2\n");
+        assert_eq!(run(&["-l", "NOPE"]).2, "kill: NOPE: invalid signal specification\n");
+
+        assert_eq!(run(&["-NOPE", "1"]).0, Err("kill: NOPE: invalid signal specification".to_string()));
+        assert!(run(&["-9"]).0.unwrap_err().starts_with("kill: usage:"));
+        let (code, _, err) = run(&["-s", "KILL", "%3", "abc"]);
+        assert_eq!(code, Ok(1));
+        assert_eq!(err, "kill: %3: no such job\nkill: abc: arguments must be process or job IDs\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_kill_job_by_spec_and_pid() {
+        use std::process::Command;
+
+        let mut shell = Shell::new().unwrap();
+        for _ in 0..2 {
+            shell
+                .tasks
+                .spawn("sleep 30", |pid| {
+                    let mut child = crate::task::spawn_child(Command::new("sleep").arg("30"))?;
+                    pid.set(child.id());
+                    let status = child.wait()?;
+                    Ok((status.code().unwrap_or(-1), String::new()))
+                })
+                .unwrap();
+        }
+        let pid = loop {
+            match (shell.tasks.pid(1), shell.tasks.pid(2)) {
+                (Some(_), Some(pid)) => break pid,
+                _ => std::thread::sleep(std::time::Duration::from_millis(10)),
+            }
+        };
+
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        assert_eq!(builtin_kill_impl(&shell, &["%1"], &mut out, &mut err).unwrap(), 0);
+        assert_eq!(builtin_kill_impl(&shell, &["-KILL", &pid.to_string()], &mut out, &mut err).unwrap(), 0);
+        assert!(err.is_empty());
+        assert_eq!(shell.tasks.wait(1), Some(TaskStatus::Completed(-1)));
+        assert_eq!(shell.tasks.wait(2), Some(TaskStatus::Completed(-1)));
+    }
+
+    #[test]
+    fn test_job_specs_bg_and_disown() {
+        use std::time::Duration;
+
+        let mut shell = Shell::new().unwrap();
+        for cmd in ["sleep 5", "python serve.py", "sleep 9"] {
+            shell
+                .tasks
+                .spawn(cmd, |_| {
+                    std::thread::sleep(Duration::from_millis(300));
+                    Ok((0, String::new()))
+                })
+                .unwrap();
+        }
+        let spec = |shell: &Shell, s: &str| shell.tasks.job_spec(s).map_err(|e| e.to_string());
+
+        assert_eq!(spec(&shell, "%2"), Ok(2));
+        assert_eq!(spec(&shell, "3"), Ok(3));
+        assert_eq!((spec(&shell, "%%"), spec(&shell, "%+"), spec(&shell, "%-")), (Ok(3), Ok(3), Ok(2)));
+        assert_eq!(spec(&shell, "%py"), Ok(2));
+        assert_eq!(spec(&shell, "%?9"), Ok(3));
+        assert_eq!(spec(&shell, "%sleep"), Err("%sleep: ambiguous job spec".to_string()));
+        assert_eq!(spec(&shell, "%7"), Err("%7: no such job".to_string()));
+        assert!(spec(&shell, "sleep").is_err());
+
+        let mut err = Vec::new();
+        assert_eq!(builtin_bg_impl(&shell, &["%py"], &mut err).unwrap(), 0);
+        assert_eq!(String::from_utf8(err).unwrap(), "bg: job 2 already in background\n");
+
+        builtin_disown(&mut shell, &["-h", "%1"]).unwrap();
+        builtin_disown(&mut shell, &["%?serve"]).unwrap();
+        let ids: Vec<TaskId> = shell.tasks.list().into_iter().map(|(id, _, _)| id).collect();
+        assert_eq!(ids, [1, 3]);
+        builtin_disown(&mut shell, &[]).unwrap();
+        assert_eq!(spec(&shell, "%+"), Ok(1));
+        assert_eq!(builtin_wait(&mut shell, &["%1"]).unwrap(), 0);
+        assert!(builtin_disown(&mut shell, &[]).is_err());
+    }
+
+    #[test]
+    fn test_variable_attributes_and_declare_p() {
+        let mut shell = Shell::new().unwrap();
+        let mut out = Vec::<u8>::new();
+        let mut err = Vec::<u8>::new();
+
+        builtin_export_impl(&mut shell, &["TB_DECL_EXP=one"], &mut out).unwrap();
+        assert_eq!(env::var("TB_DECL_EXP").unwrap(), "one");
+        builtin_export_impl(&mut shell, &["-n", "TB_DECL_EXP"], &mut out).unwrap();
+        assert!(env::var("TB_DECL_EXP").is_err());
+        assert_eq!(shell.get_var("TB_DECL_EXP").as_deref(), Some("one"));
+        assert_eq!(builtin_test_impl(&shell, "test", &["-v", "TB_DECL_EXP"], &mut Vec::new()).unwrap(), 0);
+
+        builtin_readonly_impl(&mut shell, &["TB_DECL_RO=it's"], &mut out).unwrap();
+        assert!(shell.set_var("TB_DECL_RO", "x").is_err());
+        assert!(builtin_export_impl(&mut shell, &["TB_DECL_RO=x"], &mut out).is_err());
+        assert_eq!(builtin_unset_impl(&mut shell, &["TB_DECL_RO"], &mut err).unwrap(), 1);
+        assert!(String::from_utf8_lossy(&err).contains("cannot unset: readonly variable"));
+
+        assert_eq!(builtin_declare_impl(&mut shell, &["-i", "TB_DECL_INT=12"], &mut out, &mut err).unwrap(), 0);
+        assert_eq!(builtin_declare_impl(&mut shell, &["TB_DECL_INT=abc"], &mut out, &mut err).unwrap(), 1);
+
+        let mut printed = Vec::<u8>::new();
+        let names = ["TB_DECL_EXP", "TB_DECL_RO", "TB_DECL_INT"];
+        builtin_declare_impl(&mut shell, &[&["-p"][..], &names].concat(), &mut printed, &mut err).unwrap();
+        let printed = String::from_utf8(printed).unwrap();
+        assert_eq!(
+            printed,
+            "declare -- TB_DECL_EXP='one'\ndeclare -r TB_DECL_RO='it'\"'\"'s'\ndeclare -i TB_DECL_INT='12'\n"
+        );
+
+        // The printed form reads back into a fresh shell.
+        let mut fresh = Shell::new().unwrap();
+        for line in printed.lines() {
+            let argv = crate::shell::parser::split_args(line);
+            let args: Vec<&str> = argv[1..].iter().map(String::as_str).collect();
+            builtin_declare_impl(&mut fresh, &args, &mut out, &mut err).unwrap();
+        }
+        assert_eq!(fresh.get_var("TB_DECL_RO").as_deref(), Some("it's"));
+        assert!(fresh.var_attrs("TB_DECL_RO").readonly && fresh.var_attrs("TB_DECL_INT").integer);
+
+        shell.set_array("tb_decl_arr", vec!["a".into(), "it's".into()]).unwrap();
+        shell.set_array("tb_decl_empty", Vec::new()).unwrap();
+        let mut printed = Vec::<u8>::new();
+        builtin_declare_impl(&mut shell, &["-p", "tb_decl_arr", "tb_decl_empty"], &mut printed, &mut err).unwrap();
+        assert_eq!(
+            String::from_utf8(printed).unwrap(),
+            "declare -a tb_decl_arr=([0]='a' [1]='it'\"'\"'s')\ndeclare -a tb_decl_empty=()\n"
+        );
+        let mut listed = Vec::<u8>::new();
+        builtin_declare_impl(&mut shell, &[], &mut listed, &mut err).unwrap();
+        assert!(String::from_utf8(listed).unwrap().contains("declare -a tb_decl_arr=([0]='a' "));
+
+        assert_eq!(builtin_unset_impl(&mut shell, &["TB_DECL_EXP", "TB_DECL_INT"], &mut err).unwrap(), 0);
+        assert!(shell.get_var("TB_DECL_EXP").is_none() && shell.get_var("TB_DECL_INT").is_none());
+    }
+
+    #[test]
+    fn test_echo_flags_and_printf_v() {
+        let echo = |args: &[&str]| {
+            let mut out = Vec::<u8>::new();
+            builtin_echo_impl(args, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(echo(&["-n", "hi"]), "hi");
+        assert_eq!(echo(&["-e", "a\\tb"]), "a\tb\n");
+        assert_eq!(echo(&["a\\tb"]), "a\\tb\n");
+        assert_eq!(echo(&["-ne", "x\\cy"]), "x");
+        assert_eq!(echo(&["-eE", "a\\n"]), "a\\n\n");
+        assert_eq!(echo(&["-x", "--"]), "-x --\n");
+
+        let mut out = Vec::<u8>::new();
+        let mut shell = Shell::new().unwrap();
+        let code = builtin_printf_impl(&mut shell, &["-v", "TB_PRINTF_VAR", "%03d-%s", "7", "x"], &mut out, &mut Vec::new()).unwrap();
+        assert_eq!((code, out.len()), (0, 0));
+        assert_eq!(env::var("TB_PRINTF_VAR").unwrap(), "007-x");
+        assert_eq!(builtin_printf_impl(&mut shell, &["%d", "nope"], &mut Vec::new(), &mut Vec::new()).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_test_and_bracket_status() {
+        let shell = Shell::new().unwrap();
+        let run = |name: &str, args: &[&str]| {
+            let mut err = Vec::<u8>::new();
+            let code = builtin_test_impl(&shell, name, args, &mut err).unwrap();
+            (code, String::from_utf8(err).unwrap())
+        };
+        assert_eq!(run("test", &["a", "=", "a"]).0, 0);
+        assert_eq!(run("[", &["3", "-lt", "2", "]"]).0, 1);
+        assert_eq!(run("[", &["-n", "x"]), (2, "[: missing `]'\n".to_string()));
+        assert_eq!(run("test", &["x", "-eq", "1"]), (2, "test: x: integer expression expected\n".to_string()));
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(human_size(512), "512");
+        assert_eq!(human_size(1536), "1.5K");
+        assert_eq!(human_size(20 * 1024 * 1024), "20M");
+    }
+
+    #[test]
+    fn test_cat_and_head_decode_utf16_stdin() {
+        let shell = Shell::new().unwrap();
+        let utf16 = encoding::encode("first\r\nsecond\r\n", Encoding::Utf16Le, true);
+
+        let mut stdin = BufReader::new(utf16.as_slice());
+        let mut out = Vec::<u8>::new();
+        let mut err = Vec::<u8>::new();
+        builtin_cat_impl(&shell, &[], &mut stdin, &mut out, &mut err).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "first\nsecond\n");
+
+        let mut stdin = BufReader::new(utf16.as_slice());
+        let mut out = Vec::<u8>::new();
+        builtin_head_impl(&shell, &["-n", "1"], &mut stdin, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "first\n");
+
+        let mut stdin = BufReader::new(utf16.as_slice());
+        let mut out = Vec::<u8>::new();
+        assert!(builtin_head_impl(&shell, &["--encoding", "latin-9"], &mut stdin, &mut out).is_err());
+    }
+
+    #[test]
+    fn test_checksum_encoding_hashes_transcoded_text() {
+        let shell = Shell::new().unwrap();
+        let utf16 = encoding::encode("hello", Encoding::Utf16Le, true);
+
+        let mut stdin = BufReader::new(utf16.as_slice());
+        let mut out = Vec::<u8>::new();
+        let mut err = Vec::<u8>::new();
+        builtin_checksum_impl(HashKind::Md5, &shell, &["--encoding=auto"], &mut stdin, &mut out, &mut err).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "5d41402abc4b2a76b9719d911017c592 *-\n");
+    }
+
+    #[test]
+    fn test_transcode_utf8_to_utf16_crlf() {
+        let shell = Shell::new().unwrap();
+        let mut stdin = BufReader::new("a\nb\n".as_bytes());
+        let mut out = Vec::<u8>::new();
+        builtin_transcode_impl(&shell, &["-t", "utf-16le", "--bom", "--crlf"], &mut stdin, &mut out).unwrap();
+        assert_eq!(out, encoding::encode("a\r\nb\r\n", Encoding::Utf16Le, true));
+
+        let mut stdin = BufReader::new(out.as_slice());
+        let mut back = Vec::<u8>::new();
+        builtin_transcode_impl(&shell, &["--lf"], &mut stdin, &mut back).unwrap();
+        assert_eq!(back, b"a\nb\n");
+    }
 }
@@ -17,12 +17,13 @@ use super::busybox;
 
 /// Built-in commands for tab completion
 const BUILTIN_COMMANDS: &[&str] = &[
-    "cd", "pwd", "ls", "dir", "cat", "type", "echo", "clear", "cls",
-    "exit", "quit", "jobs", "export", "set", "env", "printenv", "which", "where",
+    "cd", "z", "zi", "pushd", "popd", "dirs", "pwd", "ls", "dir", "tree", "du", "ff", "cat", "type", "command", "echo", "printf", "test", "clear", "cls",
+    "exit", "quit", "jobs", "export", "unset", "readonly", "declare", "set", "env", "printenv", "which", "where",
     "activate", "deactivate",
-    "mkdir", "rm", "del", "cp", "copy", "mv", "move", "touch",
+    "mkdir", "rm", "del", "cp", "copy", "mv", "move", "ln", "touch", "stat",
     "history", "help", "head", "tail", "whoami", "hostname",
-    "md5sum", "sha1sum", "sha256sum", "sha512sum", "fg", "wait", "kill",
+    "md5sum", "sha1sum", "sha256sum", "sha512sum", "fg", "bg", "wait", "kill", "disown", "nohup",
+    "explain", "watch", "transcode", "trash", "read",
 ];
 
 pub struct TitanHelper {
@@ -278,6 +279,14 @@ impl Completer for TitanHelper {
             }
 
             let mut candidates = self.complete_path(&partial);
+            let command = tokens[0].1.to_ascii_lowercase();
+            if tokens.len() <= 2 && matches!(command.as_str(), "cd" | "pushd") {
+                for cand in complete_cdpath(&partial, &super::path::cdpath_dirs()) {
+                    if !candidates.iter().any(|c| c.replacement == cand.replacement) {
+                        candidates.push(cand);
+                    }
+                }
+            }
             for cand in &mut candidates {
                 if quote.is_none() && cand.replacement.contains(' ') {
                     cand.replacement = format!("\"{}\"", cand.replacement);
@@ -289,6 +298,38 @@ impl Completer for TitanHelper {
     }
 }
 
+/// Directories under the `$CDPATH` entries `cdpath` whose name starts with `partial` (for `cd`).
+fn complete_cdpath(partial: &str, cdpath: &[std::path::PathBuf]) -> Vec<Pair> {
+    if !super::path::is_cdpath_candidate(partial) && !partial.is_empty() {
+        return Vec::new();
+    }
+    if partial.contains(['/', '\\']) {
+        return Vec::new();
+    }
+    let prefix = partial.to_lowercase();
+    let mut candidates: Vec<Pair> = Vec::new();
+    for dir in cdpath {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            if is_dir && name.to_lowercase().starts_with(&prefix) {
+                let display = format!("{}/", name);
+                if !candidates.iter().any(|c| c.display == display) {
+                    candidates.push(Pair {
+                        display: display.clone(),
+                        replacement: display,
+                    });
+                }
+            }
+        }
+    }
+    candidates.sort_by_key(|c| c.display.to_lowercase());
+    candidates
+}
+
 impl Highlighter for TitanHelper {
     fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
         Cow::Owned(format!("\x1b[90m{}\x1b[0m", hint))  // Gray color
@@ -315,4 +356,23 @@ mod tests {
         assert!(candidates.iter().any(|p| p.replacement == "cat"));
         assert!(candidates.iter().any(|p| p.replacement == "clear"));
     }
+
+    #[test]
+    fn test_cd_completes_cdpath_dirs() {
+        let base = std::env::temp_dir().join("titanbash_cdpath_completion");
+        std::fs::create_dir_all(base.join("pkg-alpha")).unwrap();
+        std::fs::create_dir_all(base.join("pkg-beta")).unwrap();
+        std::fs::write(base.join("pkg-file"), "").unwrap();
+        let cdpath = vec![base.clone(), std::env::temp_dir().join("titanbash_cdpath_missing")];
+
+        let names = |partial: &str| -> Vec<String> {
+            complete_cdpath(partial, &cdpath).into_iter().map(|p| p.replacement).collect()
+        };
+        assert_eq!(names("pkg-"), vec!["pkg-alpha/", "pkg-beta/"]);
+        assert_eq!(names("PKG-A"), vec!["pkg-alpha/"]);
+        assert!(names("./pkg-").is_empty());
+        assert!(complete_cdpath("pkg-", &[]).is_empty());
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
 }
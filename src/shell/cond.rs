@@ -0,0 +1,427 @@
+//! Conditional expressions for `test`, `[` and `[[ ]]`.
+//!
+//! - file predicates: `-e -f -d -s -r -w -x -L/-h` and `FILE -nt/-ot FILE`, resolved through
+//!   [`path::resolve_fs`] so long and UNC paths work
+//! - strings: `-z -n = == != < >`, integers: `-eq -ne -lt -le -gt -ge`, `-v NAME`
+//! - `!`, parentheses and `-a`/`-o` (`test`) or `&&`/`||` (`[[ ]]`)
+//!
+//! `test` follows the POSIX argument-count rules, so `[ "$x" = -f ]` compares strings.
+//! Inside `[[ ]]` the right side of `==`/`!=` is a glob pattern and `=~` is a regex
+//! (quoted parts match literally); its captures are handed back for the executor to store
+//! in `BASH_REMATCH`.
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+
+use super::path;
+
+/// One operand: its text plus which pieces were quoted (only matters for `[[ ]]` patterns).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CondWord {
+    pub parts: Vec<(String, bool)>,
+}
+
+impl CondWord {
+    pub fn literal(text: &str) -> Self {
+        Self {
+            parts: vec![(text.to_string(), true)],
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.parts.iter().map(|(t, _)| t.as_str()).collect()
+    }
+
+    fn glob_pattern(&self) -> String {
+        self.parts
+            .iter()
+            .map(|(t, quoted)| if *quoted { glob::Pattern::escape(t) } else { t.clone() })
+            .collect()
+    }
+
+    fn regex_pattern(&self) -> String {
+        self.parts
+            .iter()
+            .map(|(t, quoted)| if *quoted { regex::escape(t) } else { t.clone() })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dialect {
+    Test,
+    DoubleBracket,
+}
+
+/// Looks up a variable by name for `-v` (shell variables as well as the environment).
+pub type VarLookup<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+/// Evaluate `test` / `[` arguments (without the closing `]`).
+pub fn eval_test(cwd: &Path, args: &[String], lookup: VarLookup) -> Result<bool> {
+    let words: Vec<CondWord> = args.iter().map(|a| CondWord::literal(a)).collect();
+    let texts: Vec<&str> = args.iter().map(String::as_str).collect();
+    let eval = Evaluator {
+        cwd,
+        words: &words,
+        dialect: Dialect::Test,
+        lookup,
+        rematch: RefCell::new(None),
+    };
+
+    // POSIX: the number of arguments decides how they are read.
+    match texts.as_slice() {
+        [] => Ok(false),
+        [one] => Ok(!one.is_empty()),
+        ["!", one] => Ok(one.is_empty()),
+        [op, _] if is_unary(op) => eval.unary(op, 1),
+        [_, op, _] if is_binary(op, Dialect::Test) || matches!(*op, "-a" | "-o") => match *op {
+            "-a" => Ok(!texts[0].is_empty() && !texts[2].is_empty()),
+            "-o" => Ok(!texts[0].is_empty() || !texts[2].is_empty()),
+            _ => eval.binary(0, op, 2),
+        },
+        ["!", ..] if texts.len() <= 4 => eval_test(cwd, &args[1..], lookup).map(|r| !r),
+        ["(", inner @ .., ")"] if texts.len() <= 4 => eval_test(cwd, &args[1..1 + inner.len()], lookup),
+        _ => eval.parse_all(),
+    }
+}
+
+/// Evaluate the words between `[[` and `]]`.
+///
+/// Also returns the captures of the last `=~` evaluated (whole match first, empty when it did
+/// not match), or `None` if there was none.
+pub fn eval_double_bracket(
+    cwd: &Path,
+    words: &[CondWord],
+    lookup: VarLookup,
+) -> Result<(bool, Option<Vec<String>>)> {
+    let eval = Evaluator {
+        cwd,
+        words,
+        dialect: Dialect::DoubleBracket,
+        lookup,
+        rematch: RefCell::new(None),
+    };
+    if words.is_empty() {
+        bail!("expression expected");
+    }
+    let result = eval.parse_all()?;
+    Ok((result, eval.rematch.into_inner()))
+}
+
+fn is_unary(op: &str) -> bool {
+    matches!(
+        op,
+        "-e" | "-a" | "-f" | "-d" | "-s" | "-r" | "-w" | "-x" | "-L" | "-h" | "-z" | "-n" | "-v"
+    )
+}
+
+fn is_binary(op: &str, dialect: Dialect) -> bool {
+    match op {
+        "=" | "==" | "!=" | "<" | ">" | "-eq" | "-ne" | "-lt" | "-le" | "-gt" | "-ge" | "-nt" | "-ot" => true,
+        "=~" => dialect == Dialect::DoubleBracket,
+        _ => false,
+    }
+}
+
+struct Evaluator<'a> {
+    cwd: &'a Path,
+    words: &'a [CondWord],
+    dialect: Dialect,
+    lookup: VarLookup<'a>,
+    /// Captures of the last `=~`
+    rematch: RefCell<Option<Vec<String>>>,
+}
+
+impl Evaluator<'_> {
+    fn text(&self, i: usize) -> String {
+        self.words.get(i).map(CondWord::text).unwrap_or_default()
+    }
+
+    fn is_op(&self, i: usize, ops: &[&str]) -> bool {
+        // In `[[ ]]` a quoted `&&` or `==` is an operand, not an operator.
+        self.words.get(i).is_some_and(|w| {
+            (self.dialect == Dialect::Test || w.parts.iter().all(|(_, quoted)| !quoted)) && ops.contains(&w.text().as_str())
+        })
+    }
+
+    fn parse_all(&self) -> Result<bool> {
+        let mut pos = 0usize;
+        let result = self.parse_or(&mut pos)?;
+        if pos < self.words.len() {
+            bail!("{}: unexpected argument", self.text(pos));
+        }
+        Ok(result)
+    }
+
+    fn or_ops(&self) -> &'static [&'static str] {
+        match self.dialect {
+            Dialect::Test => &["-o"],
+            Dialect::DoubleBracket => &["||"],
+        }
+    }
+
+    fn and_ops(&self) -> &'static [&'static str] {
+        match self.dialect {
+            Dialect::Test => &["-a"],
+            Dialect::DoubleBracket => &["&&"],
+        }
+    }
+
+    fn parse_or(&self, pos: &mut usize) -> Result<bool> {
+        let mut result = self.parse_and(pos)?;
+        while self.is_op(*pos, self.or_ops()) {
+            *pos += 1;
+            let right = self.parse_and(pos)?;
+            result = result || right;
+        }
+        Ok(result)
+    }
+
+    fn parse_and(&self, pos: &mut usize) -> Result<bool> {
+        let mut result = self.parse_not(pos)?;
+        while self.is_op(*pos, self.and_ops()) {
+            *pos += 1;
+            let right = self.parse_not(pos)?;
+            result = result && right;
+        }
+        Ok(result)
+    }
+
+    fn parse_not(&self, pos: &mut usize) -> Result<bool> {
+        if self.is_op(*pos, &["!"]) && *pos + 1 < self.words.len() {
+            *pos += 1;
+            return self.parse_not(pos).map(|r| !r);
+        }
+        self.parse_primary(pos)
+    }
+
+    fn parse_primary(&self, pos: &mut usize) -> Result<bool> {
+        let start = *pos;
+        if start >= self.words.len() {
+            bail!("argument expected");
+        }
+
+        if self.is_op(start, &["("]) {
+            *pos += 1;
+            let result = self.parse_or(pos)?;
+            if !self.is_op(*pos, &[")"]) {
+                bail!("`)' expected");
+            }
+            *pos += 1;
+            return Ok(result);
+        }
+
+        // `a OP b` wins over a unary operator, so `[ -f = -f ]` compares strings.
+        if start + 2 < self.words.len() {
+            let op = self.text(start + 1);
+            if is_binary(&op, self.dialect) && self.is_op(start + 1, &[op.as_str()]) {
+                *pos = start + 3;
+                return self.binary(start, &op, start + 2);
+            }
+        }
+
+        let first = self.text(start);
+        if is_unary(&first) && self.is_op(start, &[first.as_str()]) && start + 1 < self.words.len() {
+            *pos = start + 2;
+            return self.unary(&first, start + 1);
+        }
+
+        *pos = start + 1;
+        Ok(!first.is_empty())
+    }
+
+    fn unary(&self, op: &str, operand: usize) -> Result<bool> {
+        let value = self.text(operand);
+        Ok(match op {
+            "-z" => value.is_empty(),
+            "-n" => !value.is_empty(),
+            "-v" => (self.lookup)(&value).is_some(),
+            _ => {
+                let target = self.resolve(&value);
+                match op {
+                    "-e" | "-a" => fs::symlink_metadata(&target).is_ok(),
+                    "-f" => target.is_file(),
+                    "-d" => target.is_dir(),
+                    "-s" => fs::metadata(&target).is_ok_and(|m| m.len() > 0),
+                    "-r" => is_readable(&target),
+                    "-w" => fs::metadata(&target).is_ok_and(|m| !m.permissions().readonly()),
+                    "-x" => is_executable(&target),
+                    _ => fs::symlink_metadata(&target).is_ok_and(|m| m.file_type().is_symlink()),
+                }
+            }
+        })
+    }
+
+    fn binary(&self, left: usize, op: &str, right: usize) -> Result<bool> {
+        let (a, b) = (self.text(left), self.text(right));
+        Ok(match op {
+            "=" | "==" | "!=" => {
+                let equal = match self.dialect {
+                    Dialect::Test => a == b,
+                    Dialect::DoubleBracket => glob::Pattern::new(&self.words[right].glob_pattern())
+                        .map(|p| p.matches(&a))
+                        .unwrap_or(a == b),
+                };
+                equal == (op != "!=")
+            }
+            "=~" => {
+                let pattern = self.words[right].regex_pattern();
+                let re = regex::Regex::new(&pattern).map_err(|e| anyhow::anyhow!("=~: invalid regex: {}", e))?;
+                let groups: Vec<String> = re
+                    .captures(&a)
+                    .map(|caps| caps.iter().map(|m| m.map(|m| m.as_str().to_string()).unwrap_or_default()).collect())
+                    .unwrap_or_default();
+                let matched = !groups.is_empty();
+                *self.rematch.borrow_mut() = Some(groups);
+                matched
+            }
+            "<" => a < b,
+            ">" => a > b,
+            "-nt" | "-ot" => {
+                let modified = |p: &str| fs::metadata(self.resolve(p)).and_then(|m| m.modified()).ok();
+                match (modified(&a), modified(&b), op) {
+                    (Some(x), Some(y), "-nt") => x > y,
+                    (Some(x), Some(y), _) => x < y,
+                    (Some(_), None, "-nt") | (None, Some(_), "-ot") => true,
+                    _ => false,
+                }
+            }
+            _ => {
+                let (x, y) = (parse_integer(&a)?, parse_integer(&b)?);
+                match op {
+                    "-eq" => x == y,
+                    "-ne" => x != y,
+                    "-lt" => x < y,
+                    "-le" => x <= y,
+                    "-gt" => x > y,
+                    _ => x >= y,
+                }
+            }
+        })
+    }
+
+    fn resolve(&self, file: &str) -> PathBuf {
+        path::resolve_fs(self.cwd, file)
+    }
+}
+
+fn parse_integer(text: &str) -> Result<i64> {
+    text.trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("{}: integer expression expected", text))
+}
+
+fn is_readable(target: &Path) -> bool {
+    if target.is_dir() {
+        fs::read_dir(target).is_ok()
+    } else {
+        fs::File::open(target).is_ok()
+    }
+}
+
+/// Windows has no execute bit: directories and `%PATHEXT%` extensions count as executable.
+#[cfg(windows)]
+fn is_executable(target: &Path) -> bool {
+    if target.is_dir() {
+        return true;
+    }
+    if !target.is_file() {
+        return false;
+    }
+    let Some(ext) = target.extension().map(|e| format!(".{}", e.to_string_lossy())) else {
+        return false;
+    };
+    let pathext = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+    pathext.split(';').any(|e| e.eq_ignore_ascii_case(&ext)) || ext.eq_ignore_ascii_case(".ps1")
+}
+
+#[cfg(not(windows))]
+fn is_executable(target: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(target).is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_lookup(name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+
+    fn test(args: &[&str]) -> bool {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        eval_test(&std::env::temp_dir(), &args, &env_lookup).unwrap()
+    }
+
+    fn dbl_rematch(words: &[(&str, bool)]) -> (bool, Option<Vec<String>>) {
+        let words: Vec<CondWord> = words
+            .iter()
+            .map(|(t, quoted)| CondWord {
+                parts: vec![(t.to_string(), *quoted)],
+            })
+            .collect();
+        eval_double_bracket(&std::env::temp_dir(), &words, &env_lookup).unwrap()
+    }
+
+    fn dbl(words: &[(&str, bool)]) -> bool {
+        dbl_rematch(words).0
+    }
+
+    #[test]
+    fn test_posix_argument_rules() {
+        assert!(!test(&[]));
+        assert!(test(&["x"]));
+        assert!(!test(&[""]));
+        assert!(test(&["-f"]));
+        assert!(test(&["-n", "x"]));
+        assert!(test(&["!", ""]));
+        assert!(test(&["-f", "=", "-f"]));
+        assert!(test(&["abc", "!=", "abd"]));
+        assert!(test(&["10", "-gt", "9"]));
+        assert!(!test(&["!", "1", "-eq", "1"]));
+        assert!(test(&["(", "a", "=", "a", ")", "-a", "1", "-lt", "2"]));
+        assert!(test(&["a", "=", "b", "-o", "!", "-z", "x"]));
+        assert!(eval_test(Path::new("."), &["x".into(), "-lt".into(), "2".into()], &env_lookup).is_err());
+
+        let shell_vars = |name: &str| (name == "LOCAL_ONLY").then(|| "1".to_string());
+        assert!(eval_test(Path::new("."), &["-v".into(), "LOCAL_ONLY".into()], &shell_vars).unwrap());
+        assert!(!eval_test(Path::new("."), &["-v".into(), "PATH".into()], &shell_vars).unwrap());
+    }
+
+    #[test]
+    fn test_file_predicates() {
+        let dir = std::env::temp_dir().join("titanbash_cond_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("empty"), "").unwrap();
+        fs::write(dir.join("full"), "data").unwrap();
+        let cwd = dir.clone();
+        let t = |args: &[&str]| eval_test(&cwd, &args.iter().map(|s| s.to_string()).collect::<Vec<_>>(), &env_lookup).unwrap();
+
+        assert!(t(&["-e", "empty"]) && t(&["-f", "full"]) && t(&["-d", "."]));
+        assert!(!t(&["-s", "empty"]) && t(&["-s", "full"]));
+        assert!(t(&["-r", "full"]) && t(&["-w", "full"]));
+        assert!(!t(&["-e", "missing"]) && !t(&["-L", "full"]));
+        assert!(t(&["full", "-nt", "missing"]) && t(&["missing", "-ot", "full"]));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_double_bracket_patterns() {
+        assert!(dbl(&[("main.rs", false), ("==", false), ("*.rs", false)]));
+        assert!(!dbl(&[("main.rs", false), ("==", false), ("*.rs", true)]));
+        assert!(dbl(&[("a", false), ("<", false), ("b", false), ("&&", false), ("!", false), ("-z", false), ("x", false)]));
+        assert!(dbl(&[("x", false), ("=", false), ("y", false), ("||", false), ("(", false), ("1", false), ("-eq", false), ("1", false), (")", false)]));
+
+        let (matched, groups) = dbl_rematch(&[("v1.22.3", false), ("=~", false), ("^v([0-9]+)\\.([0-9]+)", false)]);
+        assert!(matched);
+        assert_eq!(groups.unwrap(), ["v1.22", "1", "22"]);
+        assert_eq!(dbl_rematch(&[("a+b", false), ("=~", false), ("a.b", true)]), (false, Some(Vec::new())));
+        assert_eq!(dbl_rematch(&[("a", false), ("==", false), ("a", false)]), (true, None));
+    }
+}
@@ -0,0 +1,334 @@
+//! Text encoding detection and transcoding
+//!
+//! PowerShell (`Out-File`, `>` in Windows PowerShell 5) and many Windows tools write
+//! UTF-16LE with a BOM. Builtins that print text (`cat`, `head`, `tail`, `transcode`)
+//! read through [`text_reader`], which detects the encoding and yields UTF-8:
+//! - UTF-8 BOM (stripped)
+//! - UTF-16LE / UTF-16BE BOM
+//! - UTF-16 without BOM (heuristic: mostly-ASCII text with NUL high bytes)
+
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+
+use anyhow::{bail, Result};
+
+/// Bytes inspected for BOM / heuristic detection.
+const SNIFF_LEN: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    /// Parse a user-supplied name (`utf-8`, `utf16le`, `UTF-16BE`, `unicode`, ...).
+    ///
+    /// Returns `None` for `auto` (detect from content).
+    pub fn parse(name: &str) -> Result<Option<Self>> {
+        let key: String = name
+            .chars()
+            .filter(|c| *c != '-' && *c != '_')
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match key.as_str() {
+            "auto" => Ok(None),
+            "utf8" | "ascii" => Ok(Some(Encoding::Utf8)),
+            "utf16" | "utf16le" | "ucs2" | "ucs2le" | "unicode" => Ok(Some(Encoding::Utf16Le)),
+            "utf16be" | "ucs2be" | "bigendianunicode" => Ok(Some(Encoding::Utf16Be)),
+            _ => bail!(
+                "unknown encoding '{}' (supported: auto, utf-8, utf-16le, utf-16be)",
+                name
+            ),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+        }
+    }
+
+    fn bom(&self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => &[0xEF, 0xBB, 0xBF],
+            Encoding::Utf16Le => &[0xFF, 0xFE],
+            Encoding::Utf16Be => &[0xFE, 0xFF],
+        }
+    }
+}
+
+/// Line ending conversion applied by `transcode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Keep,
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            LineEnding::Keep => text.to_string(),
+            LineEnding::Lf => text.replace("\r\n", "\n"),
+            LineEnding::Crlf => text.replace("\r\n", "\n").replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// Detect the encoding of the start of a stream.
+///
+/// Returns the encoding and the length of its BOM (0 if none).
+pub fn detect(bytes: &[u8]) -> (Encoding, usize) {
+    if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return (Encoding::Utf8, 3);
+    }
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        return (Encoding::Utf16Le, 2);
+    }
+    if bytes.starts_with(&[0xFE, 0xFF]) {
+        return (Encoding::Utf16Be, 2);
+    }
+    (sniff_utf16(bytes).unwrap_or(Encoding::Utf8), 0)
+}
+
+/// BOM-less UTF-16 heuristic: text in Latin scripts has a NUL in every other byte.
+fn sniff_utf16(bytes: &[u8]) -> Option<Encoding> {
+    let pairs = bytes.len() / 2;
+    if pairs < 2 {
+        return None;
+    }
+    let mut even_nul = 0usize;
+    let mut odd_nul = 0usize;
+    for pair in bytes.chunks_exact(2) {
+        if pair[0] == 0 {
+            even_nul += 1;
+        }
+        if pair[1] == 0 {
+            odd_nul += 1;
+        }
+    }
+    // Require a strong, one-sided signal so binary data and UTF-8 are left alone.
+    if odd_nul * 10 >= pairs * 7 && even_nul * 10 <= pairs {
+        Some(Encoding::Utf16Le)
+    } else if even_nul * 10 >= pairs * 7 && odd_nul * 10 <= pairs {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+/// Wrap `reader` so it yields UTF-8.
+///
+/// Detection looks only at what the first `fill_buf` returns (reading on only to complete a
+/// split BOM), so interactive input and pipes stream line by line instead of waiting for
+/// [`SNIFF_LEN`] bytes. `forced` skips detection (a matching BOM is still stripped).
+pub fn text_reader<'a, R: BufRead + 'a>(
+    mut reader: R,
+    forced: Option<Encoding>,
+) -> io::Result<Box<dyn BufRead + 'a>> {
+    // Bytes already taken out of `reader` while completing a BOM split across reads.
+    let mut head: Vec<u8> = Vec::new();
+    let sample = loop {
+        let buf = reader.fill_buf()?;
+        let mut sample = head.clone();
+        sample.extend_from_slice(&buf[..buf.len().min(SNIFF_LEN)]);
+        let split_bom = !buf.is_empty()
+            && sample.len() < 3
+            && [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be]
+                .iter()
+                .any(|enc| enc.bom().starts_with(&sample) && enc.bom().len() > sample.len());
+        if !split_bom {
+            break sample;
+        }
+        let n = buf.len();
+        head.extend_from_slice(buf);
+        reader.consume(n);
+    };
+
+    let (encoding, bom_len) = match forced {
+        Some(enc) if sample.starts_with(enc.bom()) => (enc, enc.bom().len()),
+        Some(enc) => (enc, 0),
+        None => detect(&sample),
+    };
+    if bom_len > head.len() {
+        reader.consume(bom_len - head.len());
+        head.clear();
+    } else {
+        head.drain(..bom_len);
+    }
+
+    let stream = Cursor::new(head).chain(reader);
+    Ok(match encoding {
+        Encoding::Utf8 => Box::new(stream),
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            Box::new(BufReader::new(Utf16Decoder::new(stream, encoding == Encoding::Utf16Be)))
+        }
+    })
+}
+
+/// Streaming UTF-16 -> UTF-8 adapter (invalid code units become U+FFFD).
+struct Utf16Decoder<R> {
+    inner: R,
+    big_endian: bool,
+    /// Odd byte left over from the previous read.
+    carry: Option<u8>,
+    /// High surrogate waiting for its pair.
+    pending_surrogate: Option<u16>,
+    /// Decoded UTF-8 not yet handed out.
+    out: Vec<u8>,
+    out_pos: usize,
+}
+
+impl<R: Read> Utf16Decoder<R> {
+    fn new(inner: R, big_endian: bool) -> Self {
+        Self {
+            inner,
+            big_endian,
+            carry: None,
+            pending_surrogate: None,
+            out: Vec::new(),
+            out_pos: 0,
+        }
+    }
+
+    fn fill(&mut self) -> io::Result<bool> {
+        let mut raw = [0u8; 8192];
+        let n = self.inner.read(&mut raw)?;
+        if n == 0 {
+            if self.carry.take().is_some() || self.pending_surrogate.take().is_some() {
+                self.out.extend_from_slice("\u{FFFD}".as_bytes());
+                return Ok(true);
+            }
+            return Ok(false);
+        }
+
+        let mut bytes: Vec<u8> = Vec::with_capacity(n + 1);
+        bytes.extend(self.carry.take());
+        bytes.extend_from_slice(&raw[..n]);
+        if bytes.len() % 2 == 1 {
+            self.carry = bytes.pop();
+        }
+
+        let mut units: Vec<u16> = Vec::with_capacity(bytes.len() / 2 + 1);
+        units.extend(self.pending_surrogate.take());
+        units.extend(bytes.chunks_exact(2).map(|p| {
+            if self.big_endian {
+                u16::from_be_bytes([p[0], p[1]])
+            } else {
+                u16::from_le_bytes([p[0], p[1]])
+            }
+        }));
+        if units.last().is_some_and(|u| (0xD800..0xDC00).contains(u)) {
+            self.pending_surrogate = units.pop();
+        }
+
+        let text: String = char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect();
+        self.out.extend_from_slice(text.as_bytes());
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for Utf16Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.out_pos >= self.out.len() {
+            self.out.clear();
+            self.out_pos = 0;
+            if !self.fill()? {
+                return Ok(0);
+            }
+        }
+        let n = (self.out.len() - self.out_pos).min(buf.len());
+        buf[..n].copy_from_slice(&self.out[self.out_pos..self.out_pos + n]);
+        self.out_pos += n;
+        Ok(n)
+    }
+}
+
+/// Encode UTF-8 text into `encoding`, optionally with a BOM.
+pub fn encode(text: &str, encoding: Encoding, bom: bool) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len() * 2 + 3);
+    if bom {
+        bytes.extend_from_slice(encoding.bom());
+    }
+    match encoding {
+        Encoding::Utf8 => bytes.extend_from_slice(text.as_bytes()),
+        Encoding::Utf16Le => text.encode_utf16().for_each(|u| bytes.extend(u.to_le_bytes())),
+        Encoding::Utf16Be => text.encode_utf16().for_each(|u| bytes.extend(u.to_be_bytes())),
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(bytes: &[u8], forced: Option<Encoding>) -> String {
+        let mut out = String::new();
+        text_reader(bytes, forced).unwrap().read_to_string(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_detect_boms_and_heuristic() {
+        assert_eq!(detect(b"\xEF\xBB\xBFhi"), (Encoding::Utf8, 3));
+        assert_eq!(detect(b"\xFF\xFEh\0"), (Encoding::Utf16Le, 2));
+        assert_eq!(detect(b"\xFE\xFF\0h"), (Encoding::Utf16Be, 2));
+        assert_eq!(detect(&encode("hello\r\n", Encoding::Utf16Le, false)).0, Encoding::Utf16Le);
+        assert_eq!(detect(&encode("hello\r\n", Encoding::Utf16Be, false)).0, Encoding::Utf16Be);
+        assert_eq!(detect("héllo wörld".as_bytes()), (Encoding::Utf8, 0));
+    }
+
+    #[test]
+    fn test_text_reader_transcodes_utf16() {
+        let text = "line one\r\nzwei 😀\r\n";
+        assert_eq!(read_all(&encode(text, Encoding::Utf16Le, true), None), text);
+        assert_eq!(read_all(&encode(text, Encoding::Utf16Be, true), None), text);
+        assert_eq!(read_all(&encode(text, Encoding::Utf8, true), None), text);
+        assert_eq!(
+            read_all(&encode(text, Encoding::Utf16Le, false), Some(Encoding::Utf16Le)),
+            text
+        );
+    }
+
+    /// Hands out one scripted chunk per read and fails if read past the end, like a pipe whose
+    /// writer has not sent anything more yet.
+    struct Chunks(Vec<&'static [u8]>);
+
+    impl Read for Chunks {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            assert!(!self.0.is_empty(), "read blocked waiting for more input");
+            let chunk = self.0.remove(0);
+            buf[..chunk.len()].copy_from_slice(chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    #[test]
+    fn test_text_reader_streams_without_waiting_for_more_input() {
+        let mut lines = text_reader(BufReader::new(Chunks(vec![b"hi\n"])), None).unwrap().lines();
+        assert_eq!(lines.next().unwrap().unwrap(), "hi");
+
+        // A BOM split across reads is completed before detection, then stripped.
+        let chunks = Chunks(vec![b"\xEF", b"\xBB", b"\xBFok\n"]);
+        let mut lines = text_reader(BufReader::new(chunks), None).unwrap().lines();
+        assert_eq!(lines.next().unwrap().unwrap(), "ok");
+        let chunks = Chunks(vec![b"\xFF", b"\xFEo\0k\0\n\0", b""]);
+        assert_eq!(text_reader(BufReader::new(chunks), None).unwrap().lines().next().unwrap().unwrap(), "ok");
+    }
+
+    #[test]
+    fn test_parse_and_line_endings() {
+        assert_eq!(Encoding::parse("UTF-16").unwrap(), Some(Encoding::Utf16Le));
+        assert_eq!(Encoding::parse("utf_8").unwrap(), Some(Encoding::Utf8));
+        assert_eq!(Encoding::parse("auto").unwrap(), None);
+        assert!(Encoding::parse("latin-7").is_err());
+        assert_eq!(LineEnding::Lf.apply("a\r\nb\n"), "a\nb\n");
+        assert_eq!(LineEnding::Crlf.apply("a\r\nb\n"), "a\r\nb\r\n");
+    }
+}
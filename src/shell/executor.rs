@@ -2,21 +2,25 @@
 
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::{self, BufRead, BufReader, Read, Write};
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::thread;
 use anyhow::{bail, Context, Result};
 
-use crate::task::{register_pid, unregister_pid, TaskId, TaskManager};
+use crate::task::{spawn_child, unregister_pid, TaskId, TaskManager};
 use super::builtin;
 use super::parser::{
-    needs_shell_features, split_args, Command as AstCommand, RedirectMode, Word, QuoteMode,
+    self, needs_shell_features, split_args, Command as AstCommand, RedirectMode, Word, WordPart, QuoteMode,
 };
 use glob::glob;
 use os_pipe::{PipeReader, PipeWriter};
-use super::path;
+use super::cond::{self, CondWord};
+use super::encoding;
+use super::guard;
+use super::path::{self, DevicePath};
 use super::busybox;
+use super::policy::{self, DispatchPolicy};
 use super::venv;
 use super::Shell;
 
@@ -77,29 +81,48 @@ pub fn execute(cmd: &str, cwd: &Path) -> Result<i32> {
     }
 }
 
-/// Execute a command in background
+/// Execute a command in background. `nohup` jobs append their output to `nohup.out` and keep
+/// running after titanbash exits.
 pub fn execute_background(
     tasks: &mut TaskManager,
     cmd: &str,
     cwd: &Path,
     aliases: &HashMap<String, String>,
+    nohup: bool,
 ) -> Result<TaskId> {
     let cmd_owned = cmd.to_string();
     let cwd_owned = cwd.to_path_buf();
     let aliases_owned = aliases.clone();
     let use_shell = needs_shell_features(cmd);
+    let nohup_out = if nohup {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(cwd.join("nohup.out"))
+            .context("nohup: cannot open 'nohup.out'")?;
+        Some(file)
+    } else {
+        None
+    };
 
-    let id = tasks.spawn(cmd, move |pid| {
+    let id = tasks.spawn_with(cmd, nohup, move |pid| {
         // For background jobs, discard output by default.
         //
         // Why: piping + user-space draining can still backpressure high-throughput loggers under
         // CPU contention (common in ML/GPU workloads), which makes the child appear "stuttery" or
         // "hung". Discarding output avoids that class of stalls and matches the current UX (we
         // don't print background output anyway; only job status is shown).
-        let io = IoStreams {
-            stdin: InputStream::Null,
-            stdout: OutputStream::Null,
-            stderr: OutputStream::Null,
+        let io = match nohup_out {
+            Some(file) => IoStreams {
+                stdin: InputStream::Null,
+                stdout: OutputStream::File(file.try_clone()?),
+                stderr: OutputStream::File(file),
+            },
+            None => IoStreams {
+                stdin: InputStream::Null,
+                stdout: OutputStream::Null,
+                stderr: OutputStream::Null,
+            },
         };
 
         let mut child = if use_shell {
@@ -114,12 +137,11 @@ pub fn execute_background(
             if expanded.is_empty() {
                 return Ok((0, String::new()));
             }
-            spawn_external_stage(&expanded, &cwd_owned, io)?
+            spawn_external_stage(&expanded, &cwd_owned, io, false)?
         };
 
         let child_pid = child.id();
-        *pid.lock().unwrap_or_else(|p| p.into_inner()) = Some(child_pid);
-        register_pid(child_pid);
+        pid.set(child_pid);
 
         let status = child.wait()?;
         unregister_pid(child_pid);
@@ -128,6 +150,9 @@ pub fn execute_background(
     })?;
 
     println!("[{}] Started: {}", id, cmd);
+    if nohup {
+        println!("nohup: appending output to 'nohup.out'");
+    }
     Ok(id)
 }
 
@@ -148,8 +173,7 @@ pub fn execute_capture(cmd: &str, cwd: &Path) -> Result<(i32, String, String)> {
     // Read stdout
     let stdout_handle = thread::spawn(move || {
         let mut buf = String::new();
-        if let Some(out) = stdout {
-            let reader = BufReader::new(out);
+        if let Some(reader) = stdout.and_then(|out| encoding::text_reader(io::BufReader::new(out), None).ok()) {
             for line in reader.lines().map_while(Result::ok) {
                 println!("{}", line);
                 buf.push_str(&line);
@@ -162,8 +186,7 @@ pub fn execute_capture(cmd: &str, cwd: &Path) -> Result<(i32, String, String)> {
     // Read stderr
     let stderr_handle = thread::spawn(move || {
         let mut buf = String::new();
-        if let Some(err) = stderr {
-            let reader = BufReader::new(err);
+        if let Some(reader) = stderr.and_then(|err| encoding::text_reader(io::BufReader::new(err), None).ok()) {
             for line in reader.lines().map_while(Result::ok) {
                 eprintln!("{}", line);
                 buf.push_str(&line);
@@ -185,6 +208,25 @@ pub fn execute_ast(shell: &mut Shell, cmd: &AstCommand) -> Result<i32> {
     execute_node_with_io(shell, cmd, IoStreams::inherit())
 }
 
+/// Execute a parsed AST with stdin empty and stdout + stderr captured together (for `watch`).
+pub fn execute_ast_captured(shell: &mut Shell, cmd: &AstCommand) -> Result<(i32, Vec<u8>)> {
+    let (mut reader, writer) = os_pipe::pipe().context("Failed to create pipe")?;
+    let io = IoStreams {
+        stdin: InputStream::Null,
+        stdout: OutputStream::Pipe(writer.try_clone()?),
+        stderr: OutputStream::Pipe(writer),
+    };
+    let collector = thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = reader.read_to_end(&mut buf);
+        buf
+    });
+    // `io` is consumed here, so the write ends are closed before we wait for the reader.
+    let result = execute_node_with_io(shell, cmd, io);
+    let output = collector.join().unwrap_or_default();
+    Ok((result?, output))
+}
+
 #[derive(Debug)]
 enum InputStream {
     Inherit,
@@ -239,6 +281,17 @@ impl OutputStream {
             OutputStream::File(f) => Stdio::from(f),
         }
     }
+
+    /// The stream as a writer for in-process output (builtins, shell diagnostics); `Inherit`
+    /// writes to `inherited`, titanbash's own stdout or stderr.
+    fn into_writer(self, inherited: Box<dyn Write>) -> Box<dyn Write> {
+        match self {
+            OutputStream::Inherit => inherited,
+            OutputStream::Null => Box::new(io::sink()),
+            OutputStream::Pipe(w) => Box::new(w),
+            OutputStream::File(f) => Box::new(f),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -291,33 +344,65 @@ fn apply_redirects(shell: &mut Shell, mut io: IoStreams, redirects: &[RedirectSp
                 io.stderr = io.stdout.try_clone()?;
             }
             RedirectMode::Input => {
+                let target_text = expand_word_first(shell, r.target)?;
+                if let Some(dev) = path::device_path(&target_text) {
+                    match dev {
+                        DevicePath::Null => io.stdin = InputStream::Null,
+                        DevicePath::Stdin => {}
+                        DevicePath::Stdout | DevicePath::Stderr => {
+                            bail!("redirect: cannot read from '{}'", target_text)
+                        }
+                    }
+                    continue;
+                }
+
                 let input_path = resolve_redirect_target(shell, r.target)?;
                 let f = fs::File::open(&input_path)
                     .with_context(|| format!("redirect: cannot read '{}'", input_path.display()))?;
                 io.stdin = InputStream::File(f);
             }
-            RedirectMode::Overwrite | RedirectMode::Append => {
-                let output_path = resolve_redirect_target(shell, r.target)?;
+            RedirectMode::Overwrite | RedirectMode::ForceOverwrite | RedirectMode::Append => {
                 let target_text = expand_word_first(shell, r.target)?;
+                if let Some(dev) = path::device_path(&target_text) {
+                    io.stdout = device_output_stream(&io, dev, &target_text)?;
+                    continue;
+                }
+
+                let output_path = resolve_redirect_target(shell, r.target)?;
                 if path::is_windows_reserved_name(&output_path) {
                     bail!("redirect: {}", path::reserved_name_error(&target_text));
                 }
+                if matches!(r.mode, RedirectMode::Overwrite) {
+                    check_clobber(shell, &output_path)?;
+                }
+                if !matches!(r.mode, RedirectMode::Append) {
+                    check_truncate_guard(shell, &output_path)?;
+                }
 
                 let f = fs::OpenOptions::new()
                     .create(true)
                     .write(true)
-                    .truncate(matches!(r.mode, RedirectMode::Overwrite))
+                    .truncate(!matches!(r.mode, RedirectMode::Append))
                     .append(matches!(r.mode, RedirectMode::Append))
                     .open(&output_path)
                     .with_context(|| format!("redirect: cannot open '{}'", output_path.display()))?;
                 io.stdout = OutputStream::File(f);
             }
             RedirectMode::StderrOverwrite | RedirectMode::StderrAppend => {
-                let output_path = resolve_redirect_target(shell, r.target)?;
                 let target_text = expand_word_first(shell, r.target)?;
+                if let Some(dev) = path::device_path(&target_text) {
+                    io.stderr = device_output_stream(&io, dev, &target_text)?;
+                    continue;
+                }
+
+                let output_path = resolve_redirect_target(shell, r.target)?;
                 if path::is_windows_reserved_name(&output_path) {
                     bail!("redirect: {}", path::reserved_name_error(&target_text));
                 }
+                if matches!(r.mode, RedirectMode::StderrOverwrite) {
+                    check_clobber(shell, &output_path)?;
+                    check_truncate_guard(shell, &output_path)?;
+                }
 
                 let f = fs::OpenOptions::new()
                     .create(true)
@@ -333,6 +418,73 @@ fn apply_redirects(shell: &mut Shell, mut io: IoStreams, redirects: &[RedirectSp
     Ok(io)
 }
 
+/// `/dev/null` discards, `/dev/stdout` / `/dev/stderr` alias the streams as they are so far.
+fn device_output_stream(io: &IoStreams, dev: DevicePath, target_text: &str) -> Result<OutputStream> {
+    match dev {
+        DevicePath::Null => Ok(OutputStream::Null),
+        DevicePath::Stdout => io.stdout.try_clone(),
+        DevicePath::Stderr => io.stderr.try_clone(),
+        DevicePath::Stdin => bail!("redirect: cannot write to '{}'", target_text),
+    }
+}
+
+/// Glob patterns (matched against the file name) that always require `>|` to overwrite.
+///
+/// Example: `export TITANBASH_PROTECTED='*.lock;.env*'`
+pub const VAR_PROTECTED_REDIRECTS: &str = "TITANBASH_PROTECTED";
+
+fn protected_pattern_for(file_name: &str) -> Option<String> {
+    let patterns = std::env::var(VAR_PROTECTED_REDIRECTS).ok()?;
+    let opts = glob::MatchOptions {
+        case_sensitive: false,
+        ..Default::default()
+    };
+    patterns
+        .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+        .filter(|p| !p.is_empty())
+        .find(|p| glob::Pattern::new(p).is_ok_and(|pat| pat.matches_with(file_name, opts)))
+        .map(|p| p.to_string())
+}
+
+/// Refuse to truncate an existing regular file under `noclobber` or a protected pattern.
+fn check_clobber(shell: &Shell, target: &Path) -> Result<()> {
+    if !target.is_file() {
+        return Ok(());
+    }
+
+    if shell.options.noclobber {
+        bail!(
+            "redirect: cannot overwrite existing file '{}' (noclobber is set; use >| to force)",
+            target.display()
+        );
+    }
+
+    let file_name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if let Some(pattern) = protected_pattern_for(&file_name) {
+        bail!(
+            "redirect: cannot overwrite existing file '{}' (protected by pattern '{}'; use >| to force)",
+            target.display(),
+            pattern
+        );
+    }
+
+    Ok(())
+}
+
+/// Truncating an existing file inside a protected tree (`%WINDIR%`, ...) needs confirmation.
+fn check_truncate_guard(shell: &Shell, target: &Path) -> Result<()> {
+    if !target.is_file() {
+        return Ok(());
+    }
+    if let Some(reason) = guard::check_write(target) {
+        guard::confirm(shell, "redirect", "truncate", target, &reason)?;
+    }
+    Ok(())
+}
+
 fn execute_node_with_io(shell: &mut Shell, cmd: &AstCommand, io: IoStreams) -> Result<i32> {
     let (base, redirects) = split_redirects(cmd);
     let io = if redirects.is_empty() {
@@ -347,27 +499,28 @@ fn execute_node_with_io(shell: &mut Shell, cmd: &AstCommand, io: IoStreams) -> R
         AstCommand::Sequence(list) => {
             let mut last = 0;
             for c in list {
-                last = execute_node_with_io(shell, c, IoStreams::inherit())?;
+                last = execute_node_with_io(shell, c, io.try_clone()?)?;
             }
             Ok(last)
         }
         AstCommand::Pipeline(stages) => execute_pipeline_with_io(shell, stages, io),
         AstCommand::And(left, right) => {
-            let code = execute_node_with_io(shell, left, io)?;
+            let code = execute_node_with_io(shell, left, io.try_clone()?)?;
             if code == 0 {
-                execute_node_with_io(shell, right, IoStreams::inherit())
+                execute_node_with_io(shell, right, io)
             } else {
                 Ok(code)
             }
         }
         AstCommand::Or(left, right) => {
-            let code = execute_node_with_io(shell, left, io)?;
+            let code = execute_node_with_io(shell, left, io.try_clone()?)?;
             if code != 0 {
-                execute_node_with_io(shell, right, IoStreams::inherit())
+                execute_node_with_io(shell, right, io)
             } else {
                 Ok(code)
             }
         }
+        AstCommand::Conditional(words) => execute_conditional(shell, words),
         AstCommand::Background(_) => bail!("Background jobs must be handled by Shell"),
         AstCommand::Redirect { .. } => unreachable!("redirects flattened above"),
     }
@@ -401,7 +554,7 @@ fn execute_pipeline_with_io(shell: &mut Shell, stages: &[AstCommand], io: IoStre
             bail!("pipeline: unsupported stage");
         };
 
-        let aliased = expand_alias_words(&shell.aliases, words);
+        let aliased = expand_command_words(&shell.aliases, words);
         let expanded = expand_words(shell, &aliased)?;
         if expanded.is_empty() {
             bail!("pipeline: empty stage");
@@ -436,13 +589,24 @@ fn execute_pipeline_with_io(shell: &mut Shell, stages: &[AstCommand], io: IoStre
             }
 
             let stage_cwd = cwd.clone();
+            let stage_options = shell.options.clone();
+            let stage_aliases = shell.aliases.clone();
+            let stage_dir_stack = shell.dir_stack.clone();
+            let stage_vars = shell.vars.clone();
+            let stage_var_attrs = shell.var_attrs.clone();
+            let stage_arrays = shell.arrays.clone();
             let handle = thread::spawn(move || {
                 let mut temp_shell = Shell {
                     cwd: stage_cwd,
                     tasks: TaskManager::new(),
-                    aliases: HashMap::new(),
-                    vars: HashMap::new(),
+                    aliases: stage_aliases,
+                    vars: stage_vars,
+                    var_attrs: stage_var_attrs,
+                    arrays: stage_arrays,
                     last_status: 0,
+                    options: stage_options,
+                    interactive: false,
+                    dir_stack: stage_dir_stack,
                     should_exit: false,
                     exit_warned: false,
                 };
@@ -450,7 +614,7 @@ fn execute_pipeline_with_io(shell: &mut Shell, stages: &[AstCommand], io: IoStre
             });
             handles.push(StageHandle::Builtin(handle));
         } else {
-            let child = spawn_external_stage(&expanded, &cwd, stage_io)?;
+            let child = spawn_external_stage(&expanded, &cwd, stage_io, shell.options.devargs)?;
             handles.push(StageHandle::External(child));
         }
     }
@@ -473,28 +637,21 @@ fn execute_pipeline_with_io(shell: &mut Shell, stages: &[AstCommand], io: IoStre
 
 fn run_builtin_stage(shell: &mut Shell, name: &str, args: &[String], io: IoStreams) -> Result<i32> {
     // stdin
+    let stdin_tty = matches!(io.stdin, InputStream::Inherit) && io::stdin().is_terminal();
     let mut stdin_box: Box<dyn BufRead> = match io.stdin {
-        InputStream::Inherit => Box::new(BufReader::new(io::stdin())),
+        // The process-wide stdin buffer, so input read ahead by one command is there for the next.
+        InputStream::Inherit => Box::new(io::stdin().lock()),
         InputStream::Null => Box::new(BufReader::new(io::empty())),
         InputStream::Pipe(r) => Box::new(BufReader::new(r)),
         InputStream::File(f) => Box::new(BufReader::new(f)),
     };
 
     // stdout
-    let mut stdout_box: Box<dyn Write> = match io.stdout {
-        OutputStream::Inherit => Box::new(io::stdout()),
-        OutputStream::Null => Box::new(io::sink()),
-        OutputStream::Pipe(w) => Box::new(w),
-        OutputStream::File(f) => Box::new(f),
-    };
+    let stdout_tty = matches!(io.stdout, OutputStream::Inherit) && io::stdout().is_terminal();
+    let mut stdout_box = io.stdout.into_writer(Box::new(io::stdout()));
 
     // stderr
-    let mut stderr_box: Box<dyn Write> = match io.stderr {
-        OutputStream::Inherit => Box::new(io::stderr()),
-        OutputStream::Null => Box::new(io::sink()),
-        OutputStream::Pipe(w) => Box::new(w),
-        OutputStream::File(f) => Box::new(f),
-    };
+    let mut stderr_box = io.stderr.into_writer(Box::new(io::stderr()));
 
     match builtin::run_builtin_io(
         shell,
@@ -503,6 +660,8 @@ fn run_builtin_stage(shell: &mut Shell, name: &str, args: &[String], io: IoStrea
         &mut *stdin_box,
         &mut *stdout_box,
         &mut *stderr_box,
+        stdin_tty,
+        stdout_tty,
     ) {
         Ok(code) => {
             let _ = stdout_box.flush();
@@ -522,7 +681,7 @@ fn execute_simple_with_io(shell: &mut Shell, argv: &[Word], io: IoStreams) -> Re
         return Ok(0);
     }
 
-    let aliased = expand_alias_words(&shell.aliases, argv);
+    let aliased = expand_command_words(&shell.aliases, argv);
     let expanded = expand_words(shell, &aliased)?;
     if expanded.is_empty() {
         return Ok(0);
@@ -531,79 +690,587 @@ fn execute_simple_with_io(shell: &mut Shell, argv: &[Word], io: IoStreams) -> Re
     let name = &expanded[0];
     let args: Vec<String> = expanded.iter().skip(1).cloned().collect();
 
-    // Python venv activation must happen in-process (affects PATH/VIRTUAL_ENV).
-    if let Some(code) = venv::try_activate_from_command(shell, name)? {
-        return Ok(code);
+    let resolution = resolve_command(name, &shell.cwd);
+    if shell.options.autocd
+        && args.is_empty()
+        && matches!(resolution, Resolution::CmdFallback | Resolution::NotFound)
+        && path::resolve_fs(&shell.cwd, name).is_dir()
+    {
+        return run_builtin_stage(shell, "cd", &expanded, io);
     }
 
-    if builtin::is_builtin(name) {
-        return run_builtin_stage(shell, name, &args, io);
+    match resolution {
+        // Python venv activation must happen in-process (affects PATH/VIRTUAL_ENV).
+        Resolution::VenvActivate(venv_dir) => {
+            venv::activate(shell, &venv_dir)?;
+            Ok(0)
+        }
+        Resolution::Builtin => run_builtin_stage(shell, name, &args, io),
+        Resolution::NotFound => {
+            // To the stage's stderr, so `2>` and `2>&1` capture it.
+            let mut err = io.stderr.into_writer(Box::new(io::stderr()));
+            let _ = writeln!(err, "titanbash: {}: command not found", name);
+            Ok(127)
+        }
+        resolution => {
+            let argv = if shell.options.devargs {
+                map_device_args(&resolution, &expanded)
+            } else {
+                expanded
+            };
+            let mut child = spawn_resolved(&resolution, &argv, &shell.cwd, io)?;
+            Ok(child.wait()?.code().unwrap_or(-1))
+        }
     }
+}
 
-    let io_direct = io.try_clone()?;
-    match spawn_external_direct(&expanded, &shell.cwd, io_direct) {
-        Ok(mut child) => Ok(child.wait()?.code().unwrap_or(-1)),
-        Err(e) => {
-            let io_ps1 = io.try_clone()?;
-            if let Some(mut child) = try_spawn_ps1_fallback(&expanded, &shell.cwd, io_ps1)? {
-                return Ok(child.wait()?.code().unwrap_or(-1));
-            }
+/// How a command name is dispatched.
+///
+/// Produced by [`resolve_command`] / [`resolve_external`] without spawning anything, so the
+/// same decision drives both execution and `explain`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    /// In-process builtin.
+    Builtin,
+    /// `...\Scripts\activate*` path: venv activation in-process.
+    VenvActivate(PathBuf),
+    /// Native executable.
+    Native(PathBuf),
+    /// Explicit `.bat` / `.cmd` script, run via `cmd /C`.
+    CmdScript(PathBuf),
+    /// Explicit `.ps1` script, run via PowerShell.
+    PowerShell(PathBuf),
+    /// `<name>.ps1` found in the current directory or on PATH.
+    Ps1Fallback(PathBuf),
+    /// BusyBox applet.
+    Busybox { busybox: PathBuf, applet: String },
+    /// Last resort: hand the whole command line to `cmd /C`.
+    CmdFallback,
+    /// Nothing matched and strict mode disables the `cmd /C` fallback.
+    NotFound,
+}
 
-            if is_not_found_error(&e) {
-                let io_bb = io.try_clone()?;
-                if let Some(mut child) = try_spawn_busybox_applet(&expanded, &shell.cwd, io_bb)? {
-                    return Ok(child.wait()?.code().unwrap_or(-1));
-                }
+impl Resolution {
+    /// Human-readable description of the decision (used by `explain`).
+    pub fn describe(&self, argv: &[String]) -> String {
+        match self {
+            Resolution::Builtin => "builtin".to_string(),
+            Resolution::VenvActivate(dir) => format!("venv activation (in-process): {}", dir.display()),
+            Resolution::Native(exe) => format!("native executable: {}", exe.display()),
+            Resolution::CmdScript(script) => format!("cmd script: cmd /C {}", script.display()),
+            Resolution::PowerShell(script) => {
+                format!("PowerShell script: powershell -File {}", script.display())
+            }
+            Resolution::Ps1Fallback(script) => {
+                format!("PowerShell fallback: powershell -File {}", script.display())
+            }
+            Resolution::Busybox { busybox, applet } => {
+                format!("BusyBox applet '{}': {}", applet, busybox.display())
             }
+            Resolution::CmdFallback => format!("cmd fallback: cmd /C {}", join_cmdline(argv)),
+            Resolution::NotFound => format!(
+                "not found (strict mode: cmd fallback disabled via {})",
+                policy::VAR_STRICT
+            ),
+        }
+    }
 
-            let cmdline = join_cmdline(&expanded);
-            let mut child = spawn_cmd_with_io(&cmdline, &shell.cwd, io)?;
-            Ok(child.wait()?.code().unwrap_or(-1))
+    /// BusyBox applet hidden by this resolution (a native executable found first on PATH).
+    pub fn shadowed_applet(&self, name: &str, policy: &DispatchPolicy) -> Option<String> {
+        let Resolution::Native(_) = self else { return None };
+        if busybox::looks_like_path(name) || Path::new(name).extension().is_some() {
+            return None;
         }
+        let applet = busybox::normalize_applet_name(name);
+        (busybox::has_applet(&applet) && !policy.is_configured(&applet)).then_some(applet)
     }
 }
 
-fn spawn_external_stage(argv: &[String], cwd: &Path, io: IoStreams) -> Result<std::process::Child> {
-    let io_direct = io.try_clone()?;
-    match spawn_external_direct(argv, cwd, io_direct) {
-        Ok(child) => Ok(child),
-        Err(e) => {
-            let io_ps1 = io.try_clone()?;
-            if let Some(child) = try_spawn_ps1_fallback(argv, cwd, io_ps1)? {
-                return Ok(child);
+/// Resolve a foreground command name: venv activation, then builtins, then external dispatch.
+pub fn resolve_command(name: &str, cwd: &Path) -> Resolution {
+    if let Some(venv_dir) = venv::try_extract_venv_dir(cwd, name) {
+        return Resolution::VenvActivate(venv_dir);
+    }
+    if builtin::is_builtin(name) {
+        return Resolution::Builtin;
+    }
+    resolve_external(name, cwd)
+}
+
+/// Resolve an external command name (pipeline stages and background jobs skip builtins).
+///
+/// Order: explicit script extension, BusyBox for preferred applets, native executable,
+/// `<name>.ps1`, BusyBox applet, then `cmd /C` as a last resort (unless strict).
+pub fn resolve_external(name: &str, cwd: &Path) -> Resolution {
+    resolve_external_with_policy(name, cwd, &DispatchPolicy::from_env())
+}
+
+fn resolve_external_with_policy(name: &str, cwd: &Path, policy: &DispatchPolicy) -> Resolution {
+    let lower = name.to_ascii_lowercase();
+    if lower.ends_with(".ps1") {
+        return Resolution::PowerShell(find_script(name, cwd));
+    }
+    if lower.ends_with(".bat") || lower.ends_with(".cmd") {
+        return Resolution::CmdScript(find_script(name, cwd));
+    }
+
+    if policy.prefers_busybox(name) {
+        if let Some(resolution) = resolve_busybox(name) {
+            return resolution;
+        }
+    }
+
+    if let Some(exe) = find_native_executable(name, cwd) {
+        return Resolution::Native(exe);
+    }
+
+    if Path::new(name).extension().is_none() {
+        if let Some(script) = find_ps1_candidate(name, cwd) {
+            return Resolution::Ps1Fallback(script);
+        }
+    }
+
+    if !busybox::looks_like_path(name) && policy.allows_busybox(&busybox::normalize_applet_name(name)) {
+        if let Some(resolution) = resolve_busybox(name) {
+            return resolution;
+        }
+    }
+
+    if policy.strict {
+        Resolution::NotFound
+    } else {
+        Resolution::CmdFallback
+    }
+}
+
+fn resolve_busybox(name: &str) -> Option<Resolution> {
+    let applet = busybox::normalize_applet_name(name);
+    let bb = busybox::get().filter(|_| busybox::has_applet(&applet))?;
+    Some(Resolution::Busybox {
+        busybox: bb.path.clone(),
+        applet,
+    })
+}
+
+/// One way a command name can resolve, as listed by `type -a` / `command -V`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Candidate {
+    Alias(String),
+    Builtin,
+    /// Native executable or explicit script
+    File(PathBuf),
+    /// `<name>.ps1` fallback
+    Ps1(PathBuf),
+    Busybox { busybox: PathBuf, applet: String },
+}
+
+/// Every way `name` could be dispatched, in dispatch order: alias, builtin, executables on
+/// PATH (all of them, first one wins), `<name>.ps1`, BusyBox applet.
+///
+/// Shell functions do not exist in titanbash, so there is no function candidate.
+pub fn command_candidates(shell: &Shell, name: &str, aliases: bool) -> Vec<Candidate> {
+    let policy = DispatchPolicy::from_env();
+    let mut candidates = Vec::new();
+
+    if aliases {
+        if let Some(value) = shell.aliases.get(name) {
+            candidates.push(Candidate::Alias(value.clone()));
+        }
+    }
+    if builtin::is_builtin(name) {
+        candidates.push(Candidate::Builtin);
+    }
+
+    let busybox = if !busybox::looks_like_path(name) && policy.allows_busybox(&busybox::normalize_applet_name(name)) {
+        resolve_busybox(name).and_then(|resolution| match resolution {
+            Resolution::Busybox { busybox, applet } => Some(Candidate::Busybox { busybox, applet }),
+            _ => None,
+        })
+    } else {
+        None
+    };
+    let busybox_first = policy.prefers_busybox(name);
+    if busybox_first {
+        candidates.extend(busybox.clone());
+    }
+
+    let lower = name.to_ascii_lowercase();
+    if [".ps1", ".bat", ".cmd"].iter().any(|ext| lower.ends_with(ext)) {
+        let script = find_script(name, &shell.cwd);
+        if script.is_file() {
+            candidates.push(Candidate::File(script));
+        }
+    } else if busybox::looks_like_path(name) {
+        candidates.extend(find_native_executable(name, &shell.cwd).map(Candidate::File));
+    } else {
+        let file_name = if cfg!(windows) && Path::new(name).extension().is_none() {
+            format!("{}.exe", name)
+        } else {
+            name.to_string()
+        };
+        candidates.extend(search_path_env_all(&file_name).into_iter().map(Candidate::File));
+    }
+
+    if Path::new(name).extension().is_none() {
+        candidates.extend(find_ps1_candidate(name, &shell.cwd).map(Candidate::Ps1));
+    }
+    if !busybox_first {
+        candidates.extend(busybox);
+    }
+    candidates
+}
+
+/// Locate a native executable the way process creation would: names with a path component
+/// are resolved against `cwd`, bare names are searched on PATH (`.exe` is implied on Windows).
+fn find_native_executable(name: &str, cwd: &Path) -> Option<PathBuf> {
+    let file_name = if cfg!(windows) && Path::new(name).extension().is_none() {
+        format!("{}.exe", name)
+    } else {
+        name.to_string()
+    };
+
+    if busybox::looks_like_path(name) {
+        let candidate = path::resolve_fs(cwd, &file_name);
+        return candidate.is_file().then_some(candidate);
+    }
+
+    search_path_env(&file_name)
+}
+
+/// Locate an explicit `.bat`/`.cmd`/`.ps1` script (cwd first, then PATH).
+///
+/// Falls back to the name as typed so the script host reports the error.
+fn find_script(name: &str, cwd: &Path) -> PathBuf {
+    let candidate = path::resolve_fs(cwd, name);
+    if candidate.is_file() || busybox::looks_like_path(name) {
+        return candidate;
+    }
+    search_path_env(name).unwrap_or_else(|| PathBuf::from(name))
+}
+
+fn search_path_env(file_name: &str) -> Option<PathBuf> {
+    let path_env = std::env::var_os("PATH")?;
+    std::env::split_paths(&path_env)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(|dir| PathBuf::from(path::add_long_path_prefix(&dir.join(file_name).to_string_lossy())))
+        .find(|p| p.is_file())
+}
+
+/// Every `file_name` on PATH, in PATH order (duplicate directories listed once).
+fn search_path_env_all(file_name: &str) -> Vec<PathBuf> {
+    let Some(path_env) = std::env::var_os("PATH") else {
+        return Vec::new();
+    };
+    let mut found: Vec<PathBuf> = Vec::new();
+    for candidate in std::env::split_paths(&path_env)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(|dir| PathBuf::from(path::add_long_path_prefix(&dir.join(file_name).to_string_lossy())))
+    {
+        if candidate.is_file() && !found.contains(&candidate) {
+            found.push(candidate);
+        }
+    }
+    found
+}
+
+fn spawn_resolved(
+    resolution: &Resolution,
+    argv: &[String],
+    cwd: &Path,
+    io: IoStreams,
+) -> Result<std::process::Child> {
+    let args_only: Vec<&str> = argv.iter().skip(1).map(|s| s.as_str()).collect();
+
+    match resolution {
+        Resolution::Native(exe) => {
+            let policy = DispatchPolicy::from_env();
+            if let Some(applet) = resolution.shadowed_applet(&argv[0], &policy) {
+                policy::warn_shadowed_once(&applet, exe);
             }
 
-            if is_not_found_error(&e) {
-                let io_bb = io.try_clone()?;
-                if let Some(child) = try_spawn_busybox_applet(argv, cwd, io_bb)? {
-                    return Ok(child);
+            // Keep the historical safety net: if a found executable cannot be spawned directly
+            // (e.g. it requires elevation), let cmd.exe try (never in strict mode).
+            let io_cmd = io.try_clone()?;
+            let mut cmd = Command::new(exe);
+            cmd.args(&args_only)
+                .current_dir(cwd)
+                .stdin(io.stdin.into_stdio())
+                .stdout(io.stdout.into_stdio())
+                .stderr(io.stderr.into_stdio());
+            match spawn_child(&mut cmd) {
+                Ok(child) => Ok(child),
+                Err(e) if policy.strict => {
+                    Err(e).with_context(|| format!("Failed to execute: {}", exe.display()))
                 }
+                Err(_) => spawn_cmd_with_io(&join_cmdline(argv), cwd, io_cmd),
             }
-
-            let cmdline = join_cmdline(argv);
-            spawn_cmd_with_io(&cmdline, cwd, io)
+        }
+        Resolution::CmdScript(script) => {
+            spawn_cmd_script_with_io(&script.to_string_lossy(), &args_only, cwd, io)
+        }
+        Resolution::PowerShell(script) | Resolution::Ps1Fallback(script) => {
+            spawn_powershell_with_io(&script.to_string_lossy(), &args_only, cwd, io)
+        }
+        Resolution::Busybox { applet, .. } => {
+            let mut applet_argv: Vec<String> = Vec::with_capacity(argv.len());
+            applet_argv.push(applet.clone());
+            applet_argv.extend(argv.iter().skip(1).cloned());
+            let Some(bb_argv) = busybox::resolve_busybox_argv(applet, &applet_argv) else {
+                bail!("busybox: applet '{}' is not available", applet);
+            };
+            spawn_external_direct(&bb_argv, cwd, io)
+        }
+        Resolution::CmdFallback => spawn_cmd_with_io(&join_cmdline(argv), cwd, io),
+        Resolution::NotFound => {
+            bail!("{}: command not found", argv.first().map(|s| s.as_str()).unwrap_or_default())
+        }
+        Resolution::Builtin | Resolution::VenvActivate(_) => {
+            bail!("'{}' must run in-process", argv.first().map(|s| s.as_str()).unwrap_or_default())
         }
     }
 }
 
-fn try_spawn_ps1_fallback(
+fn spawn_external_stage(
     argv: &[String],
     cwd: &Path,
     io: IoStreams,
-) -> Result<Option<std::process::Child>> {
-    let Some(cmd) = argv.first() else { return Ok(None) };
-    let cmd_path = Path::new(cmd);
-    if cmd_path.extension().is_some() {
-        return Ok(None);
+    devargs: bool,
+) -> Result<std::process::Child> {
+    let Some(name) = argv.first() else {
+        bail!("execute: empty argv");
+    };
+    let resolution = resolve_external(name, cwd);
+    if devargs {
+        let mapped = map_device_args(&resolution, argv);
+        return spawn_resolved(&resolution, &mapped, cwd, io);
+    }
+    spawn_resolved(&resolution, argv, cwd, io)
+}
+
+/// `set -o devargs`: rewrite `/dev/null` etc. arguments to `NUL` / `CON` for Windows tools.
+///
+/// BusyBox understands `/dev/null` itself, so its argv is left alone.
+fn map_device_args(resolution: &Resolution, argv: &[String]) -> Vec<String> {
+    if matches!(resolution, Resolution::Busybox { .. }) {
+        return argv.to_vec();
+    }
+    argv.iter()
+        .enumerate()
+        .map(|(i, arg)| match path::device_path(arg) {
+            Some(dev) if i > 0 => dev.windows_name().to_string(),
+            _ => arg.clone(),
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ExplainContext {
+    Foreground,
+    PipelineStage,
+    Background,
+}
+
+/// Print how `line` would be dispatched (AST, expanded argv, resolution, redirects)
+/// without executing anything.
+pub fn explain(shell: &mut Shell, line: &str, out: &mut dyn Write) -> Result<i32> {
+    let parsed = parser::parse(line.trim())?;
+
+    writeln!(out, "AST:")?;
+    write_ast(out, &parsed, 1)?;
+
+    // Background jobs keep the string-based runner (see `Shell::execute`): anything with
+    // shell operators is handed to `cmd /C` as a whole.
+    if let AstCommand::Background(_) = parsed {
+        let cmd_str = line.trim().trim_end_matches('&').trim();
+        if needs_shell_features(cmd_str) {
+            writeln!(out)?;
+            writeln!(out, "Background job: cmd /C {} (output discarded)", cmd_str)?;
+            return Ok(0);
+        }
     }
 
-    let Some(script_path) = find_ps1_candidate(cmd, cwd) else {
-        return Ok(None);
+    let mut stage = 0usize;
+    explain_node(shell, &parsed, ExplainContext::Foreground, &mut stage, out)?;
+    Ok(0)
+}
+
+fn word_source(word: &Word) -> String {
+    word.parts
+        .iter()
+        .map(|p| match p.quote {
+            QuoteMode::None => p.text.clone(),
+            QuoteMode::Single => format!("'{}'", p.text),
+            QuoteMode::Double => format!("\"{}\"", p.text),
+        })
+        .collect()
+}
+
+fn words_source(words: &[Word]) -> String {
+    words.iter().map(word_source).collect::<Vec<_>>().join(" ")
+}
+
+fn redirect_symbol(mode: &RedirectMode) -> &'static str {
+    match mode {
+        RedirectMode::Overwrite => ">",
+        RedirectMode::ForceOverwrite => ">|",
+        RedirectMode::Append => ">>",
+        RedirectMode::Input => "<",
+        RedirectMode::StderrOverwrite => "2>",
+        RedirectMode::StderrAppend => "2>>",
+        RedirectMode::MergeStderrToStdout => "2>&1",
+    }
+}
+
+fn write_ast(out: &mut dyn Write, cmd: &AstCommand, depth: usize) -> Result<()> {
+    let indent = "  ".repeat(depth);
+    match cmd {
+        AstCommand::Empty => writeln!(out, "{}Empty", indent)?,
+        AstCommand::Simple(words) => writeln!(out, "{}Simple: {}", indent, words_source(words))?,
+        AstCommand::Conditional(words) => writeln!(out, "{}Conditional: [[ {} ]]", indent, words_source(words))?,
+        AstCommand::Redirect { cmd, target, mode } => {
+            if matches!(mode, RedirectMode::MergeStderrToStdout) {
+                writeln!(out, "{}Redirect 2>&1", indent)?;
+            } else {
+                writeln!(out, "{}Redirect {} {}", indent, redirect_symbol(mode), word_source(target))?;
+            }
+            write_ast(out, cmd, depth + 1)?;
+        }
+        AstCommand::Sequence(list) | AstCommand::Pipeline(list) => {
+            let label = if matches!(cmd, AstCommand::Sequence(_)) { "Sequence" } else { "Pipeline" };
+            writeln!(out, "{}{}", indent, label)?;
+            for c in list {
+                write_ast(out, c, depth + 1)?;
+            }
+        }
+        AstCommand::And(left, right) | AstCommand::Or(left, right) => {
+            let label = if matches!(cmd, AstCommand::And(..)) { "And (&&)" } else { "Or (||)" };
+            writeln!(out, "{}{}", indent, label)?;
+            write_ast(out, left, depth + 1)?;
+            write_ast(out, right, depth + 1)?;
+        }
+        AstCommand::Background(inner) => {
+            writeln!(out, "{}Background", indent)?;
+            write_ast(out, inner, depth + 1)?;
+        }
+    }
+    Ok(())
+}
+
+fn describe_redirect(shell: &mut Shell, r: &RedirectSpec<'_>) -> Result<String> {
+    if matches!(r.mode, RedirectMode::MergeStderrToStdout) {
+        return Ok("2>&1 (stderr -> stdout)".to_string());
+    }
+    let target_text = expand_word_first(shell, r.target)?;
+    if let Some(dev) = path::device_path(&target_text) {
+        let what = match dev {
+            DevicePath::Null => "discarded",
+            DevicePath::Stdin => "inherited stdin",
+            DevicePath::Stdout => "current stdout",
+            DevicePath::Stderr => "current stderr",
+        };
+        return Ok(format!("{} {} ({})", redirect_symbol(r.mode), target_text, what));
+    }
+    let target = resolve_redirect_target(shell, r.target)?;
+    Ok(format!("{} {}", redirect_symbol(r.mode), target.display()))
+}
+
+fn explain_node(
+    shell: &mut Shell,
+    cmd: &AstCommand,
+    ctx: ExplainContext,
+    stage: &mut usize,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let (base, redirects) = split_redirects(cmd);
+
+    let AstCommand::Simple(words) = base else {
+        for r in &redirects {
+            writeln!(out, "redirect (group): {}", describe_redirect(shell, r)?)?;
+        }
+        match base {
+            AstCommand::Sequence(list) => {
+                for c in list {
+                    explain_node(shell, c, ctx, stage, out)?;
+                }
+            }
+            AstCommand::Pipeline(list) => {
+                for c in list {
+                    explain_node(shell, c, ExplainContext::PipelineStage, stage, out)?;
+                }
+            }
+            AstCommand::And(left, right) | AstCommand::Or(left, right) => {
+                explain_node(shell, left, ctx, stage, out)?;
+                let cond = if matches!(base, AstCommand::And(..)) { "succeeds" } else { "fails" };
+                writeln!(out, "(next stage runs only if the previous one {})", cond)?;
+                explain_node(shell, right, ctx, stage, out)?;
+            }
+            AstCommand::Background(inner) => {
+                explain_node(shell, inner, ExplainContext::Background, stage, out)?;
+            }
+            AstCommand::Conditional(words) => {
+                *stage += 1;
+                writeln!(out)?;
+                writeln!(out, "Stage {}: [[ {} ]]", stage, words_source(words))?;
+                writeln!(out, "  resolve: conditional expression (evaluated in-process)")?;
+            }
+            AstCommand::Empty | AstCommand::Simple(_) | AstCommand::Redirect { .. } => {}
+        }
+        return Ok(());
     };
 
-    let args_only: Vec<&str> = argv.iter().skip(1).map(|s| s.as_str()).collect();
-    let script_str = script_path.to_string_lossy().to_string();
-    Ok(Some(spawn_powershell_with_io(&script_str, &args_only, cwd, io)?))
+    *stage += 1;
+    writeln!(out)?;
+    writeln!(out, "Stage {}: {}", stage, words_source(words))?;
+
+    let aliased = expand_command_words(&shell.aliases, words);
+    if &aliased != words {
+        writeln!(out, "  alias: {}", words_source(&aliased))?;
+    }
+
+    let expanded = expand_words(shell, &aliased)?;
+    writeln!(out, "  argv: {:?}", expanded)?;
+
+    if let Some(name) = expanded.first() {
+        let resolution = match ctx {
+            ExplainContext::Foreground => resolve_command(name, &shell.cwd),
+            ExplainContext::PipelineStage if builtin::is_builtin(name) => Resolution::Builtin,
+            ExplainContext::PipelineStage | ExplainContext::Background => resolve_external(name, &shell.cwd),
+        };
+        let mut resolved = resolution.describe(&expanded);
+        if ctx == ExplainContext::Background {
+            resolved.push_str(" (background job, output discarded)");
+        } else if ctx == ExplainContext::PipelineStage
+            && resolution == Resolution::Builtin
+            && builtin::is_state_builtin(name)
+        {
+            resolved.push_str(" (error: cannot be used in a pipeline)");
+        }
+        writeln!(out, "  resolve: {}", resolved)?;
+        if shell.options.devargs
+            && ctx != ExplainContext::Background
+            && !matches!(resolution, Resolution::Builtin | Resolution::VenvActivate(_))
+        {
+            let mapped = map_device_args(&resolution, &expanded);
+            if mapped != expanded {
+                writeln!(out, "  devargs: {:?}", mapped)?;
+            }
+        }
+        if let Some(applet) = resolution.shadowed_applet(name, &DispatchPolicy::from_env()) {
+            writeln!(
+                out,
+                "  note: shadows BusyBox applet '{}' (set {}={} to use BusyBox)",
+                applet,
+                policy::VAR_BUSYBOX_PREFER,
+                applet
+            )?;
+        }
+    }
+
+    for r in &redirects {
+        writeln!(out, "  redirect: {}", describe_redirect(shell, r)?)?;
+    }
+
+    Ok(())
 }
 
 fn find_ps1_candidate(cmd: &str, cwd: &Path) -> Option<PathBuf> {
@@ -661,69 +1328,33 @@ fn spawn_external_direct(argv: &[String], cwd: &Path, io: IoStreams) -> Result<s
         .stdout(io.stdout.into_stdio())
         .stderr(io.stderr.into_stdio());
 
-    cmd.spawn()
-        .with_context(|| format!("Failed to execute: {}", exe_path))
-}
-
-fn is_not_found_error(err: &anyhow::Error) -> bool {
-    err.chain().any(|e| {
-        e.downcast_ref::<io::Error>()
-            .is_some_and(|ioe| ioe.kind() == io::ErrorKind::NotFound)
-    })
-}
-
-fn try_spawn_busybox_applet(
-    argv: &[String],
-    cwd: &Path,
-    io: IoStreams,
-) -> Result<Option<std::process::Child>> {
-    let Some(cmd) = argv.first() else { return Ok(None) };
-    if busybox::looks_like_path(cmd) {
-        return Ok(None);
-    }
-
-    let applet = busybox::normalize_applet_name(cmd);
-    if !busybox::has_applet(&applet) {
-        return Ok(None);
-    }
-
-    let mut applet_argv: Vec<String> = Vec::with_capacity(argv.len());
-    applet_argv.push(applet.clone());
-    applet_argv.extend(argv.iter().skip(1).cloned());
-
-    let Some(bb_argv) = busybox::resolve_busybox_argv(&applet, &applet_argv) else {
-        return Ok(None);
-    };
-
-    Ok(Some(spawn_external_direct(&bb_argv, cwd, io)?))
+    spawn_child(&mut cmd).with_context(|| format!("Failed to execute: {}", exe_path))
 }
 
 fn spawn_cmd_with_io(cmdline: &str, cwd: &Path, io: IoStreams) -> Result<std::process::Child> {
-    Command::new("cmd")
-        .args(["/C", cmdline])
+    let mut cmd = Command::new("cmd");
+    cmd.args(["/C", cmdline])
         .current_dir(cwd)
         .stdin(io.stdin.into_stdio())
         .stdout(io.stdout.into_stdio())
-        .stderr(io.stderr.into_stdio())
-        .spawn()
-        .with_context(|| format!("Failed to execute via cmd: {}", cmdline))
+        .stderr(io.stderr.into_stdio());
+    spawn_child(&mut cmd).with_context(|| format!("Failed to execute via cmd: {}", cmdline))
 }
 
 fn spawn_cmd_script_with_io(script: &str, args: &[&str], cwd: &Path, io: IoStreams) -> Result<std::process::Child> {
-    Command::new("cmd")
-        .args(["/C", script])
+    let mut cmd = Command::new("cmd");
+    cmd.args(["/C", script])
         .args(args)
         .current_dir(cwd)
         .stdin(io.stdin.into_stdio())
         .stdout(io.stdout.into_stdio())
-        .stderr(io.stderr.into_stdio())
-        .spawn()
-        .with_context(|| format!("Failed to execute script: {}", script))
+        .stderr(io.stderr.into_stdio());
+    spawn_child(&mut cmd).with_context(|| format!("Failed to execute script: {}", script))
 }
 
 fn spawn_powershell_with_io(script: &str, args: &[&str], cwd: &Path, io: IoStreams) -> Result<std::process::Child> {
-    Command::new("powershell")
-        .args([
+    let mut cmd = Command::new("powershell");
+    cmd.args([
             "-NoProfile",
             "-ExecutionPolicy",
             "Bypass",
@@ -734,9 +1365,8 @@ fn spawn_powershell_with_io(script: &str, args: &[&str], cwd: &Path, io: IoStrea
         .current_dir(cwd)
         .stdin(io.stdin.into_stdio())
         .stdout(io.stdout.into_stdio())
-        .stderr(io.stderr.into_stdio())
-        .spawn()
-        .with_context(|| format!("Failed to execute script: {}", script))
+        .stderr(io.stderr.into_stdio());
+    spawn_child(&mut cmd).with_context(|| format!("Failed to execute script: {}", script))
 }
 
 fn resolve_redirect_target(shell: &mut Shell, target: &Word) -> Result<std::path::PathBuf> {
@@ -799,12 +1429,7 @@ fn expand_alias_words(aliases: &HashMap<String, String>, argv: &[Word]) -> Vec<W
             break;
         };
         // First word as string (without expansions)
-        let first_text = first
-            .parts
-            .iter()
-            .map(|p| p.text.as_str())
-            .collect::<Vec<_>>()
-            .join("");
+        let first_text = word_text(first);
         let Some(replacement) = aliases.get(&first_text) else {
             break;
         };
@@ -825,6 +1450,24 @@ fn expand_alias_words(aliases: &HashMap<String, String>, argv: &[Word]) -> Vec<W
     current
 }
 
+/// A word's text as typed, quotes removed, nothing expanded.
+fn word_text(word: &Word) -> String {
+    word.parts.iter().map(|p| p.text.as_str()).collect()
+}
+
+/// Alias expansion, except that `command name args...` runs `name` with no alias lookup.
+fn expand_command_words(aliases: &HashMap<String, String>, argv: &[Word]) -> Vec<Word> {
+    let aliased = expand_alias_words(aliases, argv);
+    match aliased.split_first() {
+        Some((first, rest))
+            if word_text(first) == "command" && rest.first().is_some_and(|w| !word_text(w).starts_with('-')) =>
+        {
+            rest.to_vec()
+        }
+        _ => aliased,
+    }
+}
+
 /// Expand environment variables and glob patterns in all arguments
 fn expand_words(shell: &mut Shell, argv: &[Word]) -> Result<Vec<String>> {
     let mut out = Vec::new();
@@ -835,24 +1478,29 @@ fn expand_words(shell: &mut Shell, argv: &[Word]) -> Result<Vec<String>> {
     Ok(out)
 }
 
+/// `~+`, `~-`, `~1`, `~-2` at the start of an unquoted word (`~` / `~user` are left to path resolution).
+fn expand_dir_tilde(shell: &Shell, text: &str) -> String {
+    let Some(rest) = text.strip_prefix('~') else {
+        return text.to_string();
+    };
+    let end = rest.find(['/', '\\']).unwrap_or(rest.len());
+    match shell.expand_dir_tilde(&rest[..end]) {
+        Some(dir) => format!("{}{}", dir.display(), &rest[end..]),
+        None => text.to_string(),
+    }
+}
+
 /// Expand a single word into one or more arguments (glob aware)
 fn expand_word_list(shell: &mut Shell, word: &Word) -> Result<Vec<String>> {
     let status = shell.last_status.to_string();
     let mut literal = String::new();
     let mut any_unquoted = false;
 
-    for part in &word.parts {
-        match part.quote {
-            QuoteMode::Single => {
-                literal.push_str(&part.text);
-            }
-            QuoteMode::Double | QuoteMode::None => {
-                any_unquoted = true;
-                let mut expanded = part.text.replace("${?}", &status).replace("$?", &status);
-                expanded = path::expand_env(&expanded);
-                literal.push_str(&expanded);
-            }
+    for (idx, part) in word.parts.iter().enumerate() {
+        if !matches!(part.quote, QuoteMode::Single) {
+            any_unquoted = true;
         }
+        literal.push_str(&expand_part(shell, idx, part, &status));
     }
 
     // If entirely single-quoted, no glob expansion
@@ -882,6 +1530,50 @@ fn expand_word_list(shell: &mut Shell, word: &Word) -> Result<Vec<String>> {
     }
 }
 
+/// Expand one part of a word: `~` forms (first unquoted part only), `$?` and variables.
+fn expand_part(shell: &Shell, idx: usize, part: &WordPart, status: &str) -> String {
+    match part.quote {
+        QuoteMode::Single => part.text.clone(),
+        QuoteMode::Double | QuoteMode::None => {
+            let text = if idx == 0 && matches!(part.quote, QuoteMode::None) {
+                expand_dir_tilde(shell, &part.text)
+            } else {
+                part.text.clone()
+            };
+            path::expand_vars(&text.replace("${?}", status).replace("$?", status), |name| shell.get_var(name))
+        }
+    }
+}
+
+/// Expand a `[[ ]]` operand: no globbing or word splitting, quoting kept for pattern operators.
+fn expand_cond_word(shell: &Shell, word: &Word) -> CondWord {
+    let status = shell.last_status.to_string();
+    let parts = word
+        .parts
+        .iter()
+        .enumerate()
+        .map(|(idx, part)| (expand_part(shell, idx, part, &status), !matches!(part.quote, QuoteMode::None)))
+        .collect();
+    CondWord { parts }
+}
+
+fn execute_conditional(shell: &mut Shell, words: &[Word]) -> Result<i32> {
+    let words: Vec<CondWord> = words.iter().map(|w| expand_cond_word(shell, w)).collect();
+    let result = cond::eval_double_bracket(&shell.cwd, &words, &|name| shell.get_var(name));
+    match result {
+        Ok((result, rematch)) => {
+            if let Some(groups) = rematch {
+                shell.set_array("BASH_REMATCH", groups)?;
+            }
+            Ok(if result { 0 } else { 1 })
+        }
+        Err(e) => {
+            eprintln!("titanbash: [[: {}", e);
+            Ok(2)
+        }
+    }
+}
+
 fn expand_word_first(shell: &mut Shell, word: &Word) -> Result<String> {
     let list = expand_word_list(shell, word)?;
     Ok(list.into_iter().next().unwrap_or_default())
@@ -1354,10 +2046,148 @@ mod tests {
         assert_eq!(expand_alias_argv(&aliases, &argv), vec!["x".to_string()]);
     }
 
+    #[test]
+    fn test_expand_dir_stack_tilde() {
+        let mut shell = Shell::new().unwrap();
+        let base = std::env::temp_dir().join("titanbash_tilde_test");
+        shell.cwd = base.join("here");
+        shell.dir_stack = vec![base.join("one"), base.join("two")];
+
+        let one = base.join("one").display().to_string();
+        assert_eq!(expand_dir_tilde(&shell, "~1/src"), format!("{}/src", one));
+        assert_eq!(expand_dir_tilde(&shell, "~-0"), base.join("two").display().to_string());
+        assert_eq!(expand_dir_tilde(&shell, "~+"), base.join("here").display().to_string());
+        assert_eq!(expand_dir_tilde(&shell, "~7"), "~7");
+        assert_eq!(expand_dir_tilde(&shell, "~/docs"), "~/docs");
+        assert_eq!(expand_dir_tilde(&shell, "~user"), "~user");
+    }
+
+    #[test]
+    fn test_execute_ast_captured_collects_sequences() {
+        let mut shell = Shell::new().unwrap();
+        let ast = parser::parse("echo one; printf 'two\\n' && [[ x == y ]] || echo three").unwrap();
+        let (code, output) = execute_ast_captured(&mut shell, &ast).unwrap();
+        assert_eq!(code, 0);
+        assert_eq!(String::from_utf8(output).unwrap(), "one\ntwo\nthree\n");
+    }
+
+    #[test]
+    fn test_command_prefix_skips_aliases() {
+        let mut aliases = HashMap::new();
+        aliases.insert("ls".to_string(), "ls --color".to_string());
+        let words = |line: &str| split_args(line).iter().map(|s| Word::from_str(s)).collect::<Vec<_>>();
+
+        assert_eq!(expand_command_words(&aliases, &words("command ls -l")), words("ls -l"));
+        assert_eq!(expand_command_words(&aliases, &words("ls -l")), words("ls --color -l"));
+        assert_eq!(expand_command_words(&aliases, &words("command -v ls")), words("command -v ls"));
+    }
+
     #[test]
     fn test_expand_argv_status() {
         let argv = vec!["echo".to_string(), "$?".to_string(), "${?}".to_string()];
         let expanded = expand_argv(42, &argv);
         assert_eq!(expanded, vec!["echo".to_string(), "42".to_string(), "42".to_string()]);
     }
+
+    #[test]
+    fn test_resolve_builtin_and_unknown() {
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(resolve_command("ls", &cwd), Resolution::Builtin);
+        assert_eq!(
+            resolve_external("definitely_not_a_command_xyz", &cwd),
+            Resolution::CmdFallback
+        );
+        assert!(matches!(
+            resolve_external("deploy.ps1", &cwd),
+            Resolution::PowerShell(_)
+        ));
+    }
+
+    #[test]
+    fn test_explain_does_not_execute() {
+        let mut shell = Shell::new().unwrap();
+        let target = std::env::temp_dir().join("titanbash_explain_should_not_exist.txt");
+        let _ = fs::remove_file(&target);
+        let line = format!("echo hi > {}", target.display());
+
+        let mut out = Vec::<u8>::new();
+        explain(&mut shell, &line, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        assert!(text.contains("Simple: echo hi"));
+        assert!(text.contains("argv: [\"echo\", \"hi\"]"));
+        assert!(text.contains("resolve: builtin"));
+        assert!(text.contains("redirect: > "));
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn test_strict_policy_disables_cmd_fallback() {
+        let cwd = std::env::current_dir().unwrap();
+        let policy = DispatchPolicy {
+            strict: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve_external_with_policy("definitely_not_a_command_xyz", &cwd, &policy),
+            Resolution::NotFound
+        );
+    }
+
+    #[test]
+    fn test_noclobber_requires_force_redirect() {
+        let mut shell = Shell::new().unwrap();
+        shell.cwd = std::env::temp_dir();
+        shell.options.noclobber = true;
+        let target = shell.cwd.join("titanbash_noclobber_test.txt");
+        fs::write(&target, "keep\n").unwrap();
+
+        let err = shell
+            .execute("echo lost > titanbash_noclobber_test.txt")
+            .unwrap_err();
+        assert!(err.to_string().contains("noclobber"));
+        assert!(err.to_string().contains("titanbash_noclobber_test.txt"));
+        assert_eq!(fs::read_to_string(&target).unwrap(), "keep\n");
+
+        shell
+            .execute("echo forced >| titanbash_noclobber_test.txt")
+            .unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap().trim(), "forced");
+
+        let _ = fs::remove_file(&target);
+    }
+
+    #[test]
+    fn test_dev_null_redirects_do_not_create_files() {
+        let mut shell = Shell::new().unwrap();
+        let dir = std::env::temp_dir().join("titanbash_devnull_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        shell.cwd = dir.clone();
+
+        shell.execute("echo hidden > /dev/null 2>&1").unwrap();
+        assert_eq!(shell.last_status, 0);
+        shell.execute("cat < /dev/null 2>/dev/null").unwrap();
+        assert_eq!(shell.last_status, 0);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        assert!(shell.execute("echo x > /dev/stdin").is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_map_device_args_skips_command_and_busybox() {
+        let argv: Vec<String> = ["/dev/null", "-o", "/dev/null", "/dev/stdout"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mapped = map_device_args(&Resolution::CmdFallback, &argv);
+        assert_eq!(mapped, vec!["/dev/null", "-o", "NUL", "CON"]);
+
+        let busybox = Resolution::Busybox {
+            busybox: PathBuf::from("busybox.exe"),
+            applet: "cat".to_string(),
+        };
+        assert_eq!(map_device_args(&busybox, &argv), argv);
+    }
 }
@@ -0,0 +1,381 @@
+//! `ff`: a gitignore-aware, `fd`-style file search.
+//!
+//! Hidden entries and anything matched by `.gitignore` / `.ignore` are skipped (and not descended
+//! into) unless `-H` / `-I` say otherwise, which is what keeps `node_modules` and `target` out of
+//! the walk. Each top-level subtree is searched on a worker thread via
+//! [`walk::for_each_ordered`], so matches come out in a stable order and stream as soon as each
+//! subtree is done. Links are reported but never followed.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Result};
+use glob::{MatchOptions, Pattern};
+use regex::{Regex, RegexBuilder};
+
+use super::walk::{self, Entry, IgnoreRules};
+
+/// How the name pattern is interpreted.
+pub enum NameFilter {
+    Regex(Regex),
+    Glob(Pattern, MatchOptions),
+}
+
+impl NameFilter {
+    /// Build a filter; unless `case_sensitive` is given, patterns with an uppercase letter are
+    /// case-sensitive and all others are not (smart case).
+    pub fn new(pattern: &str, glob: bool, case_sensitive: Option<bool>) -> Result<Self> {
+        let case_sensitive = case_sensitive.unwrap_or_else(|| pattern.chars().any(char::is_uppercase));
+        if glob {
+            let compiled = Pattern::new(pattern).map_err(|e| anyhow::anyhow!("invalid glob '{}': {}", pattern, e))?;
+            let options = MatchOptions {
+                case_sensitive,
+                require_literal_separator: false,
+                require_literal_leading_dot: false,
+            };
+            Ok(NameFilter::Glob(compiled, options))
+        } else {
+            let compiled = RegexBuilder::new(pattern)
+                .case_insensitive(!case_sensitive)
+                .build()
+                .map_err(|e| anyhow::anyhow!("invalid regex '{}': {}", pattern, e))?;
+            Ok(NameFilter::Regex(compiled))
+        }
+    }
+
+    fn is_match(&self, name: &str) -> bool {
+        match self {
+            NameFilter::Regex(re) => re.is_match(name),
+            NameFilter::Glob(pattern, options) => pattern.matches_with(name, *options),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EntryType {
+    File,
+    Dir,
+    Link,
+}
+
+impl EntryType {
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(match text {
+            "f" | "file" => EntryType::File,
+            "d" | "dir" | "directory" => EntryType::Dir,
+            "l" | "symlink" => EntryType::Link,
+            _ => bail!("unknown type '{}' (expected f, d or l)", text),
+        })
+    }
+
+    fn matches(self, entry: &Entry) -> bool {
+        match self {
+            EntryType::File => !entry.is_link() && entry.meta.is_file(),
+            EntryType::Dir => entry.is_dir(),
+            EntryType::Link => entry.is_link(),
+        }
+    }
+}
+
+/// `-S +10k` (at least), `-S -1M` (at most) or `-S 4096` (exactly); applies to files only.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SizeFilter {
+    AtLeast(u64),
+    AtMost(u64),
+    Exactly(u64),
+}
+
+impl SizeFilter {
+    /// Units as in `fd`: `b`, decimal `k m g t` and binary `ki mi gi ti`.
+    pub fn parse(text: &str) -> Result<Self> {
+        let (make, rest): (fn(u64) -> SizeFilter, &str) = match text.as_bytes().first() {
+            Some(b'+') => (SizeFilter::AtLeast, &text[1..]),
+            Some(b'-') => (SizeFilter::AtMost, &text[1..]),
+            _ => (SizeFilter::Exactly, text),
+        };
+        let split = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let (digits, unit) = rest.split_at(split);
+        let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+            "" | "b" => 1,
+            "k" | "kb" => 1000,
+            "ki" | "kib" => 1 << 10,
+            "m" | "mb" => 1000 * 1000,
+            "mi" | "mib" => 1 << 20,
+            "g" | "gb" => 1000 * 1000 * 1000,
+            "gi" | "gib" => 1 << 30,
+            "t" | "tb" => 1000 * 1000 * 1000 * 1000,
+            "ti" | "tib" => 1 << 40,
+            _ => bail!("invalid size '{}'", text),
+        };
+        let value: u64 = digits.parse().map_err(|_| anyhow::anyhow!("invalid size '{}'", text))?;
+        Ok(make(value.saturating_mul(multiplier)))
+    }
+
+    fn matches(self, size: u64) -> bool {
+        match self {
+            SizeFilter::AtLeast(limit) => size >= limit,
+            SizeFilter::AtMost(limit) => size <= limit,
+            SizeFilter::Exactly(limit) => size == limit,
+        }
+    }
+}
+
+/// A point in time for `--changed-within` / `--changed-before`: a duration back from now
+/// (`90s`, `10min`, `2h`, `3d`, `1w`) or a local date (`2024-01-31` or `2024-01-31 12:00:00`).
+pub fn parse_time_limit(text: &str, now: SystemTime) -> Result<SystemTime> {
+    let date_time = chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .or_else(|| chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0));
+    if let Some(naive) = date_time {
+        if let Some(local) = naive.and_local_timezone(chrono::Local).earliest() {
+            return Ok(local.into());
+        }
+    }
+
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (digits, unit) = text.split_at(split);
+    let seconds: u64 = match unit {
+        "s" | "sec" | "secs" => 1,
+        "m" | "min" | "mins" => 60,
+        "h" | "hour" | "hours" => 3600,
+        "d" | "day" | "days" => 86400,
+        "w" | "week" | "weeks" => 7 * 86400,
+        _ => bail!("invalid time '{}' (e.g. 30min, 2h, 3d, 1w or 2024-01-31)", text),
+    };
+    let amount: u64 = digits
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid time '{}' (e.g. 30min, 2h, 3d, 1w or 2024-01-31)", text))?;
+    Ok(now.checked_sub(Duration::from_secs(amount.saturating_mul(seconds))).unwrap_or(SystemTime::UNIX_EPOCH))
+}
+
+#[derive(Default)]
+pub struct FindOptions {
+    pub name: Option<NameFilter>,
+    /// Any of these types (all types when empty)
+    pub types: Vec<EntryType>,
+    /// Any of these extensions, without the dot (case-insensitive)
+    pub extensions: Vec<String>,
+    pub sizes: Vec<SizeFilter>,
+    pub changed_after: Option<SystemTime>,
+    pub changed_before: Option<SystemTime>,
+    /// Names to skip without descending (`-E`)
+    pub excludes: Vec<Pattern>,
+    pub hidden: bool,
+    pub no_ignore: bool,
+    pub max_depth: Option<usize>,
+}
+
+impl FindOptions {
+    fn is_match(&self, entry: &Entry) -> bool {
+        if self.name.as_ref().is_some_and(|name| !name.is_match(&entry.name)) {
+            return false;
+        }
+        if !self.types.is_empty() && !self.types.iter().any(|t| t.matches(entry)) {
+            return false;
+        }
+        if !self.extensions.is_empty() {
+            let ext = Path::new(&entry.name).extension().map(|e| e.to_string_lossy().to_lowercase());
+            if !ext.is_some_and(|ext| self.extensions.contains(&ext)) {
+                return false;
+            }
+        }
+        if !self.sizes.is_empty() {
+            let is_file = !entry.is_link() && entry.meta.is_file();
+            if !is_file || !self.sizes.iter().all(|s| s.matches(entry.meta.len())) {
+                return false;
+            }
+        }
+        if self.changed_after.is_some() || self.changed_before.is_some() {
+            let Ok(modified) = entry.meta.modified() else {
+                return false;
+            };
+            if self.changed_after.is_some_and(|t| modified < t) || self.changed_before.is_some_and(|t| modified > t) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Whether the walk should look at `entry` at all (hidden, ignored and excluded entries are
+    /// neither reported nor descended into).
+    fn is_visible(&self, entry: &Entry, rules: &IgnoreRules) -> bool {
+        if !self.hidden && entry.is_hidden() {
+            return false;
+        }
+        if self.excludes.iter().any(|p| p.matches(&entry.name)) {
+            return false;
+        }
+        self.no_ignore || !rules.is_ignored(&entry.path, entry.is_dir())
+    }
+}
+
+pub enum FindEvent {
+    Match { path: PathBuf, label: String },
+    Error(String),
+}
+
+/// Search below `root`, handing matches to `sink` in sorted, depth-first order. `label` is how
+/// `root` was written; children of `.` are labelled without the `./` prefix.
+pub fn search(
+    root: &Path,
+    label: &str,
+    opts: &FindOptions,
+    mut sink: impl FnMut(FindEvent) -> Result<()>,
+) -> Result<()> {
+    if !root.is_dir() {
+        bail!("'{}' is not a directory", label);
+    }
+    let rules = if opts.no_ignore { IgnoreRules::default() } else { IgnoreRules::for_dir(root) };
+    let entries: Vec<Entry> = walk::read_entries(root)?
+        .into_iter()
+        .filter(|entry| opts.is_visible(entry, &rules))
+        .collect();
+
+    walk::for_each_ordered(
+        &entries,
+        |entry| {
+            let mut events = Vec::new();
+            visit(entry, join_label(label, &entry.name), 1, &rules, opts, &mut events);
+            events
+        },
+        |events| {
+            for event in events {
+                sink(event)?;
+            }
+            Ok(())
+        },
+    )
+}
+
+fn join_label(parent: &str, name: &str) -> String {
+    if parent == "." {
+        name.to_string()
+    } else {
+        walk::child_label(parent, name)
+    }
+}
+
+fn visit(entry: &Entry, label: String, depth: usize, rules: &IgnoreRules, opts: &FindOptions, events: &mut Vec<FindEvent>) {
+    if opts.is_match(entry) {
+        events.push(FindEvent::Match { path: entry.path.clone(), label: label.clone() });
+    }
+    if !entry.is_dir() || opts.max_depth.is_some_and(|max| depth >= max) {
+        return;
+    }
+
+    let rules = if opts.no_ignore { rules.clone() } else { rules.enter(&entry.path) };
+    let children = match walk::read_entries(&entry.path) {
+        Ok(children) => children,
+        Err(e) => {
+            events.push(FindEvent::Error(format!("cannot read directory '{}': {}", label, e)));
+            return;
+        }
+    };
+    for child in children {
+        if crate::interrupt::seen() {
+            return;
+        }
+        if opts.is_visible(&child, &rules) {
+            visit(&child, walk::child_label(&label, &child.name), depth + 1, &rules, opts, events);
+        }
+    }
+}
+
+/// Substitute `fd` placeholders in an `-x` argument: `{}` path, `{/}` basename, `{//}` parent,
+/// `{.}` path without extension, `{/.}` basename without extension.
+pub fn expand_placeholders(template: &str, label: &str) -> String {
+    let path = Path::new(label);
+    let basename = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let parent = match path.parent().map(|p| p.to_string_lossy().into_owned()) {
+        Some(parent) if !parent.is_empty() => parent,
+        _ => ".".to_string(),
+    };
+    let stem = path.file_stem().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let without_ext = match path.extension() {
+        Some(ext) => label[..label.len() - ext.len() - 1].to_string(),
+        None => label.to_string(),
+    };
+    template
+        .replace("{//}", &parent)
+        .replace("{/.}", &stem)
+        .replace("{/}", &basename)
+        .replace("{.}", &without_ext)
+        .replace("{}", label)
+}
+
+/// Whether any argument of an `-x` template uses a placeholder (otherwise the path is appended).
+pub fn has_placeholder(template: &[String]) -> bool {
+    ["{}", "{/}", "{//}", "{.}", "{/.}"]
+        .iter()
+        .any(|p| template.iter().any(|arg| arg.contains(p)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn collect(root: &Path, opts: &FindOptions) -> Vec<String> {
+        let mut found = Vec::new();
+        search(root, ".", opts, |event| {
+            if let FindEvent::Match { label, .. } = event {
+                found.push(label);
+            }
+            Ok(())
+        })
+        .unwrap();
+        found
+    }
+
+    #[test]
+    fn test_search_respects_ignores_hidden_and_filters() {
+        let root = std::env::temp_dir().join("titanbash_find_test");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src/deep")).unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::create_dir_all(root.join(".cache")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("src/deep/Mod.RS"), vec![b'x'; 3000]).unwrap();
+        fs::write(root.join("target/debug/app.rs"), "").unwrap();
+        fs::write(root.join(".cache/x.rs"), "").unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+
+        let rs = |glob| FindOptions { name: Some(NameFilter::new(if glob { "*.rs" } else { r"\.rs$" }, glob, None).unwrap()), ..Default::default() };
+        assert_eq!(collect(&root, &rs(true)), ["src/deep/Mod.RS", "src/main.rs"]);
+        assert_eq!(collect(&root, &rs(false)), ["src/deep/Mod.RS", "src/main.rs"]);
+        assert_eq!(
+            collect(&root, &FindOptions { hidden: true, no_ignore: true, ..rs(true) }),
+            [".cache/x.rs", "src/deep/Mod.RS", "src/main.rs", "target/debug/app.rs"]
+        );
+
+        let dirs = FindOptions { types: vec![EntryType::Dir], max_depth: Some(1), ..Default::default() };
+        assert_eq!(collect(&root, &dirs), ["src"]);
+        let big = FindOptions { sizes: vec![SizeFilter::parse("+2k").unwrap()], ..Default::default() };
+        assert_eq!(collect(&root, &big), ["src/deep/Mod.RS"]);
+        let by_ext = FindOptions { extensions: vec!["rs".into()], excludes: vec![Pattern::new("deep").unwrap()], ..Default::default() };
+        assert_eq!(collect(&root, &by_ext), ["src/main.rs"]);
+        let future = parse_time_limit("2999-01-01", SystemTime::now()).unwrap();
+        assert!(collect(&root, &FindOptions { changed_after: Some(future), ..Default::default() }).is_empty());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_filter_parsing_and_placeholders() {
+        assert_eq!(SizeFilter::parse("+10k").unwrap(), SizeFilter::AtLeast(10_000));
+        assert_eq!(SizeFilter::parse("-1mi").unwrap(), SizeFilter::AtMost(1 << 20));
+        assert_eq!(SizeFilter::parse("512").unwrap(), SizeFilter::Exactly(512));
+        assert!(SizeFilter::parse("10q").is_err());
+
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        assert_eq!(parse_time_limit("2h", now).unwrap(), now - Duration::from_secs(7200));
+        assert_eq!(parse_time_limit("1w", now).unwrap(), now - Duration::from_secs(604_800));
+        assert!(parse_time_limit("soon", now).is_err());
+
+        assert!(NameFilter::new("readme", false, None).unwrap().is_match("README.md"));
+        assert!(!NameFilter::new("Readme", false, None).unwrap().is_match("README.md"));
+
+        assert_eq!(expand_placeholders("{}|{/}|{//}|{.}|{/.}", "src/lib.rs"), "src/lib.rs|lib.rs|src|src/lib|lib");
+        assert_eq!(expand_placeholders("{//}", "top.txt"), ".");
+        assert!(has_placeholder(&["x{/.}y".to_string()]) && !has_placeholder(&["{x}".to_string()]));
+    }
+}
@@ -0,0 +1,302 @@
+//! Frecency database behind `z` / `zi`.
+//!
+//! Every directory change (`cd`, `pushd`, `popd`, `z`) bumps the directory's rank in
+//! `~/.titanbash_dirs` (override with `TITANBASH_Z_DATA`), one entry per line:
+//!
+//! ```text
+//! <rank>\t<last access, unix seconds>\t<path>
+//! ```
+//!
+//! Scores follow zoxide: the rank is weighted by how recently the directory was visited,
+//! and ranks are aged (scaled down, tiny entries dropped) once their sum exceeds
+//! [`MAX_TOTAL_RANK`]. Directories that no longer exist are pruned when queried.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+
+pub const VAR_Z_DATA: &str = "TITANBASH_Z_DATA";
+
+/// Sum of ranks above which every rank is aged.
+const MAX_TOTAL_RANK: f64 = 10_000.0;
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirEntry {
+    pub path: PathBuf,
+    pub rank: f64,
+    pub last_access: u64,
+}
+
+impl DirEntry {
+    /// Rank weighted by recency.
+    pub fn score(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last_access);
+        let weight = if age < HOUR {
+            4.0
+        } else if age < DAY {
+            2.0
+        } else if age < WEEK {
+            0.5
+        } else {
+            0.25
+        };
+        self.rank * weight
+    }
+}
+
+pub struct DirDb {
+    file: PathBuf,
+    pub entries: Vec<DirEntry>,
+}
+
+impl DirDb {
+    /// The database configured by the environment.
+    pub fn open() -> Result<Self> {
+        let file = match std::env::var_os(VAR_Z_DATA) {
+            Some(file) if !file.is_empty() => PathBuf::from(file),
+            _ => dirs::home_dir()
+                .ok_or_else(|| anyhow::anyhow!("z: cannot determine home directory"))?
+                .join(".titanbash_dirs"),
+        };
+        Self::load(file)
+    }
+
+    /// Load `file` (a missing file is an empty database; unreadable lines are skipped).
+    pub fn load(file: PathBuf) -> Result<Self> {
+        let content = match fs::read_to_string(&file) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e).with_context(|| format!("z: cannot read {}", file.display())),
+        };
+        let entries = content
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                let rank = fields.next()?.parse().ok()?;
+                let last_access = fields.next()?.parse().ok()?;
+                let path = PathBuf::from(fields.next()?);
+                Some(DirEntry { path, rank, last_access })
+            })
+            .collect();
+        Ok(Self { file, entries })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let mut content = String::new();
+        for entry in &self.entries {
+            content.push_str(&format!("{}\t{}\t{}\n", entry.rank, entry.last_access, entry.path.display()));
+        }
+        // Write-then-rename so two shells never leave a half-written file behind.
+        let tmp = self.file.with_extension("tmp");
+        fs::write(&tmp, content).with_context(|| format!("z: cannot write {}", tmp.display()))?;
+        fs::rename(&tmp, &self.file).with_context(|| format!("z: cannot write {}", self.file.display()))?;
+        Ok(())
+    }
+
+    /// Record a visit to `dir`.
+    pub fn add(&mut self, dir: &Path, now: u64) {
+        match self.entries.iter_mut().find(|e| same_path(&e.path, dir)) {
+            Some(entry) => {
+                entry.rank += 1.0;
+                entry.last_access = now;
+            }
+            None => self.entries.push(DirEntry {
+                path: dir.to_path_buf(),
+                rank: 1.0,
+                last_access: now,
+            }),
+        }
+        self.age();
+    }
+
+    /// Merge `rank` into `dir` (used by imports); keeps the newer access time.
+    pub fn merge(&mut self, dir: &Path, rank: f64, last_access: u64) {
+        match self.entries.iter_mut().find(|e| same_path(&e.path, dir)) {
+            Some(entry) => {
+                entry.rank += rank;
+                entry.last_access = entry.last_access.max(last_access);
+            }
+            None => self.entries.push(DirEntry {
+                path: dir.to_path_buf(),
+                rank,
+                last_access,
+            }),
+        }
+    }
+
+    fn age(&mut self) {
+        let total: f64 = self.entries.iter().map(|e| e.rank).sum();
+        if total > MAX_TOTAL_RANK {
+            let factor = 0.9 * MAX_TOTAL_RANK / total;
+            for entry in &mut self.entries {
+                entry.rank *= factor;
+            }
+            self.entries.retain(|e| e.rank >= 1.0);
+        }
+    }
+
+    /// Drop directories that no longer exist. Returns how many were removed.
+    pub fn prune(&mut self) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|e| e.path.is_dir());
+        before - self.entries.len()
+    }
+
+    /// Entries matching `keywords`, best score first.
+    pub fn query(&self, keywords: &[&str], now: u64) -> Vec<&DirEntry> {
+        let mut matches: Vec<&DirEntry> = self
+            .entries
+            .iter()
+            .filter(|e| matches_keywords(&e.path, keywords))
+            .collect();
+        matches.sort_by(|a, b| b.score(now).total_cmp(&a.score(now)));
+        matches
+    }
+
+    /// Import a z.sh / z.lua database (`path|rank|time` lines). Returns the number of entries.
+    pub fn import_z_file(&mut self, file: &Path) -> Result<usize> {
+        let content = fs::read_to_string(file).with_context(|| format!("z: cannot read {}", file.display()))?;
+        let mut count = 0;
+        for line in content.lines() {
+            let mut fields = line.rsplitn(3, '|');
+            let (Some(time), Some(rank), Some(path)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            let (Ok(time), Ok(rank)) = (time.trim().parse::<u64>(), rank.trim().parse::<f64>()) else {
+                continue;
+            };
+            self.merge(Path::new(path), rank, time);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Import the zoxide database through `zoxide query --list --score`.
+    pub fn import_zoxide(&mut self, now: u64) -> Result<usize> {
+        let output = Command::new("zoxide")
+            .args(["query", "--list", "--score"])
+            .output()
+            .context("z: cannot run zoxide")?;
+        if !output.status.success() {
+            anyhow::bail!("z: zoxide: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        let mut count = 0;
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let Some((score, path)) = line.trim_start().split_once(' ') else {
+                continue;
+            };
+            let Ok(score) = score.parse::<f64>() else {
+                continue;
+            };
+            self.merge(Path::new(path.trim_start()), score, now);
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// Keywords must appear in order (case-insensitive); the last one in the last path component.
+pub fn matches_keywords(path: &Path, keywords: &[&str]) -> bool {
+    let haystack = path.to_string_lossy().to_lowercase();
+    let mut pos = 0;
+    for keyword in keywords {
+        let keyword = keyword.to_lowercase();
+        match haystack[pos..].find(&keyword) {
+            Some(found) => pos += found + keyword.len(),
+            None => return false,
+        }
+    }
+    match keywords.last() {
+        Some(last) => {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            name.contains(&last.to_lowercase())
+        }
+        None => true,
+    }
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    if cfg!(windows) {
+        a.to_string_lossy().eq_ignore_ascii_case(&b.to_string_lossy())
+    } else {
+        a == b
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Record a directory change (errors are ignored: `cd` must not fail because of `z`).
+pub fn record(dir: &Path) {
+    if dirs::home_dir().is_some_and(|home| same_path(&home, dir)) {
+        return;
+    }
+    if let Ok(mut db) = DirDb::open() {
+        db.add(dir, now());
+        let _ = db.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_db(name: &str) -> DirDb {
+        let file = std::env::temp_dir().join(name);
+        let _ = fs::remove_file(&file);
+        DirDb::load(file).unwrap()
+    }
+
+    #[test]
+    fn test_keyword_matching() {
+        let path = Path::new("/home/me/src/titan-bash");
+        assert!(matches_keywords(path, &["titan"]));
+        assert!(matches_keywords(path, &["src", "bash"]));
+        assert!(matches_keywords(path, &["SRC", "Titan"]));
+        assert!(!matches_keywords(path, &["bash", "src"]));
+        assert!(!matches_keywords(path, &["src"]));
+    }
+
+    #[test]
+    fn test_rank_order_and_persistence() {
+        let mut db = temp_db("titanbash_z_test_db");
+        let now = 1_700_000_000;
+        db.add(Path::new("/w/old-project"), now - 2 * WEEK);
+        db.add(Path::new("/w/old-project"), now - 2 * WEEK);
+        db.add(Path::new("/w/new-project"), now);
+
+        // Two visits two weeks ago (0.5) lose to one visit just now (4.0).
+        let best: Vec<_> = db.query(&["project"], now).iter().map(|e| e.path.clone()).collect();
+        assert_eq!(best, vec![PathBuf::from("/w/new-project"), PathBuf::from("/w/old-project")]);
+
+        db.save().unwrap();
+        let reloaded = DirDb::load(db.file.clone()).unwrap();
+        assert_eq!(reloaded.entries, db.entries);
+        assert_eq!(db.prune(), 2);
+        let _ = fs::remove_file(&db.file);
+    }
+
+    #[test]
+    fn test_import_z_file() {
+        let mut db = temp_db("titanbash_z_import_db");
+        let source = std::env::temp_dir().join("titanbash_z_import_source");
+        fs::write(&source, "/a/one|12|1700000000\nbroken line\n/a/two|3.5|1700000100\n").unwrap();
+        assert_eq!(db.import_z_file(&source).unwrap(), 2);
+        assert_eq!(db.entries[1].rank, 3.5);
+        let _ = fs::remove_file(&source);
+    }
+}
@@ -0,0 +1,184 @@
+//! Filesystem helpers shared by the builtins and the modules behind them: Windows file
+//! attributes (approximated from metadata on other platforms), recursive copies that keep
+//! symlinks, and moves that fall back to copy + verify + delete across volumes.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+/// Windows file attribute bits (`GetFileAttributesW`)
+pub const ATTR_READONLY: u32 = 0x1;
+pub const ATTR_HIDDEN: u32 = 0x2;
+pub const ATTR_SYSTEM: u32 = 0x4;
+pub const ATTR_DIRECTORY: u32 = 0x10;
+pub const ATTR_ARCHIVE: u32 = 0x20;
+pub const ATTR_REPARSE_POINT: u32 = 0x400;
+
+#[cfg(windows)]
+pub fn file_attributes(meta: &fs::Metadata) -> u32 {
+    use std::os::windows::fs::MetadataExt;
+    meta.file_attributes()
+}
+
+#[cfg(not(windows))]
+pub fn file_attributes(meta: &fs::Metadata) -> u32 {
+    let mut attrs = 0;
+    if meta.permissions().readonly() {
+        attrs |= ATTR_READONLY;
+    }
+    if meta.is_dir() {
+        attrs |= ATTR_DIRECTORY;
+    }
+    if meta.file_type().is_symlink() {
+        attrs |= ATTR_REPARSE_POINT;
+    }
+    attrs
+}
+
+/// Copy access/modification times from `src` to `dst` (best effort for directories).
+fn preserve_times(src_meta: &fs::Metadata, dst: &Path) -> io::Result<()> {
+    let mut times = fs::FileTimes::new();
+    if let Ok(modified) = src_meta.modified() {
+        times = times.set_modified(modified);
+    }
+    if let Ok(accessed) = src_meta.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    let file = if src_meta.is_dir() {
+        File::open(dst)?
+    } else {
+        fs::OpenOptions::new().write(true).open(dst)?
+    };
+    file.set_times(times)
+}
+
+/// Recreate the symlink `src` at `dst` (pointing at the same target).
+fn copy_symlink(src: &Path, dst: &Path) -> io::Result<()> {
+    let target = fs::read_link(src)?;
+    #[cfg(windows)]
+    {
+        if src.is_dir() {
+            std::os::windows::fs::symlink_dir(&target, dst)
+        } else {
+            std::os::windows::fs::symlink_file(&target, dst)
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        std::os::unix::fs::symlink(&target, dst)
+    }
+}
+
+/// Copy a file, symlink or (recursively) a directory from `src` to `dst`.
+pub fn copy_entry(src: &Path, dst: &Path, preserve: bool) -> Result<()> {
+    let meta = fs::symlink_metadata(src)
+        .with_context(|| format!("cannot stat '{}'", src.display()))?;
+
+    if meta.file_type().is_symlink() {
+        if fs::symlink_metadata(dst).is_ok() {
+            fs::remove_file(dst)?;
+        }
+        // Creating symlinks needs Developer Mode or elevation on Windows; copy the target instead.
+        if copy_symlink(src, dst).is_ok() {
+            return Ok(());
+        }
+        let followed = fs::metadata(src)
+            .with_context(|| format!("dangling symlink '{}'", src.display()))?;
+        if followed.is_dir() {
+            return copy_dir_all(src, dst, preserve);
+        }
+        fs::copy(src, dst)
+            .with_context(|| format!("cannot copy '{}' to '{}'", src.display(), dst.display()))?;
+    } else if meta.is_dir() {
+        return copy_dir_all(src, dst, preserve);
+    } else {
+        fs::copy(src, dst)
+            .with_context(|| format!("cannot copy '{}' to '{}'", src.display(), dst.display()))?;
+    }
+
+    if preserve {
+        preserve_times(&meta, dst)
+            .with_context(|| format!("cannot preserve times of '{}'", dst.display()))?;
+    }
+    Ok(())
+}
+
+/// Helper: copy directory recursively (symlinks are recreated, not followed)
+fn copy_dir_all(src: &Path, dst: &Path, preserve: bool) -> Result<()> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        copy_entry(&entry.path(), &dst.join(entry.file_name()), preserve)?;
+    }
+    if preserve {
+        // Directory times change as entries are written, so set them last.
+        let _ = preserve_times(&fs::metadata(src)?, dst);
+    }
+    Ok(())
+}
+
+/// (file count, total bytes) of a file or directory tree, used to verify cross-volume moves.
+pub fn tree_footprint(root: &Path) -> io::Result<(u64, u64)> {
+    let meta = fs::symlink_metadata(root)?;
+    if !meta.is_dir() {
+        return Ok((1, meta.len()));
+    }
+    let mut total = (0, 0);
+    for entry in fs::read_dir(root)? {
+        let (files, bytes) = tree_footprint(&entry?.path())?;
+        total.0 += files;
+        total.1 += bytes;
+    }
+    Ok(total)
+}
+
+/// Move `src` to `dst`, falling back to copy + verify + delete across volumes. A failed or
+/// incomplete copy is removed again, unless `dst` existed beforehand.
+pub fn move_entry(src: &Path, dst: &Path) -> Result<()> {
+    match fs::rename(src, dst) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            let preexisting = fs::symlink_metadata(dst).is_ok();
+            if let Err(e) = copy_and_verify(src, dst) {
+                if !preexisting {
+                    let _ = remove_entry(dst);
+                }
+                return Err(e);
+            }
+            remove_entry(src)
+                .with_context(|| format!("copied to '{}' but cannot remove '{}'", dst.display(), src.display()))
+        }
+        Err(e) => Err(e).with_context(|| {
+            format!("cannot move '{}' to '{}'", src.display(), dst.display())
+        }),
+    }
+}
+
+/// The copy half of a cross-volume [`move_entry`]: fails unless `dst` ends up matching `src`.
+fn copy_and_verify(src: &Path, dst: &Path) -> Result<()> {
+    copy_entry(src, dst, true)?;
+    let expected = tree_footprint(src)?;
+    let copied = tree_footprint(dst)?;
+    if expected != copied {
+        anyhow::bail!(
+            "copy of '{}' to '{}' is incomplete ({} files/{} bytes vs {} files/{} bytes); source kept",
+            src.display(),
+            dst.display(),
+            copied.0,
+            copied.1,
+            expected.0,
+            expected.1
+        );
+    }
+    Ok(())
+}
+
+fn remove_entry(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
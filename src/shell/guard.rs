@@ -0,0 +1,165 @@
+//! Guard rails for destructive operations on critical paths.
+//!
+//! Two kinds of protected paths:
+//! - Anchors (drive roots, `$HOME`, the current directory and its parents): the path itself
+//!   and anything that contains it. `rm -r ~` or `mv` of the repo you are standing in trips
+//!   the guard, `rm ~/notes.txt` does not.
+//! - Trees (`%WINDIR%`, the titanbash install dir, `TITANBASH_PROTECTED_PATHS`): additionally
+//!   everything inside them, including redirect truncation (`> C:\Windows\...`).
+//!
+//! A tripped guard needs `--no-preserve-root` (rm/mv) or an interactive confirmation; the
+//! shell refuses outright when it is not interactive.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, Result};
+
+use super::Shell;
+
+pub const VAR_PROTECTED_PATHS: &str = "TITANBASH_PROTECTED_PATHS";
+
+/// Why `target` must not be removed/moved, if it is protected.
+pub fn check_remove(shell: &Shell, target: &Path) -> Option<String> {
+    let target = comparable(target);
+    if target.parent().is_none() || is_drive_root(&target) {
+        return Some("filesystem root".to_string());
+    }
+
+    let mut anchors: Vec<(PathBuf, &str)> = Vec::new();
+    if let Some(home) = dirs::home_dir() {
+        anchors.push((home, "home directory"));
+    }
+    anchors.push((shell.cwd.clone(), "contains the current directory"));
+
+    for (anchor, reason) in anchors {
+        if comparable(&anchor).starts_with(&target) {
+            return Some(reason.to_string());
+        }
+    }
+
+    protected_trees()
+        .into_iter()
+        .find(|(tree, _)| {
+            let tree = comparable(tree);
+            tree.starts_with(&target) || target.starts_with(&tree)
+        })
+        .map(|(tree, reason)| format!("{} {}", reason, tree.display()))
+}
+
+/// Why truncating `target` is dangerous (inside a protected tree), if it is.
+pub fn check_write(target: &Path) -> Option<String> {
+    let target = comparable(target);
+    protected_trees()
+        .into_iter()
+        .find(|(tree, _)| target.starts_with(comparable(tree)))
+        .map(|(tree, reason)| format!("inside {} {}", reason, tree.display()))
+}
+
+/// Ask before a guarded operation. Errors if declined or if the shell is not interactive.
+pub fn confirm(shell: &Shell, cmd: &str, action: &str, target: &Path, reason: &str) -> Result<()> {
+    if !shell.interactive || !io::stdin().is_terminal() {
+        bail!(
+            "{}: refusing to {} '{}' ({}); use --no-preserve-root to override",
+            cmd,
+            action,
+            target.display(),
+            reason
+        );
+    }
+
+    let mut err = io::stderr();
+    write!(
+        err,
+        "{}: '{}' is protected ({}). Really {}? [y/N] ",
+        cmd,
+        target.display(),
+        reason,
+        action
+    )?;
+    err.flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    if !answer.trim().eq_ignore_ascii_case("y") && !answer.trim().eq_ignore_ascii_case("yes") {
+        bail!("{}: not confirmed, '{}' left alone", cmd, target.display());
+    }
+    Ok(())
+}
+
+/// Built-in trees plus user additions from `TITANBASH_PROTECTED_PATHS` (`;`-separated).
+fn protected_trees() -> Vec<(PathBuf, &'static str)> {
+    let mut trees = Vec::new();
+    for var in ["WINDIR", "SystemRoot"] {
+        if let Some(dir) = std::env::var_os(var).filter(|v| !v.is_empty()) {
+            trees.push((PathBuf::from(dir), "Windows directory"));
+        }
+    }
+    if let Some(dir) = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)) {
+        trees.push((dir, "titanbash install directory"));
+    }
+    if let Ok(extra) = std::env::var(VAR_PROTECTED_PATHS) {
+        for item in extra.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+            trees.push((super::path::normalize(item), "protected path"));
+        }
+    }
+    trees
+}
+
+/// Canonical, case-folded (on Windows) form for prefix comparison.
+fn comparable(path: &Path) -> PathBuf {
+    let canonical = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if cfg!(windows) {
+        PathBuf::from(canonical.to_string_lossy().to_lowercase())
+    } else {
+        canonical
+    }
+}
+
+/// `C:\` (or `\\?\C:\`): a prefix and a root, nothing else.
+fn is_drive_root(path: &Path) -> bool {
+    let mut components = path.components();
+    matches!(
+        (components.next(), components.next(), components.next()),
+        (Some(Component::Prefix(_)), Some(Component::RootDir), None)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_remove_anchors() {
+        let mut shell = Shell::new().unwrap();
+        let base = std::env::temp_dir().join("titanbash_guard_test");
+        let cwd = base.join("repo").join("src");
+        std::fs::create_dir_all(&cwd).unwrap();
+        shell.cwd = cwd.clone();
+
+        assert_eq!(check_remove(&shell, Path::new("/")).as_deref(), Some("filesystem root"));
+        assert!(check_remove(&shell, &base.join("repo")).is_some());
+        assert!(check_remove(&shell, &cwd).is_some());
+        assert!(check_remove(&shell, &base.join("other")).is_none());
+        if let Some(home) = dirs::home_dir() {
+            assert!(check_remove(&shell, &home).is_some());
+            assert!(check_remove(&shell, &home.join("titanbash_guard_not_a_real_file")).is_none());
+        }
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_install_dir_is_a_protected_tree() {
+        let exe_dir = std::env::current_exe().unwrap().parent().unwrap().to_path_buf();
+        assert!(check_write(&exe_dir.join("some_file.txt")).is_some());
+        assert!(check_write(&std::env::temp_dir().join("x.txt")).is_none());
+    }
+
+    #[test]
+    fn test_non_interactive_refuses() {
+        let shell = Shell::new().unwrap();
+        let err = confirm(&shell, "rm", "remove", Path::new("/"), "filesystem root").unwrap_err();
+        assert!(err.to_string().contains("--no-preserve-root"));
+    }
+}
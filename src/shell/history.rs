@@ -0,0 +1,287 @@
+//! Bash-style history expansion for interactive input.
+//!
+//! Runs on the typed line before parsing (only in the REPL, only while `histexpand` is on):
+//! - `!!` last command, `!n` entry n (as numbered by `history`), `!-n` n-th previous
+//! - `!prefix` most recent command starting with `prefix`
+//! - `!$` last word, `!^` first argument, `!*` all arguments of the last command
+//! - word designators on any event: `!!:2`, `!git:$`, `!-2:*`
+//! - `^old^new` re-runs the last command with the first `old` replaced
+//!
+//! Nothing is expanded inside single quotes; `\!` is a literal `!`.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+
+/// Characters that end a `!prefix` event.
+const EVENT_TERMINATORS: &[char] = &[';', '|', '&', '<', '>', '(', ')', '\'', '"', ':'];
+
+/// Expand history references in `line` against `entries` (oldest first).
+///
+/// Returns `None` when the line has nothing to expand.
+pub fn expand(line: &str, entries: &[String]) -> Result<Option<String>> {
+    if let Some(rest) = line.strip_prefix('^') {
+        return quick_substitution(rest, entries).map(Some);
+    }
+    if !line.contains('!') {
+        return Ok(None);
+    }
+
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = String::with_capacity(line.len());
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut i = 0usize;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\'' if !in_double => in_single = !in_single,
+            '"' if !in_single => in_double = !in_double,
+            '\\' if !in_single && chars.get(i + 1) == Some(&'!') => {
+                out.push('!');
+                i += 2;
+                continue;
+            }
+            '!' if !in_single => {
+                if let Some((text, next)) = expand_event(&chars, i + 1, entries)? {
+                    out.push_str(&text);
+                    i = next;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        out.push(c);
+        i += 1;
+    }
+
+    Ok((out != line).then_some(out))
+}
+
+/// Expand the event starting right after a `!` at `start`.
+///
+/// Returns the replacement text and the index after the reference, or `None` when the `!`
+/// is literal (`!` at the end, before a blank, `=`, `(` or a closing quote).
+fn expand_event(chars: &[char], start: usize, entries: &[String]) -> Result<Option<(String, usize)>> {
+    let Some(&first) = chars.get(start) else {
+        return Ok(None);
+    };
+    if first.is_whitespace() || matches!(first, '=' | '(' | '"') {
+        return Ok(None);
+    }
+
+    let last = || {
+        entries
+            .last()
+            .ok_or_else(|| anyhow::anyhow!("!{}: event not found", first))
+    };
+
+    // Shorthand word designators on the last command.
+    if matches!(first, '$' | '^' | '*') {
+        return Ok(Some((select_words(last()?, first)?, start + 1)));
+    }
+
+    let (event, mut pos) = if first == '!' {
+        (last()?.clone(), start + 1)
+    } else if first.is_ascii_digit() || (first == '-' && chars.get(start + 1).is_some_and(|c| c.is_ascii_digit())) {
+        let digits_start = if first == '-' { start + 1 } else { start };
+        let end = (digits_start..chars.len())
+            .find(|&j| !chars[j].is_ascii_digit())
+            .unwrap_or(chars.len());
+        let spec: String = chars[start..end].iter().collect();
+        let n: usize = chars[digits_start..end].iter().collect::<String>().parse().unwrap_or(0);
+        let index = if first == '-' {
+            entries.len().checked_sub(n)
+        } else {
+            n.checked_sub(1)
+        };
+        let Some(entry) = index.and_then(|idx| entries.get(idx)).filter(|_| n > 0) else {
+            bail!("!{}: event not found", spec);
+        };
+        (entry.clone(), end)
+    } else {
+        let end = (start..chars.len())
+            .find(|&j| chars[j].is_whitespace() || EVENT_TERMINATORS.contains(&chars[j]))
+            .unwrap_or(chars.len());
+        if end == start {
+            return Ok(None);
+        }
+        let prefix: String = chars[start..end].iter().collect();
+        let Some(entry) = entries.iter().rev().find(|e| e.starts_with(&prefix)) else {
+            bail!("!{}: event not found", prefix);
+        };
+        (entry.clone(), end)
+    };
+
+    // Optional `:designator`
+    if chars.get(pos) == Some(&':') {
+        if let Some(&designator) = chars.get(pos + 1) {
+            if matches!(designator, '$' | '^' | '*') {
+                return Ok(Some((select_words(&event, designator)?, pos + 2)));
+            }
+            if designator.is_ascii_digit() {
+                let end = (pos + 1..chars.len())
+                    .find(|&j| !chars[j].is_ascii_digit())
+                    .unwrap_or(chars.len());
+                let n: usize = chars[pos + 1..end].iter().collect::<String>().parse().unwrap_or(0);
+                let words = split_words(&event);
+                let Some(word) = words.get(n) else {
+                    bail!(":{}: bad word specifier", n);
+                };
+                pos = end;
+                return Ok(Some((word.clone(), pos)));
+            }
+        }
+    }
+
+    Ok(Some((event, pos)))
+}
+
+fn select_words(event: &str, designator: char) -> Result<String> {
+    let words = split_words(event);
+    match designator {
+        '$' => words.last().cloned().ok_or_else(|| anyhow::anyhow!(":$: bad word specifier")),
+        '^' => words.get(1).cloned().ok_or_else(|| anyhow::anyhow!(":^: bad word specifier")),
+        _ => Ok(words.get(1..).unwrap_or_default().join(" ")),
+    }
+}
+
+/// Split on blanks outside quotes, keeping the quotes (the result is re-parsed later).
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c.is_whitespace() => {
+                if !current.is_empty() {
+                    words.push(std::mem::take(&mut current));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// `^old^new[^suffix]`
+fn quick_substitution(spec: &str, entries: &[String]) -> Result<String> {
+    let mut parts = spec.splitn(3, '^');
+    let old = parts.next().unwrap_or_default();
+    let new = parts.next().unwrap_or_default();
+    let suffix = parts.next().unwrap_or_default();
+
+    let Some(last) = entries.last() else {
+        bail!("^{}: event not found", old);
+    };
+    if old.is_empty() || !last.contains(old) {
+        bail!(":s^{}^{}: substitution failed", old, new);
+    }
+    Ok(format!("{}{}", last.replacen(old, new, 1), suffix))
+}
+
+/// Escape a command for the one-line-per-entry history file.
+pub fn escape_line(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+pub fn unescape_line(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// All entries of a history file, in the order (and numbering) `history` shows them.
+pub fn load_file(path: &Path) -> io::Result<Vec<String>> {
+    Ok(fs::read_to_string(path)?.lines().map(unescape_line).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history() -> Vec<String> {
+        ["cd src", "git commit -m 'first fix'", "cargo build --release", "echo one two three"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    fn ex(line: &str) -> Option<String> {
+        expand(line, &history()).unwrap()
+    }
+
+    #[test]
+    fn test_event_designators() {
+        assert_eq!(ex("!!").as_deref(), Some("echo one two three"));
+        assert_eq!(ex("!1").as_deref(), Some("cd src"));
+        assert_eq!(ex("!-2 && ls").as_deref(), Some("cargo build --release && ls"));
+        assert_eq!(ex("!git").as_deref(), Some("git commit -m 'first fix'"));
+        assert_eq!(ex("vim !$").as_deref(), Some("vim three"));
+        assert_eq!(ex("printf !*").as_deref(), Some("printf one two three"));
+        assert_eq!(ex("echo !^").as_deref(), Some("echo one"));
+        assert_eq!(ex("echo !git:$").as_deref(), Some("echo 'first fix'"));
+        assert_eq!(ex("echo !-2:1").as_deref(), Some("echo build"));
+    }
+
+    #[test]
+    fn test_literal_bangs() {
+        assert_eq!(ex("echo hi!"), None);
+        assert_eq!(ex("echo '!!'"), None);
+        assert_eq!(ex("test 1 != 2"), None);
+        assert_eq!(ex("echo \"wow!\""), None);
+        assert_eq!(ex("echo \\!!").as_deref(), Some("echo !!"));
+        assert!(expand("!nope", &history()).is_err());
+        assert!(expand("!99", &history()).is_err());
+        assert!(expand("!!", &[]).is_err());
+    }
+
+    #[test]
+    fn test_quick_substitution() {
+        assert_eq!(ex("^one^1").as_deref(), Some("echo 1 two three"));
+        assert_eq!(ex("^three^3^ four").as_deref(), Some("echo one two 3 four"));
+        assert!(expand("^zzz^y", &history()).is_err());
+    }
+
+    #[test]
+    fn test_escape_roundtrip() {
+        let line = "echo a\\b\nnext";
+        assert_eq!(unescape_line(&escape_line(line)), line);
+        assert!(!escape_line(line).contains('\n'));
+    }
+}
@@ -624,6 +624,93 @@ impl CrosstermInput {
     }
 }
 
+/// How [`read_console_raw`] ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawRead {
+    /// Delimiter, Enter or the character limit reached
+    Done(String),
+    /// Ctrl+D / Ctrl+Z on an empty line (text typed so far is kept)
+    Eof(String),
+    /// `timeout` expired (text typed so far is kept)
+    TimedOut(String),
+    Interrupted,
+}
+
+/// Read one record from the console in raw mode (`read -s`, `-t`, `-n`, `-d`).
+///
+/// `delim` ends the record (Enter always does too); typed text is echoed to stderr unless
+/// `silent`; `max_chars` stops after that many characters.
+pub fn read_console_raw(
+    silent: bool,
+    timeout: Option<Duration>,
+    max_chars: Option<usize>,
+    delim: char,
+) -> io::Result<RawRead> {
+    let mut err = io::stderr();
+    terminal::enable_raw_mode()?;
+    let result = raw_read_loop(&mut err, silent, timeout, max_chars, delim);
+    let _ = terminal::disable_raw_mode();
+    if !silent && matches!(result, Ok(RawRead::Done(_))) {
+        let _ = write!(err, "\r\n");
+    }
+    let _ = err.flush();
+    result
+}
+
+fn raw_read_loop(
+    err: &mut io::Stderr,
+    silent: bool,
+    timeout: Option<Duration>,
+    max_chars: Option<usize>,
+    delim: char,
+) -> io::Result<RawRead> {
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let mut text = String::new();
+    loop {
+        if max_chars.is_some_and(|max| text.chars().count() >= max) {
+            return Ok(RawRead::Done(text));
+        }
+        let wait = match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(left) => left,
+                None => return Ok(RawRead::TimedOut(text)),
+            },
+            None => Duration::from_millis(100),
+        };
+        if !poll(wait)? {
+            continue;
+        }
+        let Event::Key(key) = read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('c') if ctrl => return Ok(RawRead::Interrupted),
+            KeyCode::Char('d') | KeyCode::Char('z') if ctrl && text.is_empty() => return Ok(RawRead::Eof(text)),
+            KeyCode::Enter => return Ok(RawRead::Done(text)),
+            KeyCode::Char(c) if c == delim => return Ok(RawRead::Done(text)),
+            KeyCode::Char(c) if !ctrl => {
+                text.push(c);
+                if !silent {
+                    write!(err, "{}", c)?;
+                    err.flush()?;
+                }
+            }
+            KeyCode::Backspace => {
+                let removed = text.pop().is_some();
+                if removed && !silent {
+                    write!(err, "\x08 \x08")?;
+                    err.flush()?;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 fn clamp_u16(value: usize) -> u16 {
     value.min(u16::MAX as usize) as u16
 }
@@ -0,0 +1,186 @@
+//! Link creation for `ln`: hard links, symbolic links and directory junctions.
+//!
+//! Symlink targets are stored exactly as written, so relative targets resolve against the
+//! link's own directory as on Unix. Windows has to know whether a symlink points at a directory
+//! when it is created, and refuses to create one at all without Developer Mode or elevation;
+//! that failure is reported with a hint rather than as a bare OS error. Junctions need no
+//! privilege but only link directories, by absolute path. On Unix a junction is simply a
+//! directory symlink with an absolute target.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LinkKind {
+    Hard,
+    Symbolic,
+    Junction,
+}
+
+impl LinkKind {
+    /// Wording for messages: "failed to create symbolic link ..."
+    pub fn describe(self) -> &'static str {
+        match self {
+            LinkKind::Hard => "hard link",
+            LinkKind::Symbolic => "symbolic link",
+            LinkKind::Junction => "junction",
+        }
+    }
+}
+
+/// `ERROR_PRIVILEGE_NOT_HELD`, returned by `CreateSymbolicLinkW` for unprivileged users.
+#[cfg(windows)]
+const ERROR_PRIVILEGE_NOT_HELD: i32 = 1314;
+
+/// `IO_REPARSE_TAG_MOUNT_POINT`, the reparse tag of a junction.
+#[cfg(any(windows, test))]
+const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xA000_0003;
+
+/// Create `link` pointing at `target`. Symbolic targets are used as written; hard link and
+/// junction targets must already be resolved.
+pub fn create(kind: LinkKind, target: &Path, link: &Path) -> io::Result<()> {
+    match kind {
+        LinkKind::Hard => fs::hard_link(target, link),
+        LinkKind::Symbolic => symlink(target, link),
+        LinkKind::Junction => junction(target, link),
+    }
+}
+
+/// Whether `path` itself is a symlink or junction (`is_symlink` covers both on Windows).
+pub fn is_link(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink())
+}
+
+/// Remove a file or link at `path` so a new link can take its place. Directory symlinks and
+/// junctions are directories to Windows and need `remove_dir`.
+pub fn remove_existing(path: &Path) -> io::Result<()> {
+    fs::remove_file(path).or_else(|e| if is_link(path) { fs::remove_dir(path) } else { Err(e) })
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    use std::os::windows::fs::{symlink_dir, symlink_file};
+
+    // Relative targets only resolve with backslash separators.
+    let target = std::path::PathBuf::from(target.to_string_lossy().replace('/', "\\"));
+    let base = link.parent().unwrap_or(Path::new("."));
+    let result = if base.join(&target).is_dir() {
+        symlink_dir(&target, link)
+    } else {
+        symlink_file(&target, link)
+    };
+    result.map_err(|e| {
+        if e.raw_os_error() == Some(ERROR_PRIVILEGE_NOT_HELD) {
+            io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "creating symbolic links needs Developer Mode or an elevated shell \
+                 (use --junction for a directory, or a hard link for a file)",
+            )
+        } else {
+            e
+        }
+    })
+}
+
+#[cfg(not(windows))]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn junction(target: &Path, link: &Path) -> io::Result<()> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+
+    const FILE_FLAG_OPEN_REPARSE_POINT: u32 = 0x0020_0000;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+    const FSCTL_SET_REPARSE_POINT: u32 = 0x0009_00A4;
+
+    let data = mount_point_data(&target.to_string_lossy())?;
+    fs::create_dir(link)?;
+    let result = fs::OpenOptions::new()
+        .write(true)
+        .custom_flags(FILE_FLAG_OPEN_REPARSE_POINT | FILE_FLAG_BACKUP_SEMANTICS)
+        .open(link)
+        .and_then(|dir| {
+            let mut returned = 0u32;
+            let ok = unsafe {
+                DeviceIoControl(
+                    dir.as_raw_handle() as _,
+                    FSCTL_SET_REPARSE_POINT,
+                    data.as_ptr().cast(),
+                    data.len() as u32,
+                    std::ptr::null_mut(),
+                    0,
+                    &mut returned,
+                    std::ptr::null_mut(),
+                )
+            };
+            if ok == 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        });
+    if result.is_err() {
+        let _ = fs::remove_dir(link);
+    }
+    result
+}
+
+#[cfg(not(windows))]
+fn junction(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// `REPARSE_DATA_BUFFER` for a mount point: the NT substitute name (`\??\C:\dir`) and the
+/// print name (`C:\dir`), each NUL-terminated, lengths in bytes without the terminator.
+#[cfg(any(windows, test))]
+fn mount_point_data(target: &str) -> io::Result<Vec<u8>> {
+    /// `MAXIMUM_REPARSE_DATA_BUFFER_SIZE`
+    const MAX_DATA: usize = 16 * 1024;
+
+    let plain = target.strip_prefix(r"\\?\").unwrap_or(target);
+    let substitute: Vec<u16> = format!(r"\??\{}", plain).encode_utf16().collect();
+    let print: Vec<u16> = plain.encode_utf16().collect();
+    // Tag + length + reserved (8 bytes), four offsets/lengths (8 bytes), then the names.
+    let names = (substitute.len() + print.len() + 2) * 2;
+    if 16 + names > MAX_DATA {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "junction target path is too long"));
+    }
+    let sub_bytes = (substitute.len() * 2) as u16;
+
+    let mut data = Vec::with_capacity(16 + names);
+    data.extend(IO_REPARSE_TAG_MOUNT_POINT.to_le_bytes());
+    data.extend(((8 + names) as u16).to_le_bytes());
+    data.extend(0u16.to_le_bytes());
+    data.extend(0u16.to_le_bytes());
+    data.extend(sub_bytes.to_le_bytes());
+    data.extend((sub_bytes + 2).to_le_bytes());
+    data.extend(((print.len() * 2) as u16).to_le_bytes());
+    for unit in substitute.iter().chain(&[0]).chain(&print).chain(&[0]) {
+        data.extend(unit.to_le_bytes());
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mount_point_data_layout() {
+        let data = mount_point_data(r"\\?\C:\d").unwrap();
+        let u16_at = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+        assert_eq!(&data[..4], &IO_REPARSE_TAG_MOUNT_POINT.to_le_bytes());
+        // `\??\C:\d` (8 units) and `C:\d` (4 units), each with a terminator.
+        assert_eq!(data.len(), 16 + (9 + 5) * 2);
+        assert_eq!(u16_at(4) as usize, data.len() - 8);
+        assert_eq!((u16_at(8), u16_at(10), u16_at(12), u16_at(14)), (0, 16, 18, 8));
+        let names: Vec<u16> = (16..data.len()).step_by(2).map(u16_at).collect();
+        assert_eq!(String::from_utf16(&names).unwrap(), "\\??\\C:\\d\0C:\\d\0");
+        assert!(mount_point_data(&"x".repeat(9000)).is_err());
+    }
+}
@@ -5,18 +5,68 @@ pub mod builtin;
 pub mod executor;
 pub mod parser;
 pub mod completer;
+pub mod cond;
+pub mod find;
 pub mod input;
 pub mod busybox;
+pub mod encoding;
+pub mod frecency;
+pub mod fsutil;
+pub mod guard;
+pub mod history;
+pub mod link;
+pub mod policy;
+pub mod printf;
+pub mod stat;
+pub mod trash;
 pub mod venv;
+pub mod walk;
+pub mod watch;
 
 use std::collections::HashMap;
 use std::env;
 use std::path::PathBuf;
-use anyhow::Result;
+use anyhow::{bail, Result};
 use colored::Colorize;
 
 use crate::task::TaskManager;
 
+/// Shell options toggled with `set -o` / `set +o`
+#[derive(Debug, Clone)]
+pub struct ShellOptions {
+    /// Refuse to overwrite existing files with `>` (use `>|` to force)
+    pub noclobber: bool,
+    /// Pass `/dev/null`, `/dev/stdin`, ... to native tools as `NUL` / `CON`
+    pub devargs: bool,
+    /// `rm` moves targets to the trash store instead of deleting them
+    pub saferm: bool,
+    /// Expand `!!`, `!$`, `^old^new`, ... in interactive input
+    pub histexpand: bool,
+    /// A directory name typed as a command changes into it
+    pub autocd: bool,
+}
+
+impl Default for ShellOptions {
+    fn default() -> Self {
+        Self {
+            noclobber: false,
+            devargs: false,
+            saferm: false,
+            histexpand: true,
+            autocd: false,
+        }
+    }
+}
+
+/// Variable attributes set with `declare` / `readonly` (being exported is where the value lives)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VarAttrs {
+    /// Assignments and `unset` are rejected
+    pub readonly: bool,
+    /// Assigned values must be integers
+    pub integer: bool,
+}
+
 /// Main shell state
 pub struct Shell {
     /// Current working directory
@@ -25,10 +75,20 @@ pub struct Shell {
     pub tasks: TaskManager,
     /// Command aliases (bash-style)
     pub aliases: HashMap<String, String>,
-    /// Shell variables (non-exported)
+    /// Shell variables (non-exported); exported variables live in the process environment
     pub vars: HashMap<String, String>,
+    /// `readonly` / `declare -i` attributes by variable name
+    pub var_attrs: HashMap<String, VarAttrs>,
+    /// Indexed arrays (`read -a`, `BASH_REMATCH`); never exported
+    pub arrays: HashMap<String, Vec<String>>,
     /// Last command exit status (for $?)
     pub last_status: i32,
+    /// `set -o` options
+    pub options: ShellOptions,
+    /// Running the interactive REPL (guarded operations may prompt)
+    pub interactive: bool,
+    /// `pushd` stack (most recent first; the current directory is not included)
+    pub dir_stack: Vec<PathBuf>,
     /// Should exit
     pub should_exit: bool,
     /// Exit warning shown (for running jobs confirmation)
@@ -42,7 +102,12 @@ impl Shell {
             tasks: TaskManager::new(),
             aliases: HashMap::new(),
             vars: HashMap::new(),
+            var_attrs: HashMap::new(),
+            arrays: HashMap::new(),
             last_status: 0,
+            options: ShellOptions::default(),
+            interactive: false,
+            dir_stack: Vec::new(),
             should_exit: false,
             exit_warned: false,
         })
@@ -66,7 +131,12 @@ impl Shell {
                 // For now, reuse the existing background runner (string-based) to keep TaskManager
                 // output capture behavior unchanged.
                 let cmd_str = line.trim_end_matches('&').trim();
-                executor::execute_background(&mut self.tasks, cmd_str, &self.cwd, &self.aliases)?;
+                // `nohup cmd &` is the same as `nohup cmd`: a detached job.
+                let (cmd_str, nohup) = match cmd_str.strip_prefix("nohup ") {
+                    Some(rest) => (rest.trim(), true),
+                    None => (cmd_str, false),
+                };
+                executor::execute_background(&mut self.tasks, cmd_str, &self.cwd, &self.aliases, nohup)?;
                 self.last_status = 0;
             }
             cmd => {
@@ -78,6 +148,141 @@ impl Shell {
         Ok(())
     }
 
+    /// Change the working directory, keeping `$OLDPWD` / `$PWD` and the `z` database up to date.
+    pub fn change_dir(&mut self, target: PathBuf) -> Result<()> {
+        env::set_current_dir(&target)?;
+        let old = std::mem::replace(&mut self.cwd, target);
+        env::set_var("OLDPWD", &old);
+        env::set_var("PWD", &self.cwd);
+        frecency::record(&self.cwd);
+        Ok(())
+    }
+
+    /// Value of a shell variable, falling back to the environment.
+    ///
+    /// Arrays answer `NAME[i]`, `NAME[@]` / `NAME[*]` (elements joined by spaces) and plain
+    /// `NAME` (element 0).
+    pub fn get_var(&self, name: &str) -> Option<String> {
+        let (base, index) = match name.strip_suffix(']').and_then(|n| n.split_once('[')) {
+            Some((base, index)) => (base, Some(index)),
+            None => (name, None),
+        };
+        if let Some(items) = self.arrays.get(base) {
+            return match index {
+                Some("@" | "*") => Some(items.join(" ")),
+                Some(i) => i.parse::<usize>().ok().and_then(|i| items.get(i).cloned()),
+                None => items.first().cloned(),
+            };
+        }
+        self.vars.get(name).cloned().or_else(|| env::var(name).ok())
+    }
+
+    /// Replace `name` with an indexed array, dropping any scalar of the same name.
+    pub fn set_array(&mut self, name: &str, items: Vec<String>) -> Result<()> {
+        self.check_assignable(name)?;
+        self.vars.remove(name);
+        env::remove_var(name);
+        self.arrays.insert(name.to_string(), items);
+        Ok(())
+    }
+
+    pub fn is_exported(&self, name: &str) -> bool {
+        !self.vars.contains_key(name) && env::var_os(name).is_some()
+    }
+
+    pub fn var_attrs(&self, name: &str) -> VarAttrs {
+        self.var_attrs.get(name).copied().unwrap_or_default()
+    }
+
+    /// Fail with `NAME: readonly variable` when `name` may not be assigned.
+    pub fn check_assignable(&self, name: &str) -> Result<()> {
+        if self.var_attrs(name).readonly {
+            bail!("{}: readonly variable", name);
+        }
+        Ok(())
+    }
+
+    /// Assign a variable. Shell variables stay unexported; anything else is set in the environment.
+    pub fn set_var(&mut self, name: &str, value: &str) -> Result<()> {
+        self.check_assignable(name)?;
+        let value = if self.var_attrs(name).integer {
+            match value.trim().parse::<i64>() {
+                Ok(n) => n.to_string(),
+                Err(_) => bail!("{}: integer expression expected", value),
+            }
+        } else {
+            value.to_string()
+        };
+        if let Some(items) = self.arrays.get_mut(name) {
+            match items.first_mut() {
+                Some(first) => *first = value,
+                None => items.push(value),
+            }
+            return Ok(());
+        }
+        match self.vars.get_mut(name) {
+            Some(slot) => *slot = value,
+            None => env::set_var(name, value),
+        }
+        Ok(())
+    }
+
+    /// Move a variable between the environment (`export`) and the shell (`export -n`).
+    pub fn set_exported(&mut self, name: &str, exported: bool) {
+        if exported {
+            if let Some(value) = self.vars.remove(name) {
+                env::set_var(name, value);
+            }
+        } else if let Some(value) = env::var_os(name) {
+            env::remove_var(name);
+            self.vars.insert(name.to_string(), value.to_string_lossy().into_owned());
+        }
+    }
+
+    /// Remove a variable or array along with its attributes.
+    pub fn unset_var(&mut self, name: &str) -> Result<()> {
+        if self.var_attrs(name).readonly {
+            bail!("{}: cannot unset: readonly variable", name);
+        }
+        self.vars.remove(name);
+        self.var_attrs.remove(name);
+        self.arrays.remove(name);
+        env::remove_var(name);
+        Ok(())
+    }
+
+    /// The current directory followed by the `pushd` stack, as shown by `dirs`.
+    pub fn dir_list(&self) -> Vec<PathBuf> {
+        std::iter::once(self.cwd.clone())
+            .chain(self.dir_stack.iter().cloned())
+            .collect()
+    }
+
+    /// Resolve a `dirs`-style index: `N` / `+N` from the left, `-N` from the right.
+    pub fn dir_stack_index(&self, spec: &str) -> Option<usize> {
+        let len = self.dir_stack.len() + 1;
+        if let Some(n) = spec.strip_prefix('-') {
+            let n: usize = n.parse().ok()?;
+            return (n < len).then(|| len - 1 - n);
+        }
+        let n: usize = spec.strip_prefix('+').unwrap_or(spec).parse().ok()?;
+        (n < len).then_some(n)
+    }
+
+    /// Tilde forms that refer to shell state: `~+` (cwd), `~-` ($OLDPWD), `~N` / `~+N` / `~-N` (stack).
+    pub fn expand_dir_tilde(&self, spec: &str) -> Option<PathBuf> {
+        match spec {
+            "+" => Some(self.cwd.clone()),
+            "-" => env::var_os("OLDPWD").map(PathBuf::from),
+            _ if spec.trim_start_matches(['+', '-']).chars().all(|c| c.is_ascii_digit())
+                && spec.chars().any(|c| c.is_ascii_digit()) =>
+            {
+                self.dir_stack_index(spec).map(|i| self.dir_list()[i].clone())
+            }
+            _ => None,
+        }
+    }
+
     /// Get prompt string
     pub fn prompt(&self) -> String {
         fn shorten(s: &str, max: usize) -> String {
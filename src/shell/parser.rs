@@ -4,8 +4,9 @@
 //! - Simple commands: `ls -la`
 //! - Pipelines: `ls | grep foo | head`
 //! - And/Or: `cmd1 && cmd2`, `cmd1 || cmd2`
-//! - Redirects: `echo hi > file.txt`, `cat < input.txt`
+//! - Redirects: `echo hi > file.txt`, `cat < input.txt`, `echo hi >| file.txt` (force)
 //! - Background: `cmd &`
+//! - Conditionals: `[[ $branch == feature/* ]]`
 //!
 //! Operator precedence (low to high):
 //! 1. `||` (or)
@@ -21,6 +22,8 @@ use anyhow::{bail, Result};
 pub enum RedirectMode {
     /// `>` - overwrite file
     Overwrite,
+    /// `>|` - overwrite file even when `noclobber` or a protected pattern applies
+    ForceOverwrite,
     /// `>>` - append to file
     Append,
     /// `<` - read from file
@@ -100,6 +103,8 @@ pub enum Command {
     },
     /// Background: `cmd &`
     Background(Box<Command>),
+    /// Conditional expression: `[[ $name == *.rs && -f $name ]]` (words between the brackets)
+    Conditional(Vec<Word>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -110,6 +115,7 @@ enum Token {
     AndIf,
     OrIf,
     RedirectOut,
+    RedirectOutClobber,
     RedirectOutAppend,
     RedirectErrOut,
     RedirectErrOutAppend,
@@ -388,6 +394,7 @@ impl Parser {
         loop {
             let mode = match self.peek() {
                 Some(Token::RedirectOut) => Some(RedirectMode::Overwrite),
+                Some(Token::RedirectOutClobber) => Some(RedirectMode::ForceOverwrite),
                 Some(Token::RedirectOutAppend) => Some(RedirectMode::Append),
                 Some(Token::RedirectErrOut) => Some(RedirectMode::StderrOverwrite),
                 Some(Token::RedirectErrOutAppend) => Some(RedirectMode::StderrAppend),
@@ -421,6 +428,10 @@ impl Parser {
     }
 
     fn parse_simple(&mut self) -> Result<Command> {
+        if matches!(self.peek(), Some(Token::Word(w)) if is_keyword(w, "[[")) {
+            return self.parse_conditional();
+        }
+
         let mut parts: Vec<Word> = Vec::new();
 
         while let Some(Token::Word(_)) = self.peek() {
@@ -433,6 +444,86 @@ impl Parser {
             Ok(Command::Simple(parts))
         }
     }
+
+    /// `[[ ... ]]`: operators inside the brackets (`&&`, `||`, `<`, `>`) are plain words.
+    fn parse_conditional(&mut self) -> Result<Command> {
+        self.next(); // consume `[[`
+        let mut words: Vec<Word> = Vec::new();
+        loop {
+            if words.last().is_some_and(|w| is_keyword(w, "=~")) {
+                words.push(self.parse_regex_operand()?);
+                continue;
+            }
+            let word = match self.next() {
+                Some(Token::Word(w)) if is_keyword(&w, "]]") => break,
+                Some(Token::Word(w)) => w,
+                Some(Token::AndIf) => Word::from_str("&&"),
+                Some(Token::OrIf) => Word::from_str("||"),
+                Some(Token::RedirectIn) => Word::from_str("<"),
+                Some(Token::RedirectOut) => Word::from_str(">"),
+                Some(Token::Pipe) => Word::from_str("|"),
+                Some(other) => bail!("Unexpected token in `[[': {:?}", other),
+                None => bail!("expected `]]'"),
+            };
+            words.push(word);
+        }
+        Ok(Command::Conditional(words))
+    }
+
+    /// The right side of `=~` is one regex: `|`, `<` and `>` join the tokens around them, as do
+    /// `&&`/`||` inside parentheses. Stops before `]]` or an unparenthesised `&&`/`||`.
+    fn parse_regex_operand(&mut self) -> Result<Word> {
+        let mut regex = Word { parts: Vec::new() };
+        let mut depth = 0i32;
+        loop {
+            let text = match self.peek() {
+                Some(Token::Word(w)) if depth <= 0 && is_keyword(w, "]]") => break,
+                Some(Token::AndIf | Token::OrIf) if depth <= 0 => break,
+                Some(Token::Word(w)) => {
+                    depth += paren_depth(w);
+                    regex.parts.extend(w.parts.iter().cloned());
+                    self.pos += 1;
+                    continue;
+                }
+                Some(Token::AndIf) => "&&",
+                Some(Token::OrIf) => "||",
+                Some(Token::Pipe) => "|",
+                Some(Token::RedirectIn) => "<",
+                Some(Token::RedirectOut) => ">",
+                Some(other) => bail!("Unexpected token in `[[': {:?}", other),
+                None => bail!("expected `]]'"),
+            };
+            regex.parts.push(WordPart { text: text.to_string(), quote: QuoteMode::None });
+            self.pos += 1;
+        }
+        if regex.parts.is_empty() {
+            bail!("expected regex after `=~'");
+        }
+        Ok(regex)
+    }
+}
+
+fn is_keyword(word: &Word, keyword: &str) -> bool {
+    word.parts.len() == 1 && word.parts[0].quote == QuoteMode::None && word.parts[0].text == keyword
+}
+
+/// Net change in unquoted, unescaped parenthesis nesting across `word`.
+fn paren_depth(word: &Word) -> i32 {
+    let mut depth = 0;
+    for part in word.parts.iter().filter(|p| p.quote == QuoteMode::None) {
+        let mut chars = part.text.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+    depth
 }
 
 fn tokenize(input: &str) -> Result<Vec<Token>> {
@@ -505,6 +596,12 @@ fn tokenize(input: &str) -> Result<Vec<Token>> {
                 if i + 2 < chars.len() && chars[i + 2] == '>' {
                     tokens.push(Token::RedirectErrOutAppend);
                     i += 2;
+                } else if i + 3 < chars.len() && chars[i + 2] == '&' && chars[i + 3] == '1' {
+                    // `2>&1` - keep `&1` as the target word instead of a background `&`
+                    tokens.push(Token::RedirectErrOut);
+                    buf.push_str("&1");
+                    finish_word(&mut tokens, &mut buf, &mut parts);
+                    i += 3;
                 } else {
                     tokens.push(Token::RedirectErrOut);
                     i += 1;
@@ -536,6 +633,9 @@ fn tokenize(input: &str) -> Result<Vec<Token>> {
                 if i + 1 < chars.len() && chars[i + 1] == '>' {
                     tokens.push(Token::RedirectOutAppend);
                     i += 1;
+                } else if i + 1 < chars.len() && chars[i + 1] == '|' {
+                    tokens.push(Token::RedirectOutClobber);
+                    i += 1;
                 } else {
                     tokens.push(Token::RedirectOut);
                 }
@@ -657,4 +757,64 @@ mod tests {
     fn test_is_incomplete_respects_escaped_quote_in_double_quotes() {
         assert!(!is_incomplete("echo \"a\\\"b\""));
     }
+
+    #[test]
+    fn test_parse_force_overwrite_redirect() {
+        assert_eq!(
+            parse("echo hi >| out.txt").unwrap(),
+            Command::Redirect {
+                cmd: Box::new(Command::Simple(vec!["echo".into(), "hi".into()])),
+                target: "out.txt".into(),
+                mode: RedirectMode::ForceOverwrite,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_merge_stderr_after_redirect() {
+        assert_eq!(
+            parse("cmd > /dev/null 2>&1").unwrap(),
+            Command::Redirect {
+                cmd: Box::new(Command::Redirect {
+                    cmd: Box::new(Command::Simple(vec!["cmd".into()])),
+                    target: "/dev/null".into(),
+                    mode: RedirectMode::Overwrite,
+                }),
+                target: "&1".into(),
+                mode: RedirectMode::MergeStderrToStdout,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_conditional() {
+        assert_eq!(
+            parse("[[ $a < b && -f x ]] || echo no").unwrap(),
+            Command::Or(
+                Box::new(Command::Conditional(vec![
+                    "$a".into(),
+                    "<".into(),
+                    "b".into(),
+                    "&&".into(),
+                    "-f".into(),
+                    "x".into(),
+                ])),
+                Box::new(Command::Simple(vec!["echo".into(), "no".into()])),
+            )
+        );
+        assert!(parse("[[ -f x").is_err());
+    }
+
+    #[test]
+    fn test_parse_conditional_regex_operand() {
+        let regex = |input: &str| match parse(input).unwrap() {
+            Command::Conditional(words) => words.iter().map(|w| w.parts.iter().map(|p| p.text.as_str()).collect::<String>()).collect::<Vec<_>>(),
+            other => panic!("not a conditional: {:?}", other),
+        };
+        assert_eq!(regex("[[ ab =~ ^(a|b)b$ ]]"), ["ab", "=~", "^(a|b)b$"]);
+        assert_eq!(regex("[[ x =~ x|y ]]"), ["x", "=~", "x|y"]);
+        assert_eq!(regex("[[ $v =~ ^<(a||b)>$ && -n $v ]]"), ["$v", "=~", "^<(a||b)>$", "&&", "-n", "$v"]);
+        assert_eq!(regex("[[ a =~ a || b =~ \\(b ]]"), ["a", "=~", "a", "||", "b", "=~", "\\(b"]);
+        assert!(parse("[[ a =~ ]]").is_err());
+    }
 }
@@ -90,6 +90,43 @@ pub fn reserved_name_error(name: &str) -> String {
     )
 }
 
+/// Unix pseudo-device paths that commonly appear in pasted Linux snippets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DevicePath {
+    /// `/dev/null`
+    Null,
+    /// `/dev/stdin`
+    Stdin,
+    /// `/dev/stdout`
+    Stdout,
+    /// `/dev/stderr`
+    Stderr,
+}
+
+impl DevicePath {
+    /// Closest Windows device name, for passing to native tools (`NUL` / `CON`).
+    pub fn windows_name(self) -> &'static str {
+        match self {
+            DevicePath::Null => "NUL",
+            DevicePath::Stdin | DevicePath::Stdout | DevicePath::Stderr => "CON",
+        }
+    }
+}
+
+/// Recognize `/dev/null`, `/dev/stdin`, `/dev/stdout` and `/dev/stderr`.
+///
+/// Only the exact Unix spellings match, so explicit `NUL` keeps going through
+/// [`is_windows_reserved_name`].
+pub fn device_path(text: &str) -> Option<DevicePath> {
+    match text.trim() {
+        "/dev/null" => Some(DevicePath::Null),
+        "/dev/stdin" => Some(DevicePath::Stdin),
+        "/dev/stdout" => Some(DevicePath::Stdout),
+        "/dev/stderr" => Some(DevicePath::Stderr),
+        _ => None,
+    }
+}
+
 /// Get home directory for a specific username (Windows implementation)
 #[cfg(windows)]
 fn user_home_dir(username: &str) -> PathBuf {
@@ -254,19 +291,54 @@ pub fn resolve_fs(base: &Path, path: &str) -> PathBuf {
     PathBuf::from(add_long_path_prefix(&resolved_str))
 }
 
+/// Directories listed in `$CDPATH` (`;`-separated on Windows, `:` elsewhere).
+pub fn cdpath_dirs() -> Vec<PathBuf> {
+    match std::env::var_os("CDPATH") {
+        Some(value) => std::env::split_paths(&value)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Whether `cd` may look `name` up in `$CDPATH`: a plain relative name, not `./x`, `../x`,
+/// `~/x` or anything absolute.
+pub fn is_cdpath_candidate(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(['.', '~', '/', '\\'])
+        && !normalize(name).is_absolute()
+        && (name.len() < 2 || name.as_bytes()[1] != b':')
+}
+
+/// Find `name` under one of the `$CDPATH` directories.
+pub fn cdpath_lookup(name: &str) -> Option<PathBuf> {
+    if !is_cdpath_candidate(name) {
+        return None;
+    }
+    cdpath_dirs()
+        .into_iter()
+        .map(|dir| resolve_fs(&dir, name))
+        .find(|candidate| candidate.is_dir())
+}
+
 /// Expand environment variables in path
 /// Supports both Windows and bash syntax:
 /// - %USERPROFILE% -> C:\Users\xxx (Windows)
 /// - $HOME -> C:\Users\xxx (bash)
 /// - ${HOME} -> C:\Users\xxx (bash)
 pub fn expand_env(path: &str) -> String {
+    expand_vars(path, |name| std::env::var(name).ok())
+}
+
+/// [`expand_env`] with a custom variable lookup (the executor also sees unexported shell variables).
+pub fn expand_vars(path: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
     let mut result = path.to_string();
 
     // 1. Handle ${VAR} syntax (bash with braces)
     while let Some(start) = result.find("${") {
         if let Some(end) = result[start + 2..].find('}') {
             let var_name = &result[start + 2..start + 2 + end];
-            if let Ok(value) = std::env::var(var_name) {
+            if let Some(value) = lookup(var_name) {
                 result = result.replacen(&format!("${{{}}}", var_name), &value, 1);
             } else {
                 // Can't expand, replace with empty string (bash behavior)
@@ -298,7 +370,7 @@ pub fn expand_env(path: &str) -> String {
 
             if var_len > 0 {
                 let var_name: String = rest.chars().take(var_len).collect();
-                if let Ok(value) = std::env::var(&var_name) {
+                if let Some(value) = lookup(&var_name) {
                     let pattern = format!("${}", var_name);
                     result = result.replacen(&pattern, &value, 1);
                     // Don't increment i, re-scan from same position in case value contains $
@@ -323,7 +395,7 @@ pub fn expand_env(path: &str) -> String {
             if var_name.is_empty() {
                 break;  // %% escape, skip
             }
-            if let Ok(value) = std::env::var(var_name) {
+            if let Some(value) = lookup(var_name) {
                 result = result.replacen(&format!("%{}%", var_name), &value, 1);
             } else {
                 // Can't expand, skip this one
@@ -424,6 +496,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_device_path() {
+        assert_eq!(device_path("/dev/null"), Some(DevicePath::Null));
+        assert_eq!(device_path("/dev/stderr"), Some(DevicePath::Stderr));
+        assert_eq!(device_path("/dev/nullx"), None);
+        assert_eq!(device_path("NUL"), None);
+        assert_eq!(DevicePath::Null.windows_name(), "NUL");
+        assert_eq!(DevicePath::Stdout.windows_name(), "CON");
+    }
+
     #[cfg(windows)]
     #[test]
     fn test_windows_reserved_names() {
@@ -440,4 +522,16 @@ mod tests {
         assert!(!is_windows_reserved_name(Path::new("CONSOLE")));
         assert!(!is_windows_reserved_name(Path::new("COM10")));  // only 1-9
     }
+
+    #[test]
+    fn test_cdpath_candidates() {
+        assert!(is_cdpath_candidate("packages"));
+        assert!(is_cdpath_candidate("packages/api"));
+        assert!(!is_cdpath_candidate("./packages"));
+        assert!(!is_cdpath_candidate("../x"));
+        assert!(!is_cdpath_candidate("~/src"));
+        assert!(!is_cdpath_candidate("/c/Users"));
+        assert!(!is_cdpath_candidate("C:\\Users"));
+        assert!(!is_cdpath_candidate(""));
+    }
 }
@@ -0,0 +1,129 @@
+//! Dispatch policy for the external fallback chain.
+//!
+//! Configured via environment variables so it can be set from `.titanbashrc` and is
+//! inherited by background jobs:
+//! - `TITANBASH_STRICT=1`                  never fall back to `cmd /C` for unknown commands
+//! - `TITANBASH_BUSYBOX_PREFER=find,sort`  always dispatch these names to BusyBox
+//! - `TITANBASH_BUSYBOX_NEVER=tar`         never dispatch these names to BusyBox
+//!
+//! Lists accept `,`, `;` or whitespace as separators and are case-insensitive.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use super::busybox;
+
+pub const VAR_STRICT: &str = "TITANBASH_STRICT";
+pub const VAR_BUSYBOX_PREFER: &str = "TITANBASH_BUSYBOX_PREFER";
+pub const VAR_BUSYBOX_NEVER: &str = "TITANBASH_BUSYBOX_NEVER";
+
+#[derive(Debug, Clone, Default)]
+pub struct DispatchPolicy {
+    /// Never use `cmd /C` as a last resort.
+    pub strict: bool,
+    /// Applets that always go to BusyBox, even if a native executable exists on PATH.
+    pub busybox_prefer: HashSet<String>,
+    /// Applets that never go to BusyBox.
+    pub busybox_never: HashSet<String>,
+}
+
+impl DispatchPolicy {
+    pub fn from_env() -> Self {
+        let strict = std::env::var(VAR_STRICT)
+            .map(|v| is_truthy(&v))
+            .unwrap_or(false);
+        Self {
+            strict,
+            busybox_prefer: parse_list(&std::env::var(VAR_BUSYBOX_PREFER).unwrap_or_default()),
+            busybox_never: parse_list(&std::env::var(VAR_BUSYBOX_NEVER).unwrap_or_default()),
+        }
+    }
+
+    /// `name` should skip native PATH lookup and go straight to BusyBox.
+    ///
+    /// Only bare names qualify: `find.exe` or `C:\...\find` explicitly ask for a native tool.
+    pub fn prefers_busybox(&self, name: &str) -> bool {
+        if busybox::looks_like_path(name) || Path::new(name).extension().is_some() {
+            return false;
+        }
+        let applet = busybox::normalize_applet_name(name);
+        self.busybox_prefer.contains(&applet) && !self.busybox_never.contains(&applet)
+    }
+
+    pub fn allows_busybox(&self, applet: &str) -> bool {
+        !self.busybox_never.contains(&applet.to_ascii_lowercase())
+    }
+
+    /// The user made an explicit choice for this name (no shadowing warning needed).
+    pub fn is_configured(&self, applet: &str) -> bool {
+        let applet = applet.to_ascii_lowercase();
+        self.busybox_prefer.contains(&applet) || self.busybox_never.contains(&applet)
+    }
+}
+
+fn is_truthy(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
+fn parse_list(value: &str) -> HashSet<String> {
+    value
+        .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(busybox::normalize_applet_name)
+        .collect()
+}
+
+static SHADOW_WARNED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+/// Warn (once per name per session) that a native executable shadows a BusyBox applet.
+pub fn warn_shadowed_once(applet: &str, native: &Path) {
+    let warned = SHADOW_WARNED.get_or_init(|| Mutex::new(HashSet::new()));
+    let mut guard = warned.lock().unwrap_or_else(|p| p.into_inner());
+    if !guard.insert(applet.to_string()) {
+        return;
+    }
+    eprintln!(
+        "titanbash: warning: '{}' resolves to {} (shadows the BusyBox applet; set {}={} to use BusyBox, or {}={} to silence)",
+        applet,
+        native.display(),
+        VAR_BUSYBOX_PREFER,
+        applet,
+        VAR_BUSYBOX_NEVER,
+        applet
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_list_separators_and_case() {
+        let list = parse_list("Find, sort;TAR  grep.exe");
+        assert!(list.contains("find"));
+        assert!(list.contains("sort"));
+        assert!(list.contains("tar"));
+        assert!(list.contains("grep"));
+        assert_eq!(list.len(), 4);
+    }
+
+    #[test]
+    fn test_prefers_busybox_only_for_bare_names() {
+        let policy = DispatchPolicy {
+            busybox_prefer: parse_list("find,sort"),
+            busybox_never: parse_list("sort"),
+            ..Default::default()
+        };
+        assert!(policy.prefers_busybox("find"));
+        assert!(policy.prefers_busybox("FIND"));
+        assert!(!policy.prefers_busybox("find.exe"));
+        assert!(!policy.prefers_busybox(r"C:\Windows\System32\find"));
+        // "never" wins over "prefer"
+        assert!(!policy.prefers_busybox("sort"));
+        assert!(!policy.allows_busybox("sort"));
+    }
+}
@@ -0,0 +1,510 @@
+//! `printf` formatting and the backslash escapes shared with `echo -e`.
+//!
+//! Follows POSIX / bash `printf`:
+//! - conversions `%s %b %q %c %d %i %u %o %x %X %f %F %e %E %g %G %%`
+//! - flags `- + space # 0`, width and precision (both may be `*`)
+//! - the format is reused until every argument is consumed
+//! - numeric arguments accept `0x1F`, `017` and `'c` (character code)
+
+use std::fmt::Write as _;
+
+/// Result of formatting: the text, and whether `\c` asked to stop all further output.
+#[derive(Debug, Default, PartialEq)]
+pub struct Formatted {
+    pub text: String,
+    pub stop: bool,
+    /// Arguments that were not valid numbers (printed as 0, like bash)
+    pub errors: Vec<String>,
+}
+
+/// Which escape dialect to decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Escapes {
+    /// printf format strings: `\NNN` octal
+    Format,
+    /// `echo -e` and `%b`: `\0NNN` octal, `\c` stops output
+    Echo,
+}
+
+/// Decode backslash escapes. Returns the text and whether `\c` was seen (echo dialect only).
+pub fn unescape(input: &str, dialect: Escapes) -> (String, bool) {
+    let chars: Vec<char> = input.chars().collect();
+    let mut out = String::with_capacity(input.len());
+    let mut i = 0usize;
+    while i < chars.len() {
+        if chars[i] != '\\' {
+            out.push(chars[i]);
+            i += 1;
+        } else if unescape_at(&chars, &mut i, dialect, &mut out) {
+            return (out, true);
+        }
+    }
+    (out, false)
+}
+
+/// Decode the escape whose backslash is at `chars[*i]` into `out` and move `*i` past it.
+/// Returns true for `\c` (echo dialect only).
+fn unescape_at(chars: &[char], i: &mut usize, dialect: Escapes, out: &mut String) -> bool {
+    if *i + 1 == chars.len() {
+        out.push('\\');
+        *i += 1;
+        return false;
+    }
+    let c = chars[*i + 1];
+    *i += 2;
+    match c {
+        '\\' => out.push('\\'),
+        'a' => out.push('\x07'),
+        'b' => out.push('\x08'),
+        'e' | 'E' => out.push('\x1b'),
+        'f' => out.push('\x0c'),
+        'n' => out.push('\n'),
+        'r' => out.push('\r'),
+        't' => out.push('\t'),
+        'v' => out.push('\x0b'),
+        '"' if dialect == Escapes::Format => out.push('"'),
+        '\'' if dialect == Escapes::Format => out.push('\''),
+        'c' if dialect == Escapes::Echo => return true,
+        '0'..='7' => {
+            // Echo dialect: `\0` introduces up to three more octal digits.
+            let (start, max) = match (dialect, c) {
+                (Escapes::Echo, '0') => (*i, 3),
+                (Escapes::Echo, _) => {
+                    out.push('\\');
+                    out.push(c);
+                    return false;
+                }
+                (Escapes::Format, _) => (*i - 1, 3),
+            };
+            let end = (start..chars.len().min(start + max))
+                .find(|&j| !chars[j].is_digit(8))
+                .unwrap_or(chars.len().min(start + max));
+            let digits: String = chars[start..end].iter().collect();
+            let value = u32::from_str_radix(&digits, 8).unwrap_or(0);
+            out.push(char::from_u32(value & 0xFF).unwrap_or('\u{FFFD}'));
+            *i = end;
+        }
+        'x' | 'u' | 'U' => {
+            let max = match c {
+                'x' => 2,
+                'u' => 4,
+                _ => 8,
+            };
+            let end = (*i..chars.len().min(*i + max))
+                .find(|&j| !chars[j].is_ascii_hexdigit())
+                .unwrap_or(chars.len().min(*i + max));
+            if end == *i {
+                out.push('\\');
+                out.push(c);
+                return false;
+            }
+            let digits: String = chars[*i..end].iter().collect();
+            let value = u32::from_str_radix(&digits, 16).unwrap_or(0);
+            out.push(char::from_u32(value).unwrap_or('\u{FFFD}'));
+            *i = end;
+        }
+        other => {
+            out.push('\\');
+            out.push(other);
+        }
+    }
+    false
+}
+
+/// Format `args` with `format`, reusing the format while arguments remain.
+///
+/// Escapes are decoded as the format is scanned, so an escaped `%` (`\045`) is literal text.
+pub fn format(format: &str, args: &[String]) -> Formatted {
+    let chars: Vec<char> = format.chars().collect();
+    let mut result = Formatted::default();
+    let mut args = args.iter().map(String::as_str);
+    let mut remaining = args.len();
+
+    loop {
+        let before = remaining;
+        if format_once(&chars, &mut args, &mut remaining, &mut result) {
+            result.stop = true;
+            return result;
+        }
+        // Stop when everything is consumed, or when the format takes no arguments at all.
+        if remaining == 0 || remaining == before {
+            return result;
+        }
+    }
+}
+
+/// One pass over the format. Returns true when `%b` hit `\c`.
+fn format_once<'a>(
+    chars: &[char],
+    args: &mut impl Iterator<Item = &'a str>,
+    remaining: &mut usize,
+    result: &mut Formatted,
+) -> bool {
+    let mut next_arg = |remaining: &mut usize| {
+        let arg = args.next();
+        if arg.is_some() {
+            *remaining -= 1;
+        }
+        arg
+    };
+
+    let mut i = 0usize;
+    while i < chars.len() {
+        if chars[i] == '\\' {
+            unescape_at(chars, &mut i, Escapes::Format, &mut result.text);
+            continue;
+        }
+        if chars[i] != '%' {
+            result.text.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        i += 1;
+        if chars.get(i) == Some(&'%') {
+            result.text.push('%');
+            i += 1;
+            continue;
+        }
+
+        let mut spec = Spec::default();
+        while let Some(&flag) = chars.get(i) {
+            match flag {
+                '-' => spec.left = true,
+                '+' => spec.plus = true,
+                ' ' => spec.space = true,
+                '#' => spec.alt = true,
+                '0' => spec.zero = true,
+                _ => break,
+            }
+            i += 1;
+        }
+        if chars.get(i) == Some(&'*') {
+            let width = parse_int(next_arg(remaining).unwrap_or(""), &mut result.errors);
+            if width < 0 {
+                spec.left = true;
+            }
+            spec.width = width.unsigned_abs() as usize;
+            i += 1;
+        } else {
+            spec.width = take_number(chars, &mut i);
+        }
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            if chars.get(i) == Some(&'*') {
+                let precision = parse_int(next_arg(remaining).unwrap_or(""), &mut result.errors);
+                spec.precision = (precision >= 0).then_some(precision as usize);
+                i += 1;
+            } else {
+                spec.precision = Some(take_number(chars, &mut i));
+            }
+        }
+        // Length modifiers (`%ld`, `%lld`, `%hd`) are accepted and ignored.
+        while matches!(chars.get(i), Some('l' | 'h' | 'j' | 'z' | 't' | 'L')) {
+            i += 1;
+        }
+
+        let Some(&conversion) = chars.get(i) else {
+            result.text.push('%');
+            break;
+        };
+        i += 1;
+
+        let arg = match conversion {
+            's' | 'b' | 'q' | 'c' | 'd' | 'i' | 'u' | 'o' | 'x' | 'X' | 'f' | 'F' | 'e' | 'E' | 'g' | 'G' => {
+                next_arg(remaining)
+            }
+            other => {
+                // Unknown conversion: print it literally, like bash.
+                result.text.push('%');
+                result.text.push(other);
+                continue;
+            }
+        };
+
+        let body = match conversion {
+            's' => truncate(arg.unwrap_or(""), spec.precision),
+            'b' => {
+                let (text, stop) = unescape(arg.unwrap_or(""), Escapes::Echo);
+                let text = truncate(&text, spec.precision);
+                if stop {
+                    result.text.push_str(&spec.pad(text, false));
+                    return true;
+                }
+                text
+            }
+            'q' => shell_quote(arg.unwrap_or("")),
+            'c' => arg.and_then(|a| a.chars().next()).map(String::from).unwrap_or_default(),
+            'd' | 'i' => {
+                let value = parse_int(arg.unwrap_or(""), &mut result.errors);
+                let digits = spec.min_digits(value.unsigned_abs().to_string());
+                result.text.push_str(&spec.pad_numeric(sign(value < 0, &spec), digits));
+                continue;
+            }
+            'u' | 'o' | 'x' | 'X' => {
+                let value = parse_int(arg.unwrap_or(""), &mut result.errors) as u64;
+                let (digits, prefix) = match conversion {
+                    'u' => (value.to_string(), ""),
+                    'o' => (format!("{:o}", value), if spec.alt { "0" } else { "" }),
+                    'x' => (format!("{:x}", value), if spec.alt && value != 0 { "0x" } else { "" }),
+                    _ => (format!("{:X}", value), if spec.alt && value != 0 { "0X" } else { "" }),
+                };
+                let digits = spec.min_digits(digits);
+                result.text.push_str(&spec.pad_numeric(prefix.to_string(), digits));
+                continue;
+            }
+            _ => {
+                let value = parse_float(arg.unwrap_or(""), &mut result.errors);
+                let precision = spec.precision.unwrap_or(6);
+                let magnitude = value.abs();
+                let digits = if magnitude.is_nan() || magnitude.is_infinite() {
+                    let text = if magnitude.is_nan() { "nan" } else { "inf" };
+                    spec.zero = false;
+                    text.to_string()
+                } else {
+                    match conversion {
+                        'f' | 'F' => format!("{:.*}", precision, magnitude),
+                        'e' | 'E' => format_exp(magnitude, precision),
+                        _ => format_general(magnitude, precision, spec.alt),
+                    }
+                };
+                let digits = if conversion.is_ascii_uppercase() {
+                    digits.to_uppercase()
+                } else {
+                    digits
+                };
+                let negative = value.is_sign_negative() && !value.is_nan();
+                result.text.push_str(&spec.pad_numeric(sign(negative, &spec), digits));
+                continue;
+            }
+        };
+        result.text.push_str(&spec.pad(body, false));
+    }
+    false
+}
+
+#[derive(Debug, Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alt: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    fn pad(&self, body: String, zero: bool) -> String {
+        let len = body.chars().count();
+        if len >= self.width {
+            return body;
+        }
+        let fill = self.width - len;
+        if self.left {
+            format!("{}{}", body, " ".repeat(fill))
+        } else if zero {
+            format!("{}{}", "0".repeat(fill), body)
+        } else {
+            format!("{}{}", " ".repeat(fill), body)
+        }
+    }
+
+    /// Integer precision: at least that many digits.
+    fn min_digits(&self, digits: String) -> String {
+        match self.precision {
+            Some(p) if digits.len() < p => format!("{}{}", "0".repeat(p - digits.len()), digits),
+            _ => digits,
+        }
+    }
+
+    /// Pad a number; zero padding goes between the sign/prefix and the digits.
+    fn pad_numeric(&self, prefix: String, digits: String) -> String {
+        if self.zero && !self.left {
+            let width = self.width.saturating_sub(prefix.len());
+            let padded = if digits.len() < width {
+                format!("{}{}", "0".repeat(width - digits.len()), digits)
+            } else {
+                digits
+            };
+            return format!("{}{}", prefix, padded);
+        }
+        self.pad(format!("{}{}", prefix, digits), false)
+    }
+}
+
+fn sign(negative: bool, spec: &Spec) -> String {
+    if negative {
+        "-".to_string()
+    } else if spec.plus {
+        "+".to_string()
+    } else if spec.space {
+        " ".to_string()
+    } else {
+        String::new()
+    }
+}
+
+fn take_number(chars: &[char], i: &mut usize) -> usize {
+    let start = *i;
+    while chars.get(*i).is_some_and(|c| c.is_ascii_digit()) {
+        *i += 1;
+    }
+    chars[start..*i].iter().collect::<String>().parse().unwrap_or(0)
+}
+
+fn truncate(text: &str, precision: Option<usize>) -> String {
+    match precision {
+        Some(p) => text.chars().take(p).collect(),
+        None => text.to_string(),
+    }
+}
+
+/// Integer argument: decimal, `0x` hex, leading-zero octal or `'c` / `"c` (character code).
+fn parse_int(arg: &str, errors: &mut Vec<String>) -> i64 {
+    let trimmed = arg.trim();
+    if trimmed.is_empty() {
+        return 0;
+    }
+    if let Some(rest) = trimmed.strip_prefix(['\'', '"']) {
+        return rest.chars().next().map(|c| c as i64).unwrap_or(0);
+    }
+    let (negative, digits) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let parsed = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        i64::from_str_radix(&digits[1..], 8)
+    } else {
+        digits.parse()
+    };
+    match parsed {
+        Ok(value) if negative => -value,
+        Ok(value) => value,
+        Err(_) => {
+            errors.push(format!("{}: invalid number", arg));
+            0
+        }
+    }
+}
+
+fn parse_float(arg: &str, errors: &mut Vec<String>) -> f64 {
+    let trimmed = arg.trim();
+    if trimmed.is_empty() {
+        return 0.0;
+    }
+    if trimmed.starts_with(['\'', '"']) || trimmed.contains(['x', 'X']) {
+        return parse_int(trimmed, errors) as f64;
+    }
+    trimmed.parse().unwrap_or_else(|_| {
+        errors.push(format!("{}: invalid number", arg));
+        0.0
+    })
+}
+
+/// C-style `%e`: `1.500000e+00`.
+fn format_exp(value: f64, precision: usize) -> String {
+    let text = format!("{:.*e}", precision, value);
+    let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let mut out = String::with_capacity(mantissa.len() + 4);
+    let _ = write!(out, "{}e{}{:02}", mantissa, if exponent < 0 { '-' } else { '+' }, exponent.abs());
+    out
+}
+
+/// C-style `%g`: shortest of `%e` / `%f` with `precision` significant digits.
+fn format_general(value: f64, precision: usize, alt: bool) -> String {
+    let precision = precision.max(1);
+    if value == 0.0 {
+        return if alt {
+            format!("{:.*}", precision - 1, 0.0)
+        } else {
+            "0".to_string()
+        };
+    }
+    let exponent = format!("{:.*e}", precision - 1, value)
+        .split_once('e')
+        .and_then(|(_, e)| e.parse::<i32>().ok())
+        .unwrap_or(0);
+    let text = if exponent < -4 || exponent >= precision as i32 {
+        format_exp(value, precision - 1)
+    } else {
+        format!("{:.*}", (precision as i32 - 1 - exponent).max(0) as usize, value)
+    };
+    if alt {
+        return text;
+    }
+    // Drop trailing zeros of the fraction (keeping any exponent).
+    let (number, exp) = match text.find('e') {
+        Some(pos) => text.split_at(pos),
+        None => (text.as_str(), ""),
+    };
+    let number = if number.contains('.') {
+        number.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        number
+    };
+    format!("{}{}", number, exp)
+}
+
+/// `%q`: quote so the shell reads the text back unchanged.
+pub fn shell_quote(text: &str) -> String {
+    if text.is_empty() {
+        return "''".to_string();
+    }
+    if text
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "_-./:=@%+,".contains(c))
+    {
+        return text.to_string();
+    }
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn f(format_str: &str, args: &[&str]) -> String {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        format(format_str, &args).text
+    }
+
+    #[test]
+    fn test_conversions_width_and_precision() {
+        assert_eq!(f("%s-%5s|%-5s|", &["a", "b", "c"]), "a-    b|c    |");
+        assert_eq!(f("%.2s", &["abcdef"]), "ab");
+        assert_eq!(f("%05d|%+d|% d|%-4d|", &["42", "7", "7", "3"]), "00042|+7| 7|3   |");
+        assert_eq!(f("%x %X %#x %o %#o", &["255", "255", "255", "8", "8"]), "ff FF 0xff 10 010");
+        assert_eq!(f("%d %d %d", &["0x10", "010", "'A"]), "16 8 65");
+        assert_eq!(f("%.3d|%*d|%-*d|", &["5", "4", "1", "3", "2"]), "005|   1|2  |");
+        assert_eq!(f("%.2f %8.3f %e", &["3.14159", "-2.5", "1234.5"]), "3.14   -2.500 1.234500e+03");
+        assert_eq!(f("%g %g %g", &["100000", "1000000", "0.0001"]), "100000 1e+06 0.0001");
+        assert_eq!(f("%c%c", &["hello", "w"]), "hw");
+        assert_eq!(f("100%%", &[]), "100%");
+    }
+
+    #[test]
+    fn test_format_reuse_and_missing_args() {
+        assert_eq!(f("%s=%s\n", &["a", "1", "b", "2", "c"]), "a=1\nb=2\nc=\n");
+        assert_eq!(f("[%d]", &[]), "[0]");
+        assert_eq!(f("plain\n", &["ignored"]), "plain\n");
+        let result = format("%d", &["abc".to_string()]);
+        assert_eq!(result.text, "0");
+        assert_eq!(result.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_escapes_and_b_q() {
+        assert_eq!(f("a\\tb\\n\\101\\x41\\u00e9", &[]), "a\tb\nAAé");
+        assert_eq!(f("\\045d\\n", &["5"]), "%d\n");
+        assert_eq!(f("\\x25s %s", &["x"]), "%s x");
+        assert_eq!(f("%b|", &["x\\ny\\0101"]), "x\nyA|");
+        let stopped = format("%b after", &["one\\ctwo".to_string()]);
+        assert_eq!((stopped.text.as_str(), stopped.stop), ("one", true));
+        assert_eq!(f("%q %q %q", &["plain", "two words", "it's"]), "plain 'two words' 'it'\\''s'");
+        assert_eq!(unescape("no \\c here", Escapes::Format).0, "no \\c here");
+        assert_eq!(unescape("cut\\c rest", Escapes::Echo), ("cut".to_string(), true));
+    }
+}
@@ -0,0 +1,430 @@
+//! `stat`: file metadata rendered through GNU-compatible `-c` format directives.
+//!
+//! The default report is itself a format string, so `-c`, `--printf` and the plain output share
+//! one renderer. On Windows the inode is the NTFS file index and the device is the volume serial
+//! number, both read with `GetFileInformationByHandle` (std only exposes them on nightly); there
+//! is no inode change time, so `%z` prints `-`. `%M` is an extension listing the Windows file
+//! attributes.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::fsutil::file_attributes;
+use super::printf;
+
+/// What `stat FILE` prints without `-c`.
+pub const DEFAULT_FORMAT: &str = "  File: %N\n  Size: %-15s %F\nDevice: %-15d Inode: %-20i Links: %h\n  Mode: (%a/%A)  Attributes: %M\nAccess: %x\nModify: %y\nChange: %z\n Birth: %w";
+
+/// Windows attribute bits and their names, in `%M` / JSON order.
+const ATTRIBUTE_NAMES: &[(u32, &str)] = &[
+    (0x1, "readonly"),
+    (0x2, "hidden"),
+    (0x4, "system"),
+    (0x10, "directory"),
+    (0x20, "archive"),
+    (0x100, "temporary"),
+    (0x200, "sparse"),
+    (0x400, "reparse_point"),
+    (0x800, "compressed"),
+    (0x1000, "offline"),
+    (0x2000, "not_content_indexed"),
+    (0x4000, "encrypted"),
+];
+
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+#[cfg(any(windows, test))]
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+pub struct FileStat {
+    /// The operand as given
+    pub name: String,
+    pub size: u64,
+    pub link_target: Option<PathBuf>,
+    pub attrs: u32,
+    /// `st_mode`; synthesised from the attributes on Windows
+    pub mode: u32,
+    pub device: u64,
+    pub inode: u64,
+    pub links: u64,
+    pub blocks: u64,
+    pub block_size: u64,
+    pub uid: u32,
+    pub gid: u32,
+    kind: &'static str,
+    pub accessed: Option<SystemTime>,
+    pub modified: Option<SystemTime>,
+    pub changed: Option<SystemTime>,
+    pub created: Option<SystemTime>,
+}
+
+/// The platform-specific part of a `FileStat`.
+struct Native {
+    mode: u32,
+    device: u64,
+    inode: u64,
+    links: u64,
+    blocks: u64,
+    block_size: u64,
+    uid: u32,
+    gid: u32,
+    changed: Option<SystemTime>,
+}
+
+impl FileStat {
+    /// Stat `path`, following a final symlink only when `follow` is set.
+    pub fn read(name: &str, path: &Path, follow: bool) -> io::Result<Self> {
+        let meta = if follow { fs::metadata(path)? } else { fs::symlink_metadata(path)? };
+        let link_target = if meta.file_type().is_symlink() { fs::read_link(path).ok() } else { None };
+        let native = native(path, &meta, follow);
+        Ok(Self {
+            name: name.to_string(),
+            size: meta.len(),
+            link_target,
+            attrs: file_attributes(&meta),
+            mode: native.mode,
+            device: native.device,
+            inode: native.inode,
+            links: native.links,
+            blocks: native.blocks,
+            block_size: native.block_size,
+            uid: native.uid,
+            gid: native.gid,
+            kind: file_kind(&meta),
+            accessed: meta.accessed().ok(),
+            modified: meta.modified().ok(),
+            changed: native.changed,
+            created: meta.created().ok(),
+        })
+    }
+
+    /// `%F`, e.g. "regular file", "directory", "symbolic link"
+    pub fn kind(&self) -> &'static str {
+        if self.kind == "regular file" && self.size == 0 {
+            "regular empty file"
+        } else {
+            self.kind
+        }
+    }
+
+    /// Names of the set attribute bits, e.g. `["archive", "hidden"]`.
+    pub fn attribute_names(&self) -> Vec<&'static str> {
+        ATTRIBUTE_NAMES.iter().filter(|(bit, _)| self.attrs & bit != 0).map(|(_, name)| *name).collect()
+    }
+
+    /// `%N`: the quoted name, plus ` -> 'target'` for a link.
+    fn quoted_name(&self) -> String {
+        match &self.link_target {
+            Some(target) => format!(
+                "{} -> {}",
+                printf::shell_quote(&self.name),
+                printf::shell_quote(&target.to_string_lossy())
+            ),
+            None => printf::shell_quote(&self.name),
+        }
+    }
+}
+
+fn file_kind(meta: &fs::Metadata) -> &'static str {
+    let file_type = meta.file_type();
+    if file_type.is_symlink() {
+        return "symbolic link";
+    }
+    if file_type.is_dir() {
+        return "directory";
+    }
+    if file_type.is_file() {
+        return "regular file";
+    }
+    #[cfg(not(windows))]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_fifo() {
+            return "fifo";
+        }
+        if file_type.is_socket() {
+            return "socket";
+        }
+        if file_type.is_char_device() {
+            return "character special file";
+        }
+        if file_type.is_block_device() {
+            return "block special file";
+        }
+    }
+    "weird file"
+}
+
+#[cfg(windows)]
+fn native(path: &Path, meta: &fs::Metadata, follow: bool) -> Native {
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION};
+
+    const FILE_FLAG_OPEN_REPARSE_POINT: u32 = 0x0020_0000;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+
+    // Metadata-only access; backup semantics lets directories be opened too.
+    let flags = FILE_FLAG_BACKUP_SEMANTICS | if follow { 0 } else { FILE_FLAG_OPEN_REPARSE_POINT };
+    let info = fs::OpenOptions::new().access_mode(0).custom_flags(flags).open(path).ok().and_then(|file| {
+        let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { std::mem::zeroed() };
+        let ok = unsafe { GetFileInformationByHandle(file.as_raw_handle() as _, &mut info) };
+        (ok != 0).then_some(info)
+    });
+
+    let file_type = if meta.file_type().is_symlink() {
+        S_IFLNK
+    } else if meta.is_dir() {
+        S_IFDIR
+    } else {
+        S_IFREG
+    };
+    let mut perms = if meta.permissions().readonly() { 0o444 } else { 0o666 };
+    if meta.is_dir() {
+        perms |= 0o111;
+    }
+    Native {
+        mode: file_type | perms,
+        device: info.map_or(0, |i| i.dwVolumeSerialNumber as u64),
+        inode: info.map_or(0, |i| ((i.nFileIndexHigh as u64) << 32) | i.nFileIndexLow as u64),
+        links: info.map_or(1, |i| i.nNumberOfLinks as u64),
+        blocks: meta.len().div_ceil(512),
+        block_size: 4096,
+        uid: 0,
+        gid: 0,
+        changed: None,
+    }
+}
+
+#[cfg(not(windows))]
+fn native(_path: &Path, meta: &fs::Metadata, _follow: bool) -> Native {
+    use std::os::unix::fs::MetadataExt;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let changed = u64::try_from(meta.ctime())
+        .ok()
+        .map(|secs| UNIX_EPOCH + Duration::new(secs, meta.ctime_nsec() as u32));
+    Native {
+        mode: meta.mode(),
+        device: meta.dev(),
+        inode: meta.ino(),
+        links: meta.nlink(),
+        blocks: meta.blocks(),
+        block_size: meta.blksize(),
+        uid: meta.uid(),
+        gid: meta.gid(),
+        changed,
+    }
+}
+
+/// `%A`: `drwxr-xr-x` from the mode bits.
+fn permission_string(mode: u32) -> String {
+    let kind = match mode & S_IFMT {
+        S_IFDIR => 'd',
+        S_IFLNK => 'l',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        0o010000 => 'p',
+        0o140000 => 's',
+        _ => '-',
+    };
+    let mut text = String::from(kind);
+    for shift in [6, 3, 0] {
+        let bits = (mode >> shift) & 0o7;
+        text.push(if bits & 4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 2 != 0 { 'w' } else { '-' });
+        text.push(if bits & 1 != 0 { 'x' } else { '-' });
+    }
+    text
+}
+
+/// GNU's human-readable time: `2024-01-15 10:30:45.123456789 +0100`, or `-` when unknown.
+fn human_time(time: Option<SystemTime>) -> String {
+    match time {
+        Some(time) => {
+            let local: chrono::DateTime<chrono::Local> = time.into();
+            local.format("%Y-%m-%d %H:%M:%S%.9f %z").to_string()
+        }
+        None => "-".to_string(),
+    }
+}
+
+/// Seconds since the epoch, or 0 when unknown.
+fn epoch_time(time: Option<SystemTime>) -> i64 {
+    time.map_or(0, |t| chrono::DateTime::<chrono::Utc>::from(t).timestamp())
+}
+
+/// Expand GNU `stat` directives (`%n %s %y %F` ...), with optional `-`/`0` flags and a width.
+/// Unknown directives print `?`.
+pub fn render(format: &str, st: &FileStat) -> String {
+    let mut out = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let mut flags = String::new();
+        while let Some(&f) = chars.peek().filter(|f| matches!(f, '-' | '0')) {
+            flags.push(f);
+            chars.next();
+        }
+        let mut width = 0usize;
+        while let Some(d) = chars.peek().and_then(|d| d.to_digit(10)) {
+            width = width * 10 + d as usize;
+            chars.next();
+        }
+        let Some(directive) = chars.next() else {
+            out.push('%');
+            break;
+        };
+        let value = match directive {
+            '%' => "%".to_string(),
+            'n' => st.name.clone(),
+            'N' => st.quoted_name(),
+            's' => st.size.to_string(),
+            'b' => st.blocks.to_string(),
+            'B' => "512".to_string(),
+            'o' => st.block_size.to_string(),
+            'F' => st.kind().to_string(),
+            'a' => format!("{:o}", st.mode & 0o7777),
+            'A' => permission_string(st.mode),
+            'f' => format!("{:x}", st.mode),
+            'd' => st.device.to_string(),
+            'D' => format!("{:x}", st.device),
+            'i' => st.inode.to_string(),
+            'h' => st.links.to_string(),
+            'u' => st.uid.to_string(),
+            'g' => st.gid.to_string(),
+            'M' => match st.attribute_names().join(",") {
+                names if names.is_empty() => "-".to_string(),
+                names => names,
+            },
+            'w' => human_time(st.created),
+            'W' => epoch_time(st.created).to_string(),
+            'x' => human_time(st.accessed),
+            'X' => epoch_time(st.accessed).to_string(),
+            'y' => human_time(st.modified),
+            'Y' => epoch_time(st.modified).to_string(),
+            'z' => human_time(st.changed),
+            'Z' => epoch_time(st.changed).to_string(),
+            _ => "?".to_string(),
+        };
+        let pad = width.saturating_sub(value.chars().count());
+        if flags.contains('-') {
+            out.push_str(&value);
+            out.push_str(&" ".repeat(pad));
+        } else if flags.contains('0') && value.chars().all(|c| c.is_ascii_digit()) {
+            out.push_str(&"0".repeat(pad));
+            out.push_str(&value);
+        } else {
+            out.push_str(&" ".repeat(pad));
+            out.push_str(&value);
+        }
+    }
+    out
+}
+
+/// One JSON object (single line) for `stat --json`.
+pub fn to_json(st: &FileStat) -> String {
+    let time = |t: Option<SystemTime>| match t {
+        Some(t) => json_string(&chrono::DateTime::<chrono::Local>::from(t).to_rfc3339()),
+        None => "null".to_string(),
+    };
+    let target = match &st.link_target {
+        Some(target) => json_string(&target.to_string_lossy()),
+        None => "null".to_string(),
+    };
+    let attributes: Vec<String> = st.attribute_names().iter().map(|a| json_string(a)).collect();
+    format!(
+        "{{\"name\":{},\"type\":{},\"size\":{},\"link_target\":{},\"mode\":\"{:o}\",\"permissions\":\"{}\",\
+         \"attributes\":[{}],\"device\":{},\"inode\":{},\"links\":{},\"uid\":{},\"gid\":{},\
+         \"accessed\":{},\"modified\":{},\"changed\":{},\"created\":{}}}",
+        json_string(&st.name),
+        json_string(st.kind()),
+        st.size,
+        target,
+        st.mode & 0o7777,
+        permission_string(st.mode),
+        attributes.join(","),
+        st.device,
+        st.inode,
+        st.links,
+        st.uid,
+        st.gid,
+        time(st.accessed),
+        time(st.modified),
+        time(st.changed),
+        time(st.created),
+    )
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn sample() -> FileStat {
+        FileStat {
+            name: "my file.txt".to_string(),
+            size: 1234,
+            link_target: None,
+            attrs: 0x20 | 0x2,
+            mode: S_IFREG | 0o644,
+            device: 2049,
+            inode: 42,
+            links: 1,
+            blocks: 8,
+            block_size: 4096,
+            uid: 1000,
+            gid: 100,
+            kind: "regular file",
+            accessed: None,
+            modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            changed: None,
+            created: None,
+        }
+    }
+
+    #[test]
+    fn test_render_gnu_directives() {
+        let st = sample();
+        assert_eq!(render("%n %s %F %a %A %h %i", &st), "my file.txt 1234 regular file 644 -rw-r--r-- 1 42");
+        assert_eq!(render("%N|%-6s|%6s|%06s|%%|%q", &st), "'my file.txt'|1234  |  1234|001234|%|?");
+        assert_eq!(render("%Y %W %z %M %f", &st), "1700000000 0 - hidden,archive 81a4");
+        assert!(render("%y", &st).contains(".000000000 "));
+
+        let link = FileStat { link_target: Some(PathBuf::from("target dir")), mode: S_IFLNK | 0o777, kind: "symbolic link", ..sample() };
+        assert_eq!(render("%N %F %A", &link), "'my file.txt' -> 'target dir' symbolic link lrwxrwxrwx");
+        let empty = FileStat { size: 0, ..sample() };
+        assert_eq!(render("%F", &empty), "regular empty file");
+    }
+
+    #[test]
+    fn test_json_output() {
+        let json = to_json(&FileStat { name: "a\"b\\c".to_string(), ..sample() });
+        assert!(json.starts_with("{\"name\":\"a\\\"b\\\\c\",\"type\":\"regular file\",\"size\":1234,\"link_target\":null,"));
+        assert!(json.contains("\"mode\":\"644\",\"permissions\":\"-rw-r--r--\",\"attributes\":[\"hidden\",\"archive\"]"));
+        assert!(json.contains("\"accessed\":null") && json.contains("\"modified\":\"2023-11-"));
+    }
+}
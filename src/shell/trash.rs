@@ -0,0 +1,407 @@
+//! Trash store for `rm --trash` / `set -o saferm`.
+//!
+//! Deleted items are moved to `~/.titanbash_trash` (override with `TITANBASH_TRASH_DIR`):
+//!
+//! ```text
+//! .titanbash_trash/
+//!   17/
+//!     info                 path=..., deleted=<unix seconds>, size=<bytes>
+//!     files/<file name>    the deleted file or directory
+//! ```
+//!
+//! Keeping the item under `files/` means a deleted file named `info` cannot collide with the
+//! metadata. Entries written before that layout (item next to `info`) still restore.
+//!
+//! The store is capped at `TITANBASH_TRASH_MAX_SIZE` (default 5G); the oldest entries are
+//! purged first when a new item would exceed it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+
+use super::fsutil::{move_entry, tree_footprint};
+
+pub const VAR_TRASH_DIR: &str = "TITANBASH_TRASH_DIR";
+pub const VAR_TRASH_MAX_SIZE: &str = "TITANBASH_TRASH_MAX_SIZE";
+
+const DEFAULT_MAX_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+const INFO_FILE: &str = "info";
+const FILES_DIR: &str = "files";
+
+#[derive(Debug, Clone)]
+pub struct TrashEntry {
+    pub id: u64,
+    /// Where the item lived before it was deleted
+    pub original: PathBuf,
+    pub deleted: SystemTime,
+    pub size: u64,
+    dir: PathBuf,
+}
+
+impl TrashEntry {
+    fn payload(&self) -> Option<PathBuf> {
+        let name = self.original.file_name()?;
+        let payload = self.dir.join(FILES_DIR).join(name);
+        if fs::symlink_metadata(&payload).is_ok() {
+            return Some(payload);
+        }
+        // Older layout: the item sits next to `info`.
+        Some(self.dir.join(name)).filter(|legacy| fs::symlink_metadata(legacy).is_ok())
+    }
+}
+
+pub struct TrashStore {
+    root: PathBuf,
+    pub max_size: u64,
+}
+
+impl TrashStore {
+    /// The store configured by the environment.
+    pub fn open() -> Result<Self> {
+        let root = match std::env::var_os(VAR_TRASH_DIR) {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => dirs::home_dir()
+                .ok_or_else(|| anyhow::anyhow!("trash: cannot determine home directory"))?
+                .join(".titanbash_trash"),
+        };
+        let max_size = match std::env::var(VAR_TRASH_MAX_SIZE) {
+            Ok(value) if !value.trim().is_empty() => parse_size(&value)
+                .with_context(|| format!("trash: invalid {}", VAR_TRASH_MAX_SIZE))?,
+            _ => DEFAULT_MAX_SIZE,
+        };
+        Ok(Self::with_root(root, max_size))
+    }
+
+    pub fn with_root(root: PathBuf, max_size: u64) -> Self {
+        Self { root, max_size }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Move `src` into the trash.
+    pub fn put(&self, src: &Path) -> Result<TrashEntry> {
+        let Some(name) = src.file_name() else {
+            bail!("trash: refusing to trash '{}'", src.display());
+        };
+        if self.root.starts_with(src) {
+            bail!(
+                "trash: refusing to trash '{}' (it contains the trash store)",
+                src.display()
+            );
+        }
+
+        let (_, size) = tree_footprint(src)
+            .with_context(|| format!("trash: cannot stat '{}'", src.display()))?;
+        if size > self.max_size {
+            bail!(
+                "trash: '{}' ({} bytes) is larger than the trash cap ({} bytes); use rm --no-trash",
+                src.display(),
+                size,
+                self.max_size
+            );
+        }
+
+        let (id, dir) = self.allocate()?;
+        let deleted = SystemTime::now();
+        let info = format!(
+            "path={}\ndeleted={}\nsize={}\n",
+            src.display(),
+            deleted.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            size
+        );
+        // Metadata first, so an item that made it into the store can always be restored.
+        let stored = fs::write(dir.join(INFO_FILE), info)
+            .with_context(|| format!("trash: cannot write metadata in '{}'", dir.display()))
+            .and_then(|()| {
+                fs::create_dir(dir.join(FILES_DIR))
+                    .with_context(|| format!("trash: cannot create '{}'", dir.join(FILES_DIR).display()))
+            })
+            .and_then(|()| move_entry(src, &dir.join(FILES_DIR).join(name)));
+        if let Err(e) = stored {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(e);
+        }
+
+        self.enforce_cap(id)?;
+
+        Ok(TrashEntry {
+            id,
+            original: src.to_path_buf(),
+            deleted,
+            size,
+            dir,
+        })
+    }
+
+    /// All entries, oldest first.
+    pub fn list(&self) -> Result<Vec<TrashEntry>> {
+        let mut entries = Vec::new();
+        let Ok(read) = fs::read_dir(&self.root) else {
+            return Ok(entries);
+        };
+        for dir_entry in read.flatten() {
+            let Some(id) = dir_entry.file_name().to_str().and_then(|s| s.parse::<u64>().ok()) else {
+                continue;
+            };
+            if let Some(entry) = read_info(id, &dir_entry.path()) {
+                entries.push(entry);
+            }
+        }
+        entries.sort_by_key(|e| (e.deleted, e.id));
+        Ok(entries)
+    }
+
+    /// Move entry `id` back to its original path. Returns that path.
+    pub fn restore(&self, id: u64) -> Result<PathBuf> {
+        let entry = self
+            .list()?
+            .into_iter()
+            .find(|e| e.id == id)
+            .ok_or_else(|| anyhow::anyhow!("trash: no entry with id {}", id))?;
+        let payload = entry
+            .payload()
+            .ok_or_else(|| anyhow::anyhow!("trash: entry {} has no stored item", id))?;
+
+        if fs::symlink_metadata(&entry.original).is_ok() {
+            bail!(
+                "trash: cannot restore {}: '{}' already exists",
+                id,
+                entry.original.display()
+            );
+        }
+        if let Some(parent) = entry.original.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("trash: cannot create '{}'", parent.display()))?;
+        }
+        move_entry(&payload, &entry.original)?;
+        fs::remove_dir_all(&entry.dir)
+            .with_context(|| format!("trash: cannot clean up '{}'", entry.dir.display()))?;
+        Ok(entry.original)
+    }
+
+    /// Permanently delete entries older than `older_than` (all entries if `None`).
+    ///
+    /// Returns (entries removed, bytes freed).
+    pub fn purge(&self, older_than: Option<Duration>) -> Result<(usize, u64)> {
+        let now = SystemTime::now();
+        let mut removed = (0, 0);
+        for entry in self.list()? {
+            let age = now.duration_since(entry.deleted).unwrap_or_default();
+            if older_than.is_some_and(|limit| age < limit) {
+                continue;
+            }
+            fs::remove_dir_all(&entry.dir)
+                .with_context(|| format!("trash: cannot purge '{}'", entry.dir.display()))?;
+            removed.0 += 1;
+            removed.1 += entry.size;
+        }
+        Ok(removed)
+    }
+
+    pub fn total_size(&self) -> Result<u64> {
+        Ok(self.list()?.iter().map(|e| e.size).sum())
+    }
+
+    /// Drop the oldest entries (never `keep`) until the store fits under the cap.
+    fn enforce_cap(&self, keep: u64) -> Result<()> {
+        let entries = self.list()?;
+        let mut total: u64 = entries.iter().map(|e| e.size).sum();
+        for entry in entries {
+            if total <= self.max_size {
+                break;
+            }
+            if entry.id == keep {
+                continue;
+            }
+            fs::remove_dir_all(&entry.dir)
+                .with_context(|| format!("trash: cannot purge '{}'", entry.dir.display()))?;
+            total = total.saturating_sub(entry.size);
+        }
+        Ok(())
+    }
+
+    /// Reserve the next numeric id (safe against concurrent shells).
+    fn allocate(&self) -> Result<(u64, PathBuf)> {
+        fs::create_dir_all(&self.root)
+            .with_context(|| format!("trash: cannot create '{}'", self.root.display()))?;
+        let mut id = fs::read_dir(&self.root)?
+            .flatten()
+            .filter_map(|e| e.file_name().to_str().and_then(|s| s.parse::<u64>().ok()))
+            .max()
+            .unwrap_or(0)
+            + 1;
+        loop {
+            let dir = self.root.join(id.to_string());
+            match fs::create_dir(&dir) {
+                Ok(()) => return Ok((id, dir)),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => id += 1,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("trash: cannot create '{}'", dir.display()))
+                }
+            }
+        }
+    }
+}
+
+fn read_info(id: u64, dir: &Path) -> Option<TrashEntry> {
+    let text = fs::read_to_string(dir.join(INFO_FILE)).ok()?;
+    let mut original = None;
+    let mut deleted = UNIX_EPOCH;
+    let mut size = 0;
+    for line in text.lines() {
+        match line.split_once('=') {
+            Some(("path", value)) => original = Some(PathBuf::from(value)),
+            Some(("deleted", value)) => {
+                deleted = UNIX_EPOCH + Duration::from_secs(value.parse().unwrap_or(0))
+            }
+            Some(("size", value)) => size = value.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+    Some(TrashEntry {
+        id,
+        original: original?,
+        deleted,
+        size,
+        dir: dir.to_path_buf(),
+    })
+}
+
+/// Parse an age like `30m`, `12h`, `7d` or `2w` (bare numbers are days).
+pub fn parse_age(text: &str) -> Result<Duration> {
+    let text = text.trim();
+    let (digits, unit) = text.split_at(text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len()));
+    let value: u64 = digits
+        .parse()
+        .with_context(|| format!("invalid age '{}' (e.g. 7d, 12h)", text))?;
+    let secs = match unit.to_ascii_lowercase().as_str() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => bail!("invalid age '{}' (units: s, m, h, d, w)", text),
+    };
+    Ok(Duration::from_secs(value.saturating_mul(secs)))
+}
+
+/// Parse a size like `500M`, `2G` or `1048576`.
+pub fn parse_size(text: &str) -> Result<u64> {
+    let text = text.trim();
+    let (digits, unit) = text.split_at(text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len()));
+    let value: u64 = digits
+        .parse()
+        .with_context(|| format!("invalid size '{}' (e.g. 500M, 2G)", text))?;
+    let multiplier: u64 = match unit.trim_end_matches(['b', 'B']).to_ascii_uppercase().as_str() {
+        "" => 1,
+        "K" => 1024,
+        "M" => 1024 * 1024,
+        "G" => 1024 * 1024 * 1024,
+        "T" => 1024 * 1024 * 1024 * 1024,
+        _ => bail!("invalid size '{}' (units: K, M, G, T)", text),
+    };
+    Ok(value.saturating_mul(multiplier))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str, max_size: u64) -> (PathBuf, TrashStore) {
+        let base = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&base);
+        fs::create_dir_all(&base).unwrap();
+        let store = TrashStore::with_root(base.join("trash"), max_size);
+        (base, store)
+    }
+
+    #[test]
+    fn test_put_list_restore() {
+        let (base, store) = temp_store("titanbash_trash_test", DEFAULT_MAX_SIZE);
+        let file = base.join("notes.txt");
+        fs::write(&file, b"keep me").unwrap();
+        let dir = base.join("project");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src").join("main.rs"), b"fn main() {}").unwrap();
+
+        let first = store.put(&file).unwrap();
+        let second = store.put(&dir).unwrap();
+        assert!(!file.exists() && !dir.exists());
+        assert_eq!(first.size, 7);
+        assert_ne!(first.id, second.id);
+
+        let listed = store.list().unwrap();
+        assert_eq!(listed.len(), 2);
+        assert!(listed.iter().any(|e| e.original == dir));
+
+        assert_eq!(store.restore(second.id).unwrap(), dir);
+        assert!(dir.join("src").join("main.rs").is_file());
+        fs::write(&file, b"new").unwrap();
+        assert!(store.restore(first.id).is_err());
+        assert_eq!(store.list().unwrap().len(), 1);
+
+        assert_eq!(store.purge(None).unwrap(), (1, 7));
+        assert!(store.list().unwrap().is_empty());
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_items_named_info_round_trip() {
+        let (base, store) = temp_store("titanbash_trash_info_test", DEFAULT_MAX_SIZE);
+        let file = base.join("info");
+        fs::write(&file, b"real contents").unwrap();
+        let entry = store.put(&file).unwrap();
+        assert_eq!(store.restore(entry.id).unwrap(), file);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "real contents");
+
+        fs::remove_file(&file).unwrap();
+        fs::create_dir_all(file.join("inner")).unwrap();
+        let entry = store.put(&file).unwrap();
+        assert_eq!(store.list().unwrap().len(), 1);
+        store.restore(entry.id).unwrap();
+        assert!(file.join("inner").is_dir());
+        assert!(store.list().unwrap().is_empty());
+
+        // Entries from the old layout (item next to `info`) still restore.
+        let legacy = store.root().join("9");
+        fs::create_dir_all(&legacy).unwrap();
+        fs::write(legacy.join("old.txt"), b"old").unwrap();
+        fs::write(legacy.join(INFO_FILE), format!("path={}\ndeleted=0\nsize=3\n", base.join("old.txt").display())).unwrap();
+        store.restore(9).unwrap();
+        assert_eq!(fs::read_to_string(base.join("old.txt")).unwrap(), "old");
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_cap_evicts_oldest() {
+        let (base, store) = temp_store("titanbash_trash_cap_test", 10);
+        for (name, data) in [("a", "aaaaaa"), ("b", "bbbbbb")] {
+            let file = base.join(name);
+            fs::write(&file, data).unwrap();
+            store.put(&file).unwrap();
+        }
+        let listed = store.list().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].original, base.join("b"));
+
+        let big = base.join("big");
+        fs::write(&big, vec![0u8; 11]).unwrap();
+        assert!(store.put(&big).is_err());
+        assert!(big.exists());
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_parse_age_and_size() {
+        assert_eq!(parse_age("7d").unwrap(), Duration::from_secs(7 * 86400));
+        assert_eq!(parse_age("12h").unwrap(), Duration::from_secs(12 * 3600));
+        assert!(parse_age("7y").is_err());
+        assert_eq!(parse_size("2G").unwrap(), 2 * 1024 * 1024 * 1024);
+        assert_eq!(parse_size("500mb").unwrap(), 500 * 1024 * 1024);
+        assert_eq!(parse_size("42").unwrap(), 42);
+    }
+}
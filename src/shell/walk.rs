@@ -0,0 +1,496 @@
+//! Directory walking for `tree`, `du` and `ff`.
+//!
+//! Reparse points that redirect to another path (symlinks, junctions — what
+//! `FileType::is_symlink` reports on Windows) are never descended into, so junction loops such
+//! as `Application Data -> .` cannot recurse forever; `tree` shows where they point and flags
+//! the ones that lead back into an ancestor. Top-level subtrees are walked on worker threads
+//! and written in order as soon as each one is finished, so output streams through pipes.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use anyhow::Result;
+use colored::Colorize;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+use super::fsutil::{file_attributes, ATTR_HIDDEN};
+
+pub struct Entry {
+    pub name: String,
+    pub path: PathBuf,
+    /// Metadata of the entry itself (links are not followed)
+    pub meta: fs::Metadata,
+    attrs: u32,
+}
+
+impl Entry {
+    fn new(name: String, path: PathBuf) -> io::Result<Self> {
+        let meta = fs::symlink_metadata(&path)?;
+        let attrs = file_attributes(&meta);
+        Ok(Self { name, path, meta, attrs })
+    }
+
+    pub fn is_link(&self) -> bool {
+        self.meta.file_type().is_symlink()
+    }
+
+    /// A real directory we may descend into.
+    pub fn is_dir(&self) -> bool {
+        !self.is_link() && self.meta.is_dir()
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.name.starts_with('.') || self.attrs & ATTR_HIDDEN != 0
+    }
+}
+
+/// Entries of `dir`, sorted by name.
+pub fn read_entries(dir: &Path) -> io::Result<Vec<Entry>> {
+    let mut entries: Vec<Entry> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .filter_map(|e| Entry::new(e.file_name().to_string_lossy().to_string(), e.path()).ok())
+        .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+/// A link whose target contains `dir` (the directory holding the link) would loop if followed.
+pub fn is_link_cycle(link: &Path, dir: &Path) -> bool {
+    match (fs::canonicalize(link), fs::canonicalize(dir)) {
+        (Ok(target), Ok(dir)) => dir.starts_with(&target),
+        _ => false,
+    }
+}
+
+pub(crate) fn child_label(label: &str, name: &str) -> String {
+    format!("{}/{}", label.trim_end_matches(['/', '\\']), name)
+}
+
+/// `.gitignore` / `.ignore` rules in effect for one directory, innermost last.
+#[derive(Clone, Default)]
+pub struct IgnoreRules {
+    matchers: Vec<Arc<Gitignore>>,
+}
+
+impl IgnoreRules {
+    /// Rules for the entries of `dir`, including those of its ancestors up to the repository root.
+    pub fn for_dir(dir: &Path) -> Self {
+        let mut chain: Vec<&Path> = Vec::new();
+        for ancestor in dir.ancestors() {
+            chain.push(ancestor);
+            if ancestor.join(".git").exists() {
+                break;
+            }
+        }
+        // Outside a repository only the directory's own files apply.
+        if !chain.last().is_some_and(|top| top.join(".git").exists()) {
+            chain.truncate(1);
+        }
+        chain
+            .iter()
+            .rev()
+            .fold(Self::default(), |rules, ancestor| rules.enter(ancestor))
+    }
+
+    /// The parent rules plus `dir/.gitignore` and `dir/.ignore` (which wins over `.gitignore`).
+    pub fn enter(&self, dir: &Path) -> Self {
+        let mut rules = self.clone();
+        for name in [".gitignore", ".ignore"] {
+            let file = dir.join(name);
+            if !file.is_file() {
+                continue;
+            }
+            let mut builder = GitignoreBuilder::new(dir);
+            let _ = builder.add(&file);
+            if let Ok(matcher) = builder.build() {
+                rules.matchers.push(Arc::new(matcher));
+            }
+        }
+        rules
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if is_dir && path.file_name().is_some_and(|n| n == ".git") {
+            return true;
+        }
+        for matcher in self.matchers.iter().rev() {
+            match matcher.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
+
+/// Run `job` over `items` on worker threads, handing results to `sink` in input order as soon
+/// as they are ready. Stops taking new items once `sink` fails or Ctrl+C is seen.
+pub fn for_each_ordered<T, R, J, S>(items: &[T], job: J, mut sink: S) -> Result<()>
+where
+    T: Sync,
+    R: Send,
+    J: Fn(&T) -> R + Sync,
+    S: FnMut(R) -> Result<()>,
+{
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .min(items.len());
+    if workers <= 1 {
+        for item in items {
+            if crate::interrupt::seen() {
+                break;
+            }
+            sink(job(item))?;
+        }
+        return Ok(());
+    }
+
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    thread::scope(|scope| {
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (next, stop, job) = (&next, &stop, &job);
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) && !crate::interrupt::seen() {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(idx) else { break };
+                    if tx.send((idx, job(item))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        let mut pending = BTreeMap::new();
+        let mut expected = 0usize;
+        let mut result = Ok(());
+        for (idx, value) in rx {
+            pending.insert(idx, value);
+            while let Some(value) = pending.remove(&expected) {
+                expected += 1;
+                if result.is_ok() {
+                    result = sink(value);
+                    if result.is_err() {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                }
+            }
+        }
+        result
+    })
+}
+
+pub enum DuEvent {
+    /// Total apparent size of a directory (or a file given on the command line)
+    Size { size: u64, label: String },
+    Error(String),
+}
+
+/// Walk `root`, reporting every directory down to `max_depth` (post-order) and returning the total.
+pub fn disk_usage(
+    root: &Path,
+    label: &str,
+    max_depth: Option<usize>,
+    mut sink: impl FnMut(DuEvent) -> Result<()>,
+) -> Result<u64> {
+    let meta = fs::symlink_metadata(root)?;
+    if meta.file_type().is_symlink() || !meta.is_dir() {
+        sink(DuEvent::Size {
+            size: meta.len(),
+            label: label.to_string(),
+        })?;
+        return Ok(meta.len());
+    }
+
+    let entries = match read_entries(root) {
+        Ok(entries) => entries,
+        Err(e) => {
+            sink(DuEvent::Error(format!("cannot read directory '{}': {}", label, e)))?;
+            Vec::new()
+        }
+    };
+    let (dirs, files): (Vec<Entry>, Vec<Entry>) = entries.into_iter().partition(Entry::is_dir);
+    let mut total: u64 = files.iter().map(|f| f.meta.len()).sum();
+
+    for_each_ordered(
+        &dirs,
+        |entry| {
+            let mut events = Vec::new();
+            let size = du_dir(&entry.path, &child_label(label, &entry.name), 1, max_depth, &mut events);
+            (size, events)
+        },
+        |(size, events)| {
+            total += size;
+            for event in events {
+                sink(event)?;
+            }
+            Ok(())
+        },
+    )?;
+
+    sink(DuEvent::Size {
+        size: total,
+        label: label.to_string(),
+    })?;
+    Ok(total)
+}
+
+fn du_dir(dir: &Path, label: &str, depth: usize, max_depth: Option<usize>, events: &mut Vec<DuEvent>) -> u64 {
+    let entries = match read_entries(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            events.push(DuEvent::Error(format!("cannot read directory '{}': {}", label, e)));
+            return 0;
+        }
+    };
+
+    let mut total = 0;
+    for entry in entries {
+        if crate::interrupt::seen() {
+            break;
+        }
+        if entry.is_dir() {
+            total += du_dir(&entry.path, &child_label(label, &entry.name), depth + 1, max_depth, events);
+        } else {
+            total += entry.meta.len();
+        }
+    }
+    if max_depth.is_none_or(|max| depth <= max) {
+        events.push(DuEvent::Size {
+            size: total,
+            label: label.to_string(),
+        });
+    }
+    total
+}
+
+pub struct TreeOptions {
+    pub max_depth: Option<usize>,
+    pub all: bool,
+    pub dirs_only: bool,
+    pub gitignore: bool,
+    pub color: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TreeCounts {
+    pub dirs: usize,
+    pub files: usize,
+}
+
+/// Draw `root` as a tree (without the trailing summary line).
+pub fn write_tree(root: &Path, label: &str, opts: &TreeOptions, out: &mut dyn Write) -> Result<TreeCounts> {
+    let rules = if opts.gitignore {
+        IgnoreRules::for_dir(root)
+    } else {
+        IgnoreRules::default()
+    };
+    writeln!(out, "{}", paint(label, true, false, opts))?;
+
+    let children = match visible_children(root, &rules, opts) {
+        Ok(children) => children,
+        Err(e) => {
+            writeln!(out, "[error opening dir: {}]", e)?;
+            return Ok(TreeCounts::default());
+        }
+    };
+
+    let mut counts = TreeCounts::default();
+    let last = children.len().saturating_sub(1);
+    let items: Vec<(usize, &Entry)> = children.iter().enumerate().collect();
+    for_each_ordered(
+        &items,
+        |(idx, entry)| {
+            let mut buf = Vec::new();
+            let mut counts = TreeCounts::default();
+            let _ = render_entry(entry, "", *idx == last, 1, &rules, opts, &mut buf, &mut counts);
+            (buf, counts)
+        },
+        |(buf, sub)| {
+            out.write_all(&buf)?;
+            counts.dirs += sub.dirs;
+            counts.files += sub.files;
+            Ok(())
+        },
+    )?;
+    Ok(counts)
+}
+
+fn visible_children(dir: &Path, rules: &IgnoreRules, opts: &TreeOptions) -> io::Result<Vec<Entry>> {
+    Ok(read_entries(dir)?
+        .into_iter()
+        .filter(|e| opts.all || !e.is_hidden())
+        .filter(|e| !opts.dirs_only || e.path.is_dir())
+        .filter(|e| !opts.gitignore || !rules.is_ignored(&e.path, e.meta.is_dir()))
+        .collect())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_entry(
+    entry: &Entry,
+    prefix: &str,
+    is_last: bool,
+    depth: usize,
+    rules: &IgnoreRules,
+    opts: &TreeOptions,
+    out: &mut Vec<u8>,
+    counts: &mut TreeCounts,
+) -> io::Result<()> {
+    let connector = if is_last { "└── " } else { "├── " };
+    let is_dir = entry.path.is_dir();
+    let mut line = format!("{}{}{}", prefix, connector, paint(&entry.name, is_dir, entry.is_link(), opts));
+    if is_dir {
+        counts.dirs += 1;
+    } else {
+        counts.files += 1;
+    }
+
+    if entry.is_link() {
+        if let Ok(target) = fs::read_link(&entry.path) {
+            line.push_str(&format!(" -> {}", target.display()));
+        }
+        let parent = entry.path.parent().unwrap_or(Path::new("."));
+        if is_dir && is_link_cycle(&entry.path, parent) {
+            line.push_str("  [recursive, not followed]");
+        }
+        return writeln!(out, "{}", line);
+    }
+
+    if !entry.is_dir() || opts.max_depth.is_some_and(|max| depth >= max) || crate::interrupt::seen() {
+        return writeln!(out, "{}", line);
+    }
+
+    let rules = if opts.gitignore { rules.enter(&entry.path) } else { rules.clone() };
+    let children = match visible_children(&entry.path, &rules, opts) {
+        Ok(children) => children,
+        Err(_) => return writeln!(out, "{}  [error opening dir]", line),
+    };
+    writeln!(out, "{}", line)?;
+
+    let child_prefix = format!("{}{}", prefix, if is_last { "    " } else { "│   " });
+    let last = children.len().saturating_sub(1);
+    for (idx, child) in children.iter().enumerate() {
+        render_entry(child, &child_prefix, idx == last, depth + 1, &rules, opts, out, counts)?;
+    }
+    Ok(())
+}
+
+fn paint(name: &str, is_dir: bool, is_link: bool, opts: &TreeOptions) -> String {
+    if !opts.color {
+        name.to_string()
+    } else if is_link {
+        name.cyan().bold().to_string()
+    } else if is_dir {
+        name.blue().bold().to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// `3 directories, 1 file`
+pub fn tree_summary(counts: TreeCounts, dirs_only: bool) -> String {
+    let plural = |n: usize, one: &str, many: &str| format!("{} {}", n, if n == 1 { one } else { many });
+    let dirs = plural(counts.dirs, "directory", "directories");
+    if dirs_only {
+        dirs
+    } else {
+        format!("{}, {}", dirs, plural(counts.files, "file", "files"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src/deep")).unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("src/deep/mod.rs"), "x").unwrap();
+        fs::write(root.join("target/debug/app"), vec![0u8; 2048]).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        root
+    }
+
+    #[test]
+    fn test_tree_depth_hidden_and_gitignore() {
+        let root = fixture("titanbash_walk_tree");
+        let mut opts = TreeOptions {
+            max_depth: None,
+            all: false,
+            dirs_only: false,
+            gitignore: false,
+            color: false,
+        };
+        let render = |opts: &TreeOptions| {
+            let mut out = Vec::new();
+            let counts = write_tree(&root, ".", opts, &mut out).unwrap();
+            (String::from_utf8(out).unwrap(), counts)
+        };
+
+        let (text, counts) = render(&opts);
+        assert_eq!(
+            text,
+            ".\n├── src\n│   ├── deep\n│   │   └── mod.rs\n│   └── main.rs\n└── target\n    └── debug\n        └── app\n"
+        );
+        assert_eq!(tree_summary(counts, false), "4 directories, 3 files");
+
+        opts.gitignore = true;
+        opts.max_depth = Some(1);
+        assert_eq!(render(&opts).0, ".\n└── src\n");
+
+        opts.gitignore = false;
+        opts.all = true;
+        opts.dirs_only = true;
+        assert_eq!(render(&opts).0, ".\n├── src\n└── target\n");
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_disk_usage_depth() {
+        let root = fixture("titanbash_walk_du");
+        let mut lines = Vec::new();
+        let total = disk_usage(&root, "r", Some(1), |event| {
+            if let DuEvent::Size { size, label } = event {
+                lines.push(format!("{} {}", size, label));
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(total, 13 + 1 + 2048 + 8);
+        assert_eq!(lines, vec!["14 r/src", "2048 r/target", "2070 r"]);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_for_each_ordered_keeps_input_order() {
+        let items: Vec<u64> = (0..50).collect();
+        let mut seen = Vec::new();
+        for_each_ordered(
+            &items,
+            |n| {
+                thread::sleep(std::time::Duration::from_millis(50 - n));
+                n * 2
+            },
+            |n| {
+                seen.push(n);
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(seen, items.iter().map(|n| n * 2).collect::<Vec<_>>());
+    }
+}
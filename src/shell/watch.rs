@@ -0,0 +1,198 @@
+//! `watch`: re-run a command line and redraw its output in place.
+//!
+//! The command goes through the AST executor with stdout and stderr captured, so builtins,
+//! aliases and pipelines behave exactly as at the prompt. Frames are drawn on the alternate
+//! screen; `-d` shows characters that changed since the previous run in reverse video.
+//! Ctrl+C (via [`crate::interrupt`]) ends the loop, as does a change in output with `-g`.
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use unicode_width::UnicodeWidthChar;
+
+use super::encoding;
+use super::executor;
+use super::parser;
+use super::Shell;
+
+pub struct WatchOptions {
+    pub interval: Duration,
+    pub differences: bool,
+    pub exit_on_change: bool,
+}
+
+/// Run `cmdline` until Ctrl+C (130) or, with `-g`, until its output changes (0).
+pub fn run(shell: &mut Shell, cmdline: &str, opts: &WatchOptions, mut out: &mut dyn Write) -> Result<i32> {
+    let ast = parser::parse(cmdline)?;
+    queue!(&mut out, EnterAlternateScreen, Hide)?;
+    out.flush()?;
+    let result = watch_loop(shell, cmdline, &ast, opts, &mut *out);
+    queue!(&mut out, Show, LeaveAlternateScreen)?;
+    out.flush()?;
+
+    // `-g` leaves the screen that triggered the exit visible in the scrollback.
+    if let Ok((0, lines)) = &result {
+        for line in lines {
+            writeln!(out, "{}", line)?;
+        }
+    }
+    result.map(|(code, _)| code)
+}
+
+fn watch_loop(
+    shell: &mut Shell,
+    cmdline: &str,
+    ast: &parser::Command,
+    opts: &WatchOptions,
+    out: &mut dyn Write,
+) -> Result<(i32, Vec<String>)> {
+    let mut previous: Option<Vec<String>> = None;
+    loop {
+        let (_, captured) = executor::execute_ast_captured(shell, ast)?;
+        if crate::interrupt::seen() {
+            return Ok((130, Vec::new()));
+        }
+        let lines = output_lines(&captured);
+
+        let changed = previous.as_ref().is_some_and(|prev| *prev != lines);
+        let header = format!("Every {:.1}s: {}", opts.interval.as_secs_f64(), cmdline);
+        let stamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let size = terminal::size().unwrap_or((80, 24));
+        let highlight = if opts.differences { previous.as_deref() } else { None };
+        render_frame(out, &header, &stamp, &lines, highlight, size)?;
+
+        if opts.exit_on_change && changed {
+            return Ok((0, lines));
+        }
+        previous = Some(lines);
+
+        let deadline = Instant::now() + opts.interval;
+        while Instant::now() < deadline {
+            if crate::interrupt::seen() {
+                return Ok((130, Vec::new()));
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+/// Decode captured output (UTF-8/UTF-16/legacy code page) into display lines.
+fn output_lines(captured: &[u8]) -> Vec<String> {
+    match encoding::text_reader(captured, None) {
+        Ok(reader) => io::BufRead::lines(reader).map_while(Result::ok).map(|l| sanitize(&l)).collect(),
+        Err(_) => String::from_utf8_lossy(captured).lines().map(sanitize).collect(),
+    }
+}
+
+/// Drop ANSI escape sequences and control characters, expand tabs to 8 columns.
+fn sanitize(line: &str) -> String {
+    let mut clean = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\x1b' => {
+                if chars.peek() == Some(&'[') {
+                    chars.next();
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+            }
+            '\t' => {
+                let col = clean.chars().count();
+                clean.push_str(&" ".repeat(8 - col % 8));
+            }
+            c if c.is_control() => {}
+            c => clean.push(c),
+        }
+    }
+    clean
+}
+
+/// Draw one frame: header + timestamp, a blank line, then as much output as fits.
+pub fn render_frame(
+    mut out: &mut dyn Write,
+    header: &str,
+    stamp: &str,
+    lines: &[String],
+    previous: Option<&[String]>,
+    (width, height): (u16, u16),
+) -> io::Result<()> {
+    let width = width as usize;
+    queue!(&mut out, MoveTo(0, 0), Clear(ClearType::All))?;
+
+    let stamp_width = stamp.chars().count();
+    let header = fit(header, width.saturating_sub(stamp_width + 1));
+    let gap = width.saturating_sub(header.chars().count() + stamp_width).max(1);
+    queue!(&mut out, Print(&header), Print(" ".repeat(gap)), Print(stamp))?;
+
+    for (row, line) in lines.iter().take((height as usize).saturating_sub(2)).enumerate() {
+        queue!(&mut out, MoveTo(0, row as u16 + 2))?;
+        let line = fit(line, width);
+        match previous {
+            Some(prev) => {
+                let old: Vec<char> = prev.get(row).map(|l| l.chars().collect()).unwrap_or_default();
+                for (col, ch) in line.chars().enumerate() {
+                    if old.get(col) != Some(&ch) {
+                        queue!(&mut out, SetAttribute(Attribute::Reverse), Print(ch), SetAttribute(Attribute::NoReverse))?;
+                    } else {
+                        queue!(&mut out, Print(ch))?;
+                    }
+                }
+            }
+            None => queue!(&mut out, Print(&line))?,
+        }
+    }
+    out.flush()
+}
+
+/// The longest prefix of `text` that fits in `width` terminal columns.
+fn fit(text: &str, width: usize) -> String {
+    let mut used = 0;
+    text.chars()
+        .take_while(|c| {
+            used += c.width().unwrap_or(0);
+            used <= width
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_strips_escapes_and_expands_tabs() {
+        assert_eq!(sanitize("\x1b[1;32mok\x1b[0m\tdone\r"), "ok      done");
+        assert_eq!(fit("héllo wörld", 5), "héllo");
+        assert_eq!(fit("日本語", 4), "日本");
+    }
+
+    #[test]
+    fn test_render_frame_highlights_changed_characters() {
+        let mut out = Vec::new();
+        let lines = vec!["count: 42".to_string(), "new".to_string()];
+        let previous = vec!["count: 41".to_string()];
+        render_frame(&mut out, "Every 2.0s: jobs", "12:00", &lines, Some(&previous), (40, 10)).unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        let reverse = |c: char| format!("\x1b[7m{}\x1b[27m", c);
+        assert!(text.contains("Every 2.0s: jobs"));
+        assert!(text.contains(&format!("count: 4{}", reverse('2'))));
+        assert!(text.contains(&format!("{}{}{}", reverse('n'), reverse('e'), reverse('w'))));
+
+        let mut plain = Vec::new();
+        render_frame(&mut plain, "h", "t", &lines, None, (40, 3)).unwrap();
+        let plain = String::from_utf8(plain).unwrap();
+        assert!(plain.contains("count: 42") && !plain.contains("new") && !plain.contains("\x1b[7m"));
+    }
+}
@@ -1,12 +1,19 @@
 //! Task management - background jobs
 
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, OnceLock};
 use std::thread::{self, JoinHandle};
 use std::time::Instant;
-use std::process::{Command, Stdio};
-use anyhow::{bail, Context, Result};
+use std::process::{Child, Command};
+#[cfg(windows)]
+use std::process::Stdio;
+use anyhow::{bail, Result};
+
+pub mod signal;
+
+use signal::Signal;
 
 pub type TaskId = u32;
 
@@ -133,8 +140,7 @@ pub fn init_kill_on_close_job_best_effort() {
 fn create_kill_on_close_job_and_assign_self() -> Result<ProcessJobHandle> {
     use windows_sys::Win32::Foundation::{CloseHandle, GetLastError};
     use windows_sys::Win32::System::JobObjects::{
-        AssignProcessToJobObject, CreateJobObjectW, SetInformationJobObject,
-        JobObjectExtendedLimitInformation, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        AssignProcessToJobObject, CreateJobObjectW, JOB_OBJECT_LIMIT_BREAKAWAY_OK,
         JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
     };
     use windows_sys::Win32::System::Threading::GetCurrentProcess;
@@ -145,9 +151,33 @@ fn create_kill_on_close_job_and_assign_self() -> Result<ProcessJobHandle> {
             bail!("CreateJobObjectW failed (err={})", GetLastError());
         }
 
-        let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
-        info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        // Breakaway lets background jobs move to their own job objects (see `spawn_child`).
+        if let Err(e) = set_job_limits(job, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE | JOB_OBJECT_LIMIT_BREAKAWAY_OK) {
+            let _ = CloseHandle(job);
+            return Err(e);
+        }
 
+        let ok = AssignProcessToJobObject(job, GetCurrentProcess());
+        if ok == 0 {
+            let err = GetLastError();
+            let _ = CloseHandle(job);
+            bail!("AssignProcessToJobObject failed (err={})", err);
+        }
+
+        Ok(ProcessJobHandle(job))
+    }
+}
+
+#[cfg(windows)]
+fn set_job_limits(job: windows_sys::Win32::Foundation::HANDLE, flags: u32) -> Result<()> {
+    use windows_sys::Win32::Foundation::GetLastError;
+    use windows_sys::Win32::System::JobObjects::{
+        JobObjectExtendedLimitInformation, SetInformationJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+    };
+
+    unsafe {
+        let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+        info.BasicLimitInformation.LimitFlags = flags;
         let ok = SetInformationJobObject(
             job,
             JobObjectExtendedLimitInformation,
@@ -155,19 +185,172 @@ fn create_kill_on_close_job_and_assign_self() -> Result<ProcessJobHandle> {
             std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
         );
         if ok == 0 {
-            let err = GetLastError();
-            let _ = CloseHandle(job);
-            bail!("SetInformationJobObject failed (err={})", err);
+            bail!("SetInformationJobObject failed (err={})", GetLastError());
         }
+        Ok(())
+    }
+}
 
-        let ok = AssignProcessToJobObject(job, GetCurrentProcess());
-        if ok == 0 {
-            let err = GetLastError();
-            let _ = CloseHandle(job);
-            bail!("AssignProcessToJobObject failed (err={})", err);
+/// A background task's own job object. With `KILL_ON_JOB_CLOSE` set, its processes die with
+/// titanbash even when it crashes (the OS closes the handle); `disown` clears the limit.
+#[cfg(windows)]
+struct TaskJob(windows_sys::Win32::Foundation::HANDLE);
+
+#[cfg(windows)]
+unsafe impl Send for TaskJob {}
+#[cfg(windows)]
+unsafe impl Sync for TaskJob {}
+
+#[cfg(windows)]
+impl TaskJob {
+    fn new() -> Result<Self> {
+        use windows_sys::Win32::Foundation::GetLastError;
+        use windows_sys::Win32::System::JobObjects::CreateJobObjectW;
+
+        let job = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+        if job.is_null() {
+            bail!("CreateJobObjectW failed (err={})", unsafe { GetLastError() });
         }
+        let job = TaskJob(job);
+        job.set_kill_on_close(true)?;
+        Ok(job)
+    }
 
-        Ok(ProcessJobHandle(job))
+    fn set_kill_on_close(&self, kill: bool) -> Result<()> {
+        use windows_sys::Win32::System::JobObjects::JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        set_job_limits(self.0, if kill { JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE } else { 0 })
+    }
+
+    fn assign(&self, child: &Child) -> std::io::Result<()> {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::System::JobObjects::AssignProcessToJobObject;
+
+        if unsafe { AssignProcessToJobObject(self.0, child.as_raw_handle() as _) } == 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for TaskJob {
+    fn drop(&mut self) {
+        unsafe {
+            windows_sys::Win32::Foundation::CloseHandle(self.0);
+        }
+    }
+}
+
+/// What [`spawn_child`] needs to know on a background task's thread.
+#[derive(Clone)]
+struct JobContext {
+    /// `nohup`: leave titanbash's job object and console
+    detached: bool,
+    #[cfg(windows)]
+    job: Option<Arc<TaskJob>>,
+}
+
+thread_local! {
+    /// Set on a background task's thread.
+    static JOB_CONTEXT: RefCell<Option<JobContext>> = const { RefCell::new(None) };
+}
+
+/// Spawn `cmd`, applying the background-job policy when called from a task thread.
+///
+/// Background processes get their own process group, so Ctrl+C at the prompt does not reach them
+/// and `kill` can signal the group. On Windows, ordinary jobs move from titanbash's job object to
+/// the task's own kill-on-close [`TaskJob`], so they still die if titanbash crashes but `disown`
+/// can release them; anything they start before being assigned escapes it. `nohup` jobs leave
+/// the job object and the console entirely. If the breakaway is refused (titanbash running
+/// inside a job that forbids it), the process stays in titanbash's job object.
+pub fn spawn_child(cmd: &mut Command) -> std::io::Result<Child> {
+    let Some(context) = JOB_CONTEXT.with(|c| c.borrow().clone()) else {
+        return cmd.spawn();
+    };
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        const CREATE_BREAKAWAY_FROM_JOB: u32 = 0x0100_0000;
+
+        let flags = if context.detached { DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP } else { CREATE_NEW_PROCESS_GROUP };
+        let in_job = PROCESS_JOB.get().is_some_and(|job| job.is_some());
+        let mut spawned = None;
+        if in_job && (context.detached || context.job.is_some()) {
+            cmd.creation_flags(flags | CREATE_BREAKAWAY_FROM_JOB);
+            match cmd.spawn() {
+                Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {}
+                result => spawned = Some(result?),
+            }
+        }
+        let child = match spawned {
+            Some(child) => child,
+            None => {
+                cmd.creation_flags(flags);
+                cmd.spawn()?
+            }
+        };
+        if let Some(job) = &context.job {
+            // Best effort: the PID registry and `TaskManager::drop` still cover a normal exit.
+            let _ = job.assign(&child);
+        }
+        Ok(child)
+    }
+
+    #[cfg(not(windows))]
+    {
+        use std::os::unix::process::CommandExt;
+        let _ = context.detached;
+        cmd.process_group(0);
+        cmd.spawn()
+    }
+}
+
+/// Where a task body publishes the PID of the process it runs.
+#[derive(Clone)]
+pub struct TaskPid {
+    pid: Arc<Mutex<Option<u32>>>,
+    /// Left running when titanbash exits (`nohup`, `disown -h`)
+    detached: Arc<AtomicBool>,
+}
+
+impl TaskPid {
+    fn new(detached: bool) -> Self {
+        Self {
+            pid: Arc::new(Mutex::new(None)),
+            detached: Arc::new(AtomicBool::new(detached)),
+        }
+    }
+
+    /// Record the task's process and, unless the task is detached, [`register_pid`] it. Both
+    /// happen under the lock [`TaskManager::keep_on_exit`] takes, so a `disown` racing the spawn
+    /// cannot leave the PID registered.
+    pub fn set(&self, pid: u32) {
+        let mut slot = self.pid.lock().unwrap_or_else(|p| p.into_inner());
+        *slot = Some(pid);
+        if !self.detached.load(Ordering::SeqCst) {
+            register_pid(pid);
+        }
+    }
+
+    fn get(&self) -> Option<u32> {
+        *self.pid.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    #[cfg(windows)]
+    fn is_detached(&self) -> bool {
+        self.detached.load(Ordering::SeqCst)
+    }
+
+    /// Mark the task detached and take its PID out of the registry.
+    fn detach(&self) {
+        self.detached.store(true, Ordering::SeqCst);
+        if let Some(pid) = *self.pid.lock().unwrap_or_else(|p| p.into_inner()) {
+            unregister_pid(pid);
+        }
     }
 }
 
@@ -176,9 +359,14 @@ struct Task {
     command: String,
     status: Arc<Mutex<TaskStatus>>,
     output: Arc<Mutex<String>>,
-    pid: Arc<Mutex<Option<u32>>>,
+    pid: TaskPid,
     started: Instant,
     handle: Option<JoinHandle<()>>,
+    /// Shares titanbash's console (not `nohup`), so console events can reach it
+    console: bool,
+    /// The task's own kill-on-close job object (not for `nohup`)
+    #[cfg(windows)]
+    job: Option<Arc<TaskJob>>,
 }
 
 /// Manages background tasks
@@ -198,20 +386,38 @@ impl TaskManager {
     /// Spawn a new background task
     pub fn spawn<F>(&mut self, cmd: &str, f: F) -> Result<TaskId>
     where
-        F: FnOnce(Arc<Mutex<Option<u32>>>) -> Result<(i32, String)> + Send + 'static,
+        F: FnOnce(TaskPid) -> Result<(i32, String)> + Send + 'static,
+    {
+        self.spawn_with(cmd, false, f)
+    }
+
+    /// Spawn a background task; `detached` tasks (`nohup`) are left running when titanbash exits.
+    pub fn spawn_with<F>(&mut self, cmd: &str, detached: bool, f: F) -> Result<TaskId>
+    where
+        F: FnOnce(TaskPid) -> Result<(i32, String)> + Send + 'static,
     {
         let id = self.next_id;
         self.next_id += 1;
 
         let status = Arc::new(Mutex::new(TaskStatus::Running));
         let output = Arc::new(Mutex::new(String::new()));
-        let pid = Arc::new(Mutex::new(None));
+        let pid = TaskPid::new(detached);
 
         let status_clone = status.clone();
         let output_clone = output.clone();
         let pid_clone = pid.clone();
 
+        // Without a job object of its own the task's processes stay in titanbash's.
+        #[cfg(windows)]
+        let job = if detached { None } else { TaskJob::new().ok().map(Arc::new) };
+        let context = JobContext {
+            detached,
+            #[cfg(windows)]
+            job: job.clone(),
+        };
+
         let handle = thread::spawn(move || {
+            JOB_CONTEXT.with(|slot| *slot.borrow_mut() = Some(context));
             match f(pid_clone) {
                 Ok((code, out)) => {
                     *output_clone.lock().unwrap() = out;
@@ -230,6 +436,9 @@ impl TaskManager {
             pid,
             started: Instant::now(),
             handle: Some(handle),
+            console: !detached,
+            #[cfg(windows)]
+            job,
         });
 
         Ok(id)
@@ -265,33 +474,98 @@ impl TaskManager {
     }
 
     pub fn pid(&self, id: TaskId) -> Option<u32> {
-        self.tasks.get(&id).and_then(|t| *t.pid.lock().unwrap())
+        self.tasks.get(&id).and_then(|t| t.pid.get())
     }
 
-    pub fn kill(&mut self, id: TaskId) -> Result<()> {
-        let Some(task) = self.tasks.get(&id) else {
-            bail!("kill: {}: no such job", id);
+    /// Resolve a job spec: `%N` (or `N`), `%%`/`%+` (current job), `%-` (previous job),
+    /// `%name` (command starts with `name`) or `%?text` (command contains `text`).
+    pub fn job_spec(&self, spec: &str) -> Result<TaskId> {
+        let ids: Vec<TaskId> = self.list().into_iter().map(|(id, _, _)| id).collect();
+        let body = spec.strip_prefix('%').unwrap_or(spec);
+        if let Ok(id) = body.parse::<TaskId>() {
+            if !self.tasks.contains_key(&id) {
+                bail!("{}: no such job", spec);
+            }
+            return Ok(id);
+        }
+        if !spec.starts_with('%') {
+            bail!("{}: invalid job spec", spec);
+        }
+
+        let found = match body {
+            "" | "%" | "+" => ids.last().copied(),
+            "-" => ids.iter().rev().nth(1).or(ids.last()).copied(),
+            _ => {
+                let matches: Vec<TaskId> = ids
+                    .iter()
+                    .copied()
+                    .filter(|id| {
+                        let command = &self.tasks[id].command;
+                        match body.strip_prefix('?') {
+                            Some(text) => command.contains(text),
+                            None => command.starts_with(body),
+                        }
+                    })
+                    .collect();
+                if matches.len() > 1 {
+                    bail!("{}: ambiguous job spec", spec);
+                }
+                matches.first().copied()
+            }
         };
-        let Some(pid) = *task.pid.lock().unwrap() else {
-            bail!("kill: {}: process not started yet", id);
+        found.ok_or_else(|| anyhow::anyhow!("{}: no such job", spec))
+    }
+
+    /// Forget a job without killing it; it keeps running after titanbash exits.
+    pub fn disown(&mut self, id: TaskId) -> Result<()> {
+        self.keep_on_exit(id)?;
+        self.tasks.remove(&id);
+        Ok(())
+    }
+
+    /// Keep a job in the table but leave it running when titanbash exits (`disown -h`).
+    pub fn keep_on_exit(&mut self, id: TaskId) -> Result<()> {
+        let Some(task) = self.tasks.get_mut(&id) else {
+            bail!("disown: {}: no such job", id);
         };
+        #[cfg(windows)]
+        if let Some(job) = &task.job {
+            job.set_kill_on_close(false).map_err(|e| anyhow::anyhow!("disown: {}: {}", id, e))?;
+        }
+        task.pid.detach();
+        Ok(())
+    }
 
-        let status = Command::new("taskkill")
-            .args(["/PID", &pid.to_string(), "/T", "/F"])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .with_context(|| format!("kill: failed to execute taskkill for pid {}", pid))?;
+    /// The job whose process has `pid`.
+    pub fn job_for_pid(&self, pid: u32) -> Option<TaskId> {
+        self.tasks
+            .iter()
+            .find(|(_, task)| task.pid.get() == Some(pid))
+            .map(|(&id, _)| id)
+    }
+
+    /// Send `signal` to a job's process, and its process tree / group unless `tree` is false.
+    pub fn signal(&self, id: TaskId, signal: Signal, tree: bool) -> Result<()> {
+        let Some(task) = self.tasks.get(&id) else {
+            bail!("%{}: no such job", id);
+        };
+        let Some(pid) = task.pid.get() else {
+            bail!("%{}: process not started yet", id);
+        };
 
-        if status.success() {
+        signal::send(pid, signal, tree, task.console).map_err(|e| anyhow::anyhow!("({}) - {}", pid, e))?;
+        if signal == Signal::KILL {
             unregister_pid(pid);
-            Ok(())
-        } else {
-            bail!("kill: taskkill failed (pid {})", pid)
         }
+        Ok(())
+    }
+
+    /// Kill a job's whole process tree.
+    pub fn kill(&mut self, id: TaskId) -> Result<()> {
+        self.signal(id, Signal::KILL, true)
     }
 
-    /// Best-effort termination of all running background jobs.
+    /// Best-effort termination of all running background jobs (detached ones are left alone).
     ///
     /// On Windows this uses `taskkill /T /F` (process tree kill). On other platforms this is a no-op.
     pub fn kill_all_running_best_effort(&mut self) -> usize {
@@ -307,7 +581,7 @@ impl TaskManager {
                 .iter()
                 .filter_map(|(&id, task)| {
                     let status = task.status.lock().unwrap().clone();
-                    matches!(status, TaskStatus::Running).then_some(id)
+                    (matches!(status, TaskStatus::Running) && !task.pid.is_detached()).then_some(id)
                 })
                 .collect();
 
@@ -382,13 +656,50 @@ impl Drop for TaskManager {
     }
 }
 
-#[cfg(all(test, windows))]
+#[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(windows)]
     use std::process::{Command, Stdio};
-    use std::thread;
+    #[cfg(windows)]
     use std::time::{Duration, Instant};
+    use std::sync::mpsc;
+
+    fn registered(pid: u32) -> bool {
+        pid_registry().lock().unwrap().contains(&pid)
+    }
 
+    #[test]
+    fn disown_before_the_pid_is_set_keeps_it_unregistered() {
+        let mut tasks = TaskManager::new();
+        let spawn_fake = |tasks: &mut TaskManager, fake_pid: u32| {
+            let (go, wait) = mpsc::channel::<()>();
+            let id = tasks
+                .spawn("fake", move |pid| {
+                    wait.recv().ok();
+                    pid.set(fake_pid);
+                    Ok((0, String::new()))
+                })
+                .unwrap();
+            (id, go)
+        };
+
+        let (early, go) = spawn_fake(&mut tasks, 4_000_001);
+        tasks.keep_on_exit(early).unwrap();
+        go.send(()).unwrap();
+        tasks.wait(early);
+        assert_eq!(tasks.pid(early), Some(4_000_001));
+        assert!(!registered(4_000_001));
+
+        let (late, go) = spawn_fake(&mut tasks, 4_000_002);
+        go.send(()).unwrap();
+        tasks.wait(late);
+        assert!(registered(4_000_002));
+        tasks.disown(late).unwrap();
+        assert!(!registered(4_000_002));
+    }
+
+    #[cfg(windows)]
     fn pid_exists(pid: u32) -> bool {
         let script = format!(
             "try {{ Get-Process -Id {} -ErrorAction Stop | Out-Null; exit 0 }} catch {{ exit 1 }}",
@@ -404,6 +715,7 @@ mod tests {
             .unwrap_or(false)
     }
 
+    #[cfg(windows)]
     #[test]
     fn dropping_task_manager_terminates_running_jobs() {
         let mut tasks = TaskManager::new();
@@ -417,7 +729,7 @@ mod tests {
                     .stderr(Stdio::null())
                     .spawn()?;
 
-                *pid.lock().unwrap() = Some(child.id());
+                pid.set(child.id());
                 let _ = child.wait();
                 Ok((0, String::new()))
             })
@@ -0,0 +1,227 @@
+//! Signals for `kill`: names and numbers, and delivery to a process or its process tree.
+//!
+//! Unix delivers real signals, to the whole process group when the target leads one (background
+//! jobs do, see [`super::spawn_child`]). Windows has no signals, so each maps to the nearest
+//! mechanism:
+//! - `INT`, `QUIT`, `BREAK`: a Ctrl+Break console event to the job's process group. Ctrl+C cannot
+//!   be aimed at a single group, and only background jobs on titanbash's console lead one.
+//! - `TERM`, `HUP`: a soft close (`taskkill` without `/F`, which posts `WM_CLOSE`, plus Ctrl+Break
+//!   for console jobs), then a forced kill if the process is still alive after [`TERM_GRACE`].
+//! - `KILL`: `taskkill /F`.
+//!
+//! Signal 0 only checks that the process exists. Unless `tree` is false, Windows kills the whole
+//! process tree (`/T`) and Unix signals the process group.
+
+use std::io;
+#[cfg(windows)]
+use std::time::Duration;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Signal {
+    pub name: &'static str,
+    pub number: i32,
+}
+
+const fn sig(name: &'static str, number: i32) -> Signal {
+    Signal { name, number }
+}
+
+impl Signal {
+    pub const KILL: Signal = sig("KILL", 9);
+    pub const TERM: Signal = sig("TERM", 15);
+    /// `kill -0`: no signal, only an existence check.
+    pub const NONE: Signal = sig("0", 0);
+}
+
+#[cfg(windows)]
+const SIGNALS: &[Signal] = &[
+    sig("HUP", 1),
+    sig("INT", 2),
+    sig("QUIT", 3),
+    Signal::KILL,
+    Signal::TERM,
+    sig("BREAK", 21),
+];
+
+#[cfg(not(windows))]
+const SIGNALS: &[Signal] = &[
+    sig("HUP", libc::SIGHUP),
+    sig("INT", libc::SIGINT),
+    sig("QUIT", libc::SIGQUIT),
+    sig("ABRT", libc::SIGABRT),
+    Signal::KILL,
+    sig("USR1", libc::SIGUSR1),
+    sig("SEGV", libc::SIGSEGV),
+    sig("USR2", libc::SIGUSR2),
+    sig("PIPE", libc::SIGPIPE),
+    sig("ALRM", libc::SIGALRM),
+    Signal::TERM,
+    sig("CHLD", libc::SIGCHLD),
+    sig("CONT", libc::SIGCONT),
+    sig("STOP", libc::SIGSTOP),
+    sig("TSTP", libc::SIGTSTP),
+    sig("TTIN", libc::SIGTTIN),
+    sig("TTOU", libc::SIGTTOU),
+    sig("WINCH", libc::SIGWINCH),
+];
+
+/// How long `TERM` waits after the soft close before forcing (Windows).
+#[cfg(windows)]
+pub const TERM_GRACE: Duration = Duration::from_secs(3);
+
+/// The signals this platform supports, in number order (for `kill -l`).
+pub fn all() -> &'static [Signal] {
+    SIGNALS
+}
+
+/// A signal by number or by name, with or without `SIG`, in any case: `15`, `TERM`, `sigterm`.
+pub fn parse(spec: &str) -> Option<Signal> {
+    if let Ok(number) = spec.parse::<i32>() {
+        if number == 0 {
+            return Some(Signal::NONE);
+        }
+        return SIGNALS.iter().copied().find(|s| s.number == number);
+    }
+    let upper = spec.to_ascii_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    SIGNALS.iter().copied().find(|s| s.name == name)
+}
+
+/// Deliver `signal` to `pid`. `console_group` marks a background job that leads its own process
+/// group on titanbash's console, the only kind of target a Windows console event can reach.
+#[cfg(not(windows))]
+pub fn send(pid: u32, signal: Signal, tree: bool, _console_group: bool) -> io::Result<()> {
+    let pid = libc::pid_t::try_from(pid).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    // Only a group the target leads; titanbash's own group would include the shell.
+    let leads_group = unsafe { libc::getpgid(pid) == pid && libc::getpgrp() != pid };
+    let target = if tree && leads_group { -pid } else { pid };
+    if unsafe { libc::kill(target, signal.number) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Deliver `signal` to `pid`. `console_group` marks a background job that leads its own process
+/// group on titanbash's console, the only kind of target a Windows console event can reach.
+#[cfg(windows)]
+pub fn send(pid: u32, signal: Signal, tree: bool, console_group: bool) -> io::Result<()> {
+    match signal.number {
+        0 => ProcessHandle::open(pid).map(drop),
+        2 | 3 | 21 if console_group => ctrl_break(pid),
+        2 | 3 | 21 => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("SIG{} can only be sent to background jobs on this console", signal.name),
+        )),
+        9 => taskkill(pid, tree, true),
+        _ => {
+            let process = ProcessHandle::open(pid)?;
+            // Either may fail (no window to close, no console group); the wait decides.
+            let _ = taskkill(pid, tree, false);
+            if console_group {
+                let _ = ctrl_break(pid);
+            }
+            if process.wait(TERM_GRACE) {
+                Ok(())
+            } else {
+                taskkill(pid, tree, true)
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+fn ctrl_break(pid: u32) -> io::Result<()> {
+    use windows_sys::Win32::System::Console::{GenerateConsoleCtrlEvent, CTRL_BREAK_EVENT};
+
+    if unsafe { GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid) } == 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+fn taskkill(pid: u32, tree: bool, force: bool) -> io::Result<()> {
+    use std::process::{Command, Stdio};
+
+    let mut cmd = Command::new("taskkill");
+    cmd.args(["/PID", &pid.to_string()]);
+    if tree {
+        cmd.arg("/T");
+    }
+    if force {
+        cmd.arg("/F");
+    }
+    let status = cmd.stdout(Stdio::null()).stderr(Stdio::null()).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("taskkill failed (pid {})", pid)))
+    }
+}
+
+/// A process handle that can only be waited on.
+#[cfg(windows)]
+struct ProcessHandle(windows_sys::Win32::Foundation::HANDLE);
+
+#[cfg(windows)]
+impl ProcessHandle {
+    fn open(pid: u32) -> io::Result<Self> {
+        use windows_sys::Win32::System::Threading::OpenProcess;
+        const SYNCHRONIZE: u32 = 0x0010_0000;
+
+        let handle = unsafe { OpenProcess(SYNCHRONIZE, 0, pid) };
+        if handle.is_null() {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ProcessHandle(handle))
+        }
+    }
+
+    /// Whether the process exited within `timeout`.
+    fn wait(&self, timeout: Duration) -> bool {
+        use windows_sys::Win32::Foundation::WAIT_OBJECT_0;
+        use windows_sys::Win32::System::Threading::WaitForSingleObject;
+
+        unsafe { WaitForSingleObject(self.0, timeout.as_millis() as u32) == WAIT_OBJECT_0 }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for ProcessHandle {
+    fn drop(&mut self) {
+        unsafe {
+            windows_sys::Win32::Foundation::CloseHandle(self.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_signal_specs() {
+        assert_eq!(parse("TERM"), Some(Signal::TERM));
+        assert_eq!(parse("sigkill"), Some(Signal::KILL));
+        assert_eq!(parse("9"), Some(Signal::KILL));
+        assert_eq!(parse("0"), Some(Signal::NONE));
+        assert_eq!(parse("INT").map(|s| s.number), Some(2));
+        assert_eq!(parse("bogus"), None);
+        assert_eq!(parse("99"), None);
+        assert!(all().windows(2).all(|w| w[0].number < w[1].number));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_send_signals_process_group() {
+        use std::os::unix::process::{CommandExt, ExitStatusExt};
+
+        let mut child = std::process::Command::new("sleep").arg("30").process_group(0).spawn().unwrap();
+        send(child.id(), Signal::NONE, true, false).unwrap();
+        send(child.id(), Signal::TERM, true, false).unwrap();
+        assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGTERM));
+        assert!(send(child.id(), Signal::NONE, true, false).is_err());
+    }
+}